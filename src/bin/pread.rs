@@ -1,14 +1,57 @@
-use std::{fs::File, io::IoSliceMut, os::fd::AsFd};
+use mempoll::hexdump::{Column, HexDump, Sidebar};
+use mempoll::memory::proc_memory::ProcMemory;
 
-use nix::sys::uio::preadv;
+fn usage() -> ! {
+    eprintln!("usage: pread <pid> <hex address> [length] [--column byte|word|dword|qword|float|double] [--utf16] [--watch <secs>]");
+    std::process::exit(1);
+}
 
 fn main() {
-    let path = format!("/proc/{}/mem", 3703u32);
-    println!("{path}");
-    let fd = File::open(path).unwrap();
-    let mut buff = Box::new([0u8;4096]);
-    let mut iov = [ IoSliceMut::new(buff.as_mut_slice()) ];
-    let len = preadv(fd.as_fd(), iov.as_mut_slice(), 0x5602b0994eb0usize as i64).unwrap();
-    println!("{len}");
-    println!("{:?}", buff);
-}
\ No newline at end of file
+    let mut args = std::env::args().skip(1);
+    let (Some(pid), Some(address)) = (args.next(), args.next()) else {
+        usage();
+    };
+    let pid = pid.parse::<u32>().expect("invalid pid");
+    let address = usize::from_str_radix(address.trim_start_matches("0x"), 16).expect("invalid address");
+
+    let mut len = 0x100;
+    let mut dump = HexDump::new();
+    let mut watch = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--column" => dump = dump.column(args.next().unwrap_or_else(|| usage()).parse().unwrap_or_else(|e| {
+                eprintln!("{e}");
+                usage()
+            })),
+            "--utf16" => dump = dump.sidebar(Sidebar::Utf16),
+            "--watch" => watch = Some(args.next().and_then(|s| s.parse::<f64>().ok()).unwrap_or_else(|| usage())),
+            s => len = match s.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => s.parse(),
+            }.unwrap_or_else(|_| usage()),
+        }
+    }
+    if dump.column == Column::Float || dump.column == Column::Double {
+        dump = dump.width(32);
+    }
+
+    let mut mem = ProcMemory::new(pid);
+    if let Err(e) = mem.open() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+    if let Err(e) = mem.process.maps() {
+        eprintln!("{e:?}");
+    }
+
+    let Some(secs) = watch else {
+        print!("{}", dump.render(&mem, &mem.process.maps, address, len));
+        return;
+    };
+    dump = dump.color(true);
+    loop {
+        // 清屏后重绘，变化的单元高亮
+        print!("\x1b[2J\x1b[H{}", dump.render(&mem, &mem.process.maps, address, len));
+        std::thread::sleep(std::time::Duration::from_secs_f64(secs));
+    }
+}
@@ -1,7 +1,21 @@
-use mempoll::{memory::proc_memory, process::{MapRange, MemoryType}, searcher::{self, MemorySearcher, SearchType}};
+use mempoll::{memory::proc_memory, process::{MapRange, MemoryType, Process}, searcher::{self, MemorySearcher, SearchType}};
 
 fn main() {
-    let mut proc = proc_memory::ProcMemory::new(22671);
+    let Some(arg) = std::env::args().nth(1) else {
+        eprintln!("usage: process <pid|name>");
+        std::process::exit(1);
+    };
+    let pid = match arg.parse::<u32>() {
+        Ok(pid) => pid,
+        Err(_) => match Process::by_name(&arg) {
+            Ok(Some(p)) => p.pid,
+            _ => {
+                eprintln!("process {arg} not found");
+                std::process::exit(1);
+            }
+        }
+    };
+    let mut proc = proc_memory::ProcMemory::new(pid);
     match proc.open() {
         Ok(_) => {},
         Err(e) => println!("{:#?}", e),
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use crate::memory::{MemoryError, MemoryReader, MemoryWriter};
+use crate::memory::ptrace_memory::{PtraceMemory, Trap, SI_KERNEL, TRAP_BRKPT};
+use crate::registers::{GeneralRegisters, Regs};
+
+/// 断点指令，aarch64 为 brk #0
+#[cfg(target_arch = "aarch64")]
+pub const BREAK_INSN: &[u8] = &[0x00, 0x00, 0x20, 0xd4];
+/// 断点指令，x86_64 为 int3
+#[cfg(not(target_arch = "aarch64"))]
+pub const BREAK_INSN: &[u8] = &[0xcc];
+
+/// 命中时 pc 超过断点地址的字节数，int3 执行完才停下
+#[cfg(target_arch = "aarch64")]
+const PC_OFFSET: usize = 0;
+#[cfg(not(target_arch = "aarch64"))]
+const PC_OFFSET: usize = 1;
+
+/// 一次命中
+#[derive(Debug, Clone)]
+pub struct BreakHit {
+    pub tid: u32,
+    pub address: usize,
+    /// 命中时的寄存器，pc 已回退到断点地址
+    pub regs: Regs,
+}
+
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    pub address: usize,
+    /// 被断点指令覆盖的原始字节
+    pub original: Vec<u8>,
+    pub enabled: bool,
+    /// 每次命中时的寄存器快照
+    pub hits: Vec<BreakHit>,
+}
+
+/// 用断点指令覆盖代码的软件断点
+///
+/// 命中后回退 pc，恢复原始字节单步越过，再写回断点指令。单步期间其它线程可能错过这个断点。
+/// drop 时恢复所有原始字节。
+pub struct Breakpoints<'a> {
+    mem: &'a PtraceMemory,
+    points: BTreeMap<usize, Breakpoint>,
+}
+
+impl<'a> Breakpoints<'a> {
+    pub fn new(mem: &'a PtraceMemory) -> Self {
+        Breakpoints { mem, points: BTreeMap::new() }
+    }
+
+    pub fn get(&self, address: usize) -> Option<&Breakpoint> {
+        self.points.get(&address)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.points.values()
+    }
+
+    /// 设置断点，已存在时重新启用
+    pub fn add(&mut self, address: usize) -> Result<(), MemoryError> {
+        if self.points.contains_key(&address) {
+            return self.enable(address);
+        }
+        let mut original = vec![0; BREAK_INSN.len()];
+        self.mem.read_exact(address, &mut original)?;
+        self.mem.writebuf(address, BREAK_INSN)?;
+        self.points.insert(address, Breakpoint { address, original, enabled: true, hits: Vec::new() });
+        Ok(())
+    }
+
+    pub fn enable(&mut self, address: usize) -> Result<(), MemoryError> {
+        let point = self.points.get_mut(&address)
+            .ok_or(MemoryError::BreakpointError(format!("No breakpoint at {address:#x}")))?;
+        if !point.enabled {
+            self.mem.writebuf(address, BREAK_INSN)?;
+            point.enabled = true;
+        }
+        Ok(())
+    }
+
+    /// 恢复原始字节，保留命中记录
+    pub fn disable(&mut self, address: usize) -> Result<(), MemoryError> {
+        let point = self.points.get_mut(&address)
+            .ok_or(MemoryError::BreakpointError(format!("No breakpoint at {address:#x}")))?;
+        if point.enabled {
+            self.mem.writebuf(address, &point.original)?;
+            point.enabled = false;
+        }
+        Ok(())
+    }
+
+    /// 删除断点，返回它的命中记录
+    pub fn remove(&mut self, address: usize) -> Result<Breakpoint, MemoryError> {
+        self.disable(address)?;
+        // 已经停在这个断点上但还没处理的线程回退 pc 后放走
+        for trap in self.mem.take_traps(|t| trap_address(t) == Some(address)) {
+            let mut regs = trap.regs;
+            regs.set_pc(address as u64);
+            self.mem.set_regs(trap.tid, &regs)?;
+            self.mem.cont(trap.tid)?;
+        }
+        self.points.remove(&address)
+            .ok_or(MemoryError::BreakpointError(format!("No breakpoint at {address:#x}")))
+    }
+
+    pub fn clear(&mut self) -> Result<(), MemoryError> {
+        let addresses: Vec<usize> = self.points.keys().copied().collect();
+        for address in addresses {
+            self.remove(address)?;
+        }
+        Ok(())
+    }
+
+    /// 等待命中直到超时或收集到至少 max 个，每次命中后越过断点恢复运行
+    pub fn wait(&mut self, timeout: Duration, max: usize) -> Result<Vec<BreakHit>, MemoryError> {
+        let deadline = Instant::now() + timeout;
+        let mut hits = Vec::new();
+        while hits.len() < max && Instant::now() < deadline {
+            let stops = self.mem.poll_stops()?;
+            let traps = self.take_traps(|_| true);
+            if stops.is_empty() && traps.is_empty() {
+                std::thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            for trap in traps {
+                let hit = self.record(&trap)?;
+                self.step_over(hit.tid)?;
+                self.mem.cont(hit.tid)?;
+                hits.push(hit);
+            }
+            for (tid, _) in stops {
+                self.mem.cont(tid)?;
+            }
+        }
+        Ok(hits)
+    }
+
+    /// 单步跟踪 count 条指令，返回每一步之后的寄存器，经过断点时不会触发
+    pub fn trace(&mut self, tid: u32, count: usize) -> Result<Vec<Regs>, MemoryError> {
+        let was_running = self.mem.stop(tid)?;
+        // 停下时刚好命中的话先回退 pc
+        for trap in self.take_traps(|t| t.tid == tid) {
+            self.record(&trap)?;
+        }
+        let mut res = Vec::with_capacity(count);
+        let mut err = None;
+        for _ in 0..count {
+            match self.step_over(tid).and_then(|_| self.mem.regs(tid)) {
+                Ok(regs) => res.push(regs),
+                Err(e) => {
+                    err = Some(e);
+                    break;
+                },
+            }
+        }
+        if was_running {
+            self.mem.cont(tid)?;
+        }
+        match err {
+            Some(e) => Err(e),
+            None => Ok(res),
+        }
+    }
+
+    /// 取走属于这些断点的 Trap
+    fn take_traps(&self, pred: impl Fn(&Trap) -> bool) -> Vec<Trap> {
+        self.mem.take_traps(|t| pred(t) && trap_address(t).is_some_and(|a| self.points.contains_key(&a)))
+    }
+
+    /// 回退 pc 并记录命中，线程保持停止
+    fn record(&mut self, trap: &Trap) -> Result<BreakHit, MemoryError> {
+        let address = trap.regs.pc() as usize - PC_OFFSET;
+        let mut regs = trap.regs;
+        regs.set_pc(address as u64);
+        self.mem.set_regs(trap.tid, &regs)?;
+        let hit = BreakHit { tid: trap.tid, address, regs };
+        if let Some(point) = self.points.get_mut(&address) {
+            point.hits.push(hit.clone());
+        }
+        Ok(hit)
+    }
+
+    /// 单步一条指令，当前位置有断点时临时恢复原始字节
+    fn step_over(&self, tid: u32) -> Result<(), MemoryError> {
+        let pc = self.mem.regs(tid)?.pc() as usize;
+        match self.points.get(&pc) {
+            Some(point) if point.enabled => {
+                self.mem.writebuf(pc, &point.original)?;
+                let res = self.mem.step(tid);
+                self.mem.writebuf(pc, BREAK_INSN)?;
+                res
+            },
+            _ => self.mem.step(tid),
+        }
+    }
+}
+
+impl Drop for Breakpoints<'_> {
+    fn drop(&mut self) {
+        let _ = self.clear();
+    }
+}
+
+/// 断点指令造成的 Trap 对应的断点地址
+fn trap_address(trap: &Trap) -> Option<usize> {
+    if trap.code == SI_KERNEL || trap.code == TRAP_BRKPT {
+        (trap.regs.pc() as usize).checked_sub(PC_OFFSET)
+    } else {
+        None
+    }
+}
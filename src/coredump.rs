@@ -0,0 +1,207 @@
+use std::io::Write;
+use crate::elf::{self, ElfHeader, FileMapping, ProgramHeader, SectionHeader};
+use crate::memory::{MemoryError, MemoryReader};
+use crate::memory::ptrace_memory::PtraceMemory;
+use crate::process::{MapRange, Process};
+use crate::registers::Regs;
+
+#[cfg(target_arch = "aarch64")]
+const ELF_MACHINE: u16 = elf::EM_AARCH64;
+#[cfg(not(target_arch = "aarch64"))]
+const ELF_MACHINE: u16 = elf::EM_X86_64;
+
+/// elf_prstatus.pr_reg 的大小
+pub const PR_REG_SIZE: usize = std::mem::size_of::<Regs>();
+
+/// elf_prstatus 中各字段的偏移
+pub const PR_PID_OFFSET: usize = 32;
+const PR_PPID_OFFSET: usize = 36;
+const PR_PGRP_OFFSET: usize = 40;
+const PR_SID_OFFSET: usize = 44;
+/// pr_reg 的偏移，即它之前部分的大小
+pub const PR_REG_OFFSET: usize = 112;
+
+const PAGE_SIZE: u64 = 4096;
+const CHUNK_SIZE: usize = 0x10000;
+
+/// 一个线程的 NT_PRSTATUS 内容
+#[derive(Debug, Clone)]
+pub struct ThreadStatus {
+    pub tid: u32,
+    pub ppid: u32,
+    pub pgrp: u32,
+    pub sid: u32,
+    pub regs: Regs,
+}
+
+impl ThreadStatus {
+    /// ppid、pgrp、sid 从 /proc/pid/task/tid/stat 读取，读不到时为 0
+    pub fn new(pid: u32, tid: u32, regs: Regs) -> Self {
+        let (ppid, pgrp, sid) = stat_ids(pid, tid).unwrap_or_default();
+        ThreadStatus { tid, ppid, pgrp, sid, regs }
+    }
+
+    fn to_prstatus(&self) -> Vec<u8> {
+        let mut desc = vec![0u8; PR_REG_OFFSET + PR_REG_SIZE + 8];
+        for (offset, value) in [(PR_PID_OFFSET, self.tid), (PR_PPID_OFFSET, self.ppid), (PR_PGRP_OFFSET, self.pgrp), (PR_SID_OFFSET, self.sid)] {
+            desc[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        desc[PR_REG_OFFSET..PR_REG_OFFSET + PR_REG_SIZE].copy_from_slice(bytemuck::bytes_of(&self.regs));
+        desc
+    }
+}
+
+/// stat 中状态之后依次为 ppid、pgrp、session
+fn stat_ids(pid: u32, tid: u32) -> Option<(u32, u32, u32)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/task/{tid}/stat")).ok()?;
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace().skip(1).map(|f| f.parse().ok());
+    Some((fields.next()??, fields.next()??, fields.next()??))
+}
+
+/// 把进程内存写成 ELF core 文件
+pub struct CoreDumper<'a, R: MemoryReader> {
+    process: &'a Process,
+    reader: &'a R,
+    threads: Vec<ThreadStatus>,
+}
+
+impl<'a, R: MemoryReader> CoreDumper<'a, R> {
+    pub fn new(process: &'a Process, reader: &'a R) -> Self {
+        CoreDumper {
+            process,
+            reader,
+            threads: Vec::new(),
+        }
+    }
+
+    /// 附带寄存器，第一个应为主线程
+    pub fn threads(mut self, threads: Vec<ThreadStatus>) -> Self {
+        self.threads = threads;
+        self
+    }
+}
+
+impl<'a> CoreDumper<'a, PtraceMemory> {
+    /// 附带所有线程的寄存器，它们在同一次 stop_world 中读取，之后只恢复这次停住的线程
+    pub fn from_ptrace(ptrace: &'a PtraceMemory) -> Result<Self, MemoryError> {
+        let threads = ptrace.thread_status()?;
+        Ok(CoreDumper::new(&ptrace.process, ptrace).threads(threads))
+    }
+}
+
+impl<'a, R: MemoryReader> CoreDumper<'a, R> {
+
+    fn notes(&self, maps: &[&MapRange]) -> Vec<u8> {
+        let mut notes = Vec::new();
+        for thread in self.threads.iter() {
+            elf::write_note(&mut notes, "CORE", elf::NT_PRSTATUS, &thread.to_prstatus());
+        }
+
+        let files = maps.iter()
+            .filter(|m| m.pathname.starts_with('/'))
+            .map(|m| FileMapping {
+                start: m.address.0 as u64,
+                end: m.address.1 as u64,
+                file_offset: m.offset as u64,
+                path: m.pathname.clone(),
+            })
+            .collect::<Vec<_>>();
+        elf::write_note(&mut notes, "CORE", elf::NT_FILE, &elf::build_nt_file(&files, PAGE_SIZE));
+        notes
+    }
+
+    /// 写出 core，返回写入的字节数。不可读或读取失败的页以 0 填充
+    pub fn write<W: Write>(&self, out: &mut W, filter: Option<impl Fn(&MapRange) -> bool>) -> Result<usize, MemoryError> {
+        let maps = self.process.maps.iter()
+            .filter(|m| filter.as_ref().is_none_or(|f| f(m)))
+            .collect::<Vec<_>>();
+        let notes = self.notes(&maps);
+
+        let phnum = maps.len() + 1;
+        let phnum_u32 = u32::try_from(phnum).map_err(|_| MemoryError::CoreFileError(format!("Too many maps: {}", maps.len())))?;
+        // 超过 e_phnum 的范围时和内核一样用 PN_XNUM，在程序头之后放一个节头记录实际数量
+        let xnum = phnum >= elf::PN_XNUM as usize;
+        let shdr_off = (elf::EHDR_SIZE + phnum * elf::PHDR_SIZE) as u64;
+        let notes_off = if xnum { shdr_off + elf::SHDR_SIZE as u64 } else { shdr_off };
+        let mut data_off = (notes_off + notes.len() as u64).next_multiple_of(PAGE_SIZE);
+
+        let mut ehdr = ElfHeader {
+            e_type: elf::ET_CORE,
+            e_machine: ELF_MACHINE,
+            e_phoff: elf::EHDR_SIZE as u64,
+            e_phentsize: elf::PHDR_SIZE as u16,
+            e_phnum: phnum as u16,
+            ..Default::default()
+        };
+        if xnum {
+            ehdr.e_phnum = elf::PN_XNUM;
+            ehdr.e_shoff = shdr_off;
+            ehdr.e_shentsize = elf::SHDR_SIZE as u16;
+            ehdr.e_shnum = 1;
+        }
+
+        let mut headers = Vec::with_capacity(notes_off as usize);
+        headers.extend_from_slice(&ehdr.to_bytes());
+        headers.extend_from_slice(&ProgramHeader {
+            p_type: elf::PT_NOTE,
+            p_offset: notes_off,
+            p_filesz: notes.len() as u64,
+            p_align: 4,
+            ..Default::default()
+        }.to_bytes());
+
+        for map in maps.iter() {
+            let size = (map.address.1 - map.address.0) as u64;
+            let filesz = if map.readable() { size } else { 0 };
+            let mut flags = 0;
+            if map.readable() { flags |= elf::PF_R; }
+            if map.writable() { flags |= elf::PF_W; }
+            if map.executable() { flags |= elf::PF_X; }
+            headers.extend_from_slice(&ProgramHeader {
+                p_type: elf::PT_LOAD,
+                p_flags: flags,
+                p_offset: data_off,
+                p_vaddr: map.address.0 as u64,
+                p_filesz: filesz,
+                p_memsz: size,
+                p_align: PAGE_SIZE,
+                ..Default::default()
+            }.to_bytes());
+            data_off += filesz;
+        }
+        if xnum {
+            headers.extend_from_slice(&SectionHeader { sh_info: phnum_u32, ..Default::default() }.to_bytes());
+        }
+
+        let io_err = |e: std::io::Error| MemoryError::IoError(e.to_string());
+        out.write_all(&headers).map_err(io_err)?;
+        out.write_all(&notes).map_err(io_err)?;
+        let mut written = headers.len() + notes.len();
+        let padding = written.next_multiple_of(PAGE_SIZE as usize) - written;
+        out.write_all(&vec![0u8; padding]).map_err(io_err)?;
+        written += padding;
+
+        let mut buff = vec![0u8; CHUNK_SIZE];
+        for map in maps.iter().filter(|m| m.readable()) {
+            let mut offset = 0;
+            let size = map.address.1 - map.address.0;
+            while offset < size {
+                let len = std::cmp::min(CHUNK_SIZE, size - offset);
+                let chunk = &mut buff[..len];
+                let mut read_bytes = 0;
+                while read_bytes < len {
+                    match self.reader.readbuf(map.address.0 + offset + read_bytes, &mut chunk[read_bytes..]) {
+                        Ok(n) if n > 0 => read_bytes += n,
+                        _ => break,
+                    }
+                }
+                chunk[read_bytes..].fill(0);
+                out.write_all(chunk).map_err(io_err)?;
+                offset += len;
+            }
+            written += size;
+        }
+
+        Ok(written)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::memory::snapshot_memory::SnapshotMemory;
+use crate::memory::{read_lossy, MemoryReader};
+use crate::structs::{Primitive, Value};
+
+const CHUNK_SIZE: usize = 0x10_0000;
+
+/// 一个值的变化
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub address: usize,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// 连续发生变化的一段，[start, end)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedRange {
+    pub start: usize,
+    pub end: usize,
+    pub changes: Vec<Change>,
+}
+
+impl ChangedRange {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// 比较两个内存来源：进程与快照、快照与快照，或者两段不同的区域
+///
+/// 按 ty 的大小为单位、每隔 step 字节比较一次，任意一边读不到的单元跳过。
+#[derive(Debug, Clone)]
+pub struct MemoryDiff {
+    pub ty: Primitive,
+    pub step: usize,
+}
+
+impl MemoryDiff {
+    pub fn new(ty: Primitive) -> Self {
+        MemoryDiff { ty, step: ty.size() }
+    }
+
+    /// 比较的间隔，默认等于类型大小；设为 1 时不要求对齐
+    pub fn step(mut self, step: usize) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    /// 比较 old 和 new 中相同地址的内容
+    pub fn diff<A: MemoryReader, B: MemoryReader>(&self, old: &A, new: &B, ranges: impl IntoIterator<Item = (usize, usize)>) -> Vec<ChangedRange> {
+        let mut res = Vec::new();
+        for (start, end) in ranges {
+            self.diff_range(old, start, new, start, end - start, &mut res);
+        }
+        res
+    }
+
+    /// 比较 a 中 addr_a 开始和 b 中 addr_b 开始的 len 字节，结果中的地址属于 a
+    pub fn diff_regions<A: MemoryReader, B: MemoryReader>(&self, a: &A, addr_a: usize, b: &B, addr_b: usize, len: usize) -> Vec<ChangedRange> {
+        let mut res = Vec::new();
+        self.diff_range(a, addr_a, b, addr_b, len, &mut res);
+        res
+    }
+
+    /// 比较两个快照中 old 保存的范围
+    pub fn diff_snapshots(&self, old: &SnapshotMemory, new: &SnapshotMemory) -> Vec<ChangedRange> {
+        self.diff(old, new, old.ranges())
+    }
+
+    /// 比较两段已经读出的字节，None 为读不到，结果中的地址从 base 开始
+    pub fn diff_bytes(&self, base: usize, old: &[Option<u8>], new: &[Option<u8>]) -> Vec<ChangedRange> {
+        let len = std::cmp::min(old.len(), new.len());
+        let mut res = Vec::new();
+        self.compare_cells(base, &old[..len], &new[..len], len, &mut res);
+        res
+    }
+
+    fn diff_range<A: MemoryReader, B: MemoryReader>(&self, a: &A, addr_a: usize, b: &B, addr_b: usize, len: usize, res: &mut Vec<ChangedRange>) {
+        let size = self.ty.size();
+        // 每块的长度是 step 的整数倍，多读 size 字节让跨块的单元也能比较
+        let chunk = CHUNK_SIZE.div_ceil(self.step) * self.step;
+        let mut offset = 0;
+        while offset < len {
+            let cells = std::cmp::min(chunk, len - offset);
+            let read = std::cmp::min(cells + size, len - offset);
+            let old = read_lossy(a, &[], addr_a + offset, read);
+            let new = read_lossy(b, &[], addr_b + offset, read);
+            self.compare_cells(addr_a + offset, &old, &new, cells, res);
+            offset += cells;
+        }
+    }
+
+    /// 比较从 0 开始、偏移小于 limit 的单元
+    fn compare_cells(&self, base: usize, old: &[Option<u8>], new: &[Option<u8>], limit: usize, res: &mut Vec<ChangedRange>) {
+        let size = self.ty.size();
+        let mut off = 0;
+        while off < limit && off + size <= old.len() {
+            let cell = off..off + size;
+            off += self.step;
+            // 先直接比较，只有不同的单元才复制出字节
+            if old[cell.clone()] == new[cell.clone()] {
+                continue;
+            }
+            let (Some(o), Some(n)) = (bytes(&old[cell.clone()]), bytes(&new[cell.clone()])) else {
+                continue;
+            };
+
+            let change = Change {
+                address: base + cell.start,
+                old: self.ty.decode(&o),
+                new: self.ty.decode(&n),
+            };
+            // 与上一个变化相邻时合并
+            match res.last_mut() {
+                Some(last) if last.changes.last().is_some_and(|c| c.address + self.step == change.address) => {
+                    last.end = change.address + size;
+                    last.changes.push(change);
+                },
+                _ => res.push(ChangedRange {
+                    start: change.address,
+                    end: change.address + size,
+                    changes: vec![change],
+                }),
+            }
+        }
+    }
+}
+
+fn bytes(cell: &[Option<u8>]) -> Option<Vec<u8>> {
+    cell.iter().copied().collect()
+}
@@ -0,0 +1,387 @@
+use std::fmt;
+
+pub const ELFMAG: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+pub const ELFCLASS64: u8 = 2;
+pub const ELFDATA2LSB: u8 = 1;
+pub const EV_CURRENT: u8 = 1;
+
+pub const ET_CORE: u16 = 4;
+
+pub const EM_X86_64: u16 = 62;
+pub const EM_AARCH64: u16 = 183;
+
+/// e_phnum 放不下时写 PN_XNUM，实际数量在第 0 个节头的 sh_info 中
+pub const PN_XNUM: u16 = 0xffff;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_NOTE: u32 = 4;
+
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_DYNSYM: u32 = 11;
+
+pub const DT_NULL: u64 = 0;
+pub const DT_HASH: u64 = 4;
+pub const DT_STRTAB: u64 = 5;
+pub const DT_SYMTAB: u64 = 6;
+pub const DT_STRSZ: u64 = 10;
+pub const DT_GNU_HASH: u64 = 0x6ffffef5;
+
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_GNU_IFUNC: u8 = 10;
+pub const SHN_UNDEF: u16 = 0;
+
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+pub const NT_PRSTATUS: u32 = 1;
+pub const NT_PRFPREG: u32 = 2;
+pub const NT_PRPSINFO: u32 = 3;
+pub const NT_FILE: u32 = 0x46494c45;
+pub const NT_ARM_SYSTEM_CALL: u32 = 0x404;
+
+pub const EHDR_SIZE: usize = 64;
+pub const PHDR_SIZE: usize = 56;
+pub const SHDR_SIZE: usize = 64;
+pub const SYM_SIZE: usize = 24;
+pub const DYN_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub enum ElfError {
+    Truncated(usize),
+    BadMagic,
+    Unsupported(String),
+    BadNote(String),
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[inline]
+pub(crate) fn u16_at(data: &[u8], off: usize) -> Result<u16, ElfError> {
+    data.get(off..off + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(ElfError::Truncated(off))
+}
+
+#[inline]
+pub(crate) fn u32_at(data: &[u8], off: usize) -> Result<u32, ElfError> {
+    data.get(off..off + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or(ElfError::Truncated(off))
+}
+
+#[inline]
+pub(crate) fn u64_at(data: &[u8], off: usize) -> Result<u64, ElfError> {
+    data.get(off..off + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or(ElfError::Truncated(off))
+}
+
+/// Elf64_Ehdr，只支持小端 64 位
+#[derive(Debug, Clone, Default)]
+pub struct ElfHeader {
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_entry: u64,
+    pub e_phoff: u64,
+    pub e_shoff: u64,
+    pub e_flags: u32,
+    pub e_phentsize: u16,
+    pub e_phnum: u16,
+    pub e_shentsize: u16,
+    pub e_shnum: u16,
+    pub e_shstrndx: u16,
+}
+
+impl ElfHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        if data.len() < EHDR_SIZE {
+            return Err(ElfError::Truncated(data.len()));
+        }
+        if data[0..4] != ELFMAG {
+            return Err(ElfError::BadMagic);
+        }
+        if data[4] != ELFCLASS64 {
+            return Err(ElfError::Unsupported(format!("class {}", data[4])));
+        }
+        if data[5] != ELFDATA2LSB {
+            return Err(ElfError::Unsupported(format!("data encoding {}", data[5])));
+        }
+
+        Ok(ElfHeader {
+            e_type: u16_at(data, 16)?,
+            e_machine: u16_at(data, 18)?,
+            e_entry: u64_at(data, 24)?,
+            e_phoff: u64_at(data, 32)?,
+            e_shoff: u64_at(data, 40)?,
+            e_flags: u32_at(data, 48)?,
+            e_phentsize: u16_at(data, 54)?,
+            e_phnum: u16_at(data, 56)?,
+            e_shentsize: u16_at(data, 58)?,
+            e_shnum: u16_at(data, 60)?,
+            e_shstrndx: u16_at(data, 62)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; EHDR_SIZE] {
+        let mut b = [0u8; EHDR_SIZE];
+        b[0..4].copy_from_slice(&ELFMAG);
+        b[4] = ELFCLASS64;
+        b[5] = ELFDATA2LSB;
+        b[6] = EV_CURRENT;
+        b[16..18].copy_from_slice(&self.e_type.to_le_bytes());
+        b[18..20].copy_from_slice(&self.e_machine.to_le_bytes());
+        b[20..24].copy_from_slice(&(EV_CURRENT as u32).to_le_bytes());
+        b[24..32].copy_from_slice(&self.e_entry.to_le_bytes());
+        b[32..40].copy_from_slice(&self.e_phoff.to_le_bytes());
+        b[40..48].copy_from_slice(&self.e_shoff.to_le_bytes());
+        b[48..52].copy_from_slice(&self.e_flags.to_le_bytes());
+        b[52..54].copy_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+        b[54..56].copy_from_slice(&self.e_phentsize.to_le_bytes());
+        b[56..58].copy_from_slice(&self.e_phnum.to_le_bytes());
+        b[58..60].copy_from_slice(&self.e_shentsize.to_le_bytes());
+        b[60..62].copy_from_slice(&self.e_shnum.to_le_bytes());
+        b[62..64].copy_from_slice(&self.e_shstrndx.to_le_bytes());
+        b
+    }
+}
+
+/// Elf64_Phdr
+#[derive(Debug, Clone, Default)]
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_offset: u64,
+    pub p_vaddr: u64,
+    pub p_paddr: u64,
+    pub p_filesz: u64,
+    pub p_memsz: u64,
+    pub p_align: u64,
+}
+
+impl ProgramHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        Ok(ProgramHeader {
+            p_type: u32_at(data, 0)?,
+            p_flags: u32_at(data, 4)?,
+            p_offset: u64_at(data, 8)?,
+            p_vaddr: u64_at(data, 16)?,
+            p_paddr: u64_at(data, 24)?,
+            p_filesz: u64_at(data, 32)?,
+            p_memsz: u64_at(data, 40)?,
+            p_align: u64_at(data, 48)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; PHDR_SIZE] {
+        let mut b = [0u8; PHDR_SIZE];
+        b[0..4].copy_from_slice(&self.p_type.to_le_bytes());
+        b[4..8].copy_from_slice(&self.p_flags.to_le_bytes());
+        b[8..16].copy_from_slice(&self.p_offset.to_le_bytes());
+        b[16..24].copy_from_slice(&self.p_vaddr.to_le_bytes());
+        b[24..32].copy_from_slice(&self.p_paddr.to_le_bytes());
+        b[32..40].copy_from_slice(&self.p_filesz.to_le_bytes());
+        b[40..48].copy_from_slice(&self.p_memsz.to_le_bytes());
+        b[48..56].copy_from_slice(&self.p_align.to_le_bytes());
+        b
+    }
+}
+
+/// Elf64_Shdr
+#[derive(Debug, Clone, Default)]
+pub struct SectionHeader {
+    pub sh_name: u32,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub sh_addr: u64,
+    pub sh_offset: u64,
+    pub sh_size: u64,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u64,
+    pub sh_entsize: u64,
+}
+
+impl SectionHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        Ok(SectionHeader {
+            sh_name: u32_at(data, 0)?,
+            sh_type: u32_at(data, 4)?,
+            sh_flags: u64_at(data, 8)?,
+            sh_addr: u64_at(data, 16)?,
+            sh_offset: u64_at(data, 24)?,
+            sh_size: u64_at(data, 32)?,
+            sh_link: u32_at(data, 40)?,
+            sh_info: u32_at(data, 44)?,
+            sh_addralign: u64_at(data, 48)?,
+            sh_entsize: u64_at(data, 56)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; SHDR_SIZE] {
+        let mut b = [0u8; SHDR_SIZE];
+        b[0..4].copy_from_slice(&self.sh_name.to_le_bytes());
+        b[4..8].copy_from_slice(&self.sh_type.to_le_bytes());
+        b[8..16].copy_from_slice(&self.sh_flags.to_le_bytes());
+        b[16..24].copy_from_slice(&self.sh_addr.to_le_bytes());
+        b[24..32].copy_from_slice(&self.sh_offset.to_le_bytes());
+        b[32..40].copy_from_slice(&self.sh_size.to_le_bytes());
+        b[40..44].copy_from_slice(&self.sh_link.to_le_bytes());
+        b[44..48].copy_from_slice(&self.sh_info.to_le_bytes());
+        b[48..56].copy_from_slice(&self.sh_addralign.to_le_bytes());
+        b[56..64].copy_from_slice(&self.sh_entsize.to_le_bytes());
+        b
+    }
+}
+
+/// Elf64_Sym
+#[derive(Debug, Clone, Default)]
+pub struct Sym {
+    pub st_name: u32,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size: u64,
+}
+
+impl Sym {
+    pub fn parse(data: &[u8]) -> Result<Self, ElfError> {
+        Ok(Sym {
+            st_name: u32_at(data, 0)?,
+            st_info: *data.get(4).ok_or(ElfError::Truncated(4))?,
+            st_other: *data.get(5).ok_or(ElfError::Truncated(5))?,
+            st_shndx: u16_at(data, 6)?,
+            st_value: u64_at(data, 8)?,
+            st_size: u64_at(data, 16)?,
+        })
+    }
+
+    pub fn st_type(&self) -> u8 {
+        self.st_info & 0xf
+    }
+}
+
+/// 从字符串表中取以 \0 结尾的字符串
+pub fn str_at(strtab: &[u8], off: usize) -> Option<&str> {
+    let tail = strtab.get(off..)?;
+    let end = tail.iter().position(|b| *b == 0)?;
+    std::str::from_utf8(&tail[..end]).ok()
+}
+
+/// PT_NOTE 段里的一条 note
+#[derive(Debug, Clone, Copy)]
+pub struct Note<'a> {
+    pub n_type: u32,
+    pub name: &'a [u8],
+    pub desc: &'a [u8],
+}
+
+#[inline]
+fn align4(v: usize) -> usize {
+    (v + 3) & !3
+}
+
+/// 遍历 PT_NOTE 段内容
+pub fn notes(data: &[u8]) -> impl Iterator<Item = Result<Note<'_>, ElfError>> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset + 12 > data.len() {
+            return None;
+        }
+        let parse = || {
+            let namesz = u32_at(data, offset)? as usize;
+            let descsz = u32_at(data, offset + 4)? as usize;
+            let n_type = u32_at(data, offset + 8)?;
+            let name_off = offset + 12;
+            let desc_off = name_off + align4(namesz);
+            let end = desc_off + align4(descsz);
+            let name = data.get(name_off..name_off + namesz)
+                .ok_or(ElfError::BadNote(format!("name out of range at {offset:#x}")))?;
+            let desc = data.get(desc_off..desc_off + descsz)
+                .ok_or(ElfError::BadNote(format!("desc out of range at {offset:#x}")))?;
+            // name 带结尾的 \0
+            let name = name.strip_suffix(&[0]).unwrap_or(name);
+            Ok((Note { n_type, name, desc }, end))
+        };
+        match parse() {
+            Ok((note, end)) => {
+                offset = end;
+                Some(Ok(note))
+            },
+            Err(e) => {
+                offset = data.len();
+                Some(Err(e))
+            }
+        }
+    })
+}
+
+/// 按 ELF64 note 格式追加一条 note
+pub fn write_note(out: &mut Vec<u8>, name: &str, n_type: u32, desc: &[u8]) {
+    let namesz = name.len() + 1;
+    out.extend_from_slice(&(namesz as u32).to_le_bytes());
+    out.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    out.extend_from_slice(&n_type.to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out.resize(out.len() + align4(namesz) - name.len(), 0);
+    out.extend_from_slice(desc);
+    out.resize(out.len() + align4(desc.len()) - desc.len(), 0);
+}
+
+/// NT_FILE 中的一项映射，file_offset 以字节为单位
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMapping {
+    pub start: u64,
+    pub end: u64,
+    pub file_offset: u64,
+    pub path: String,
+}
+
+/// 解析 NT_FILE：count, page_size, count * (start, end, pgoff), 然后是 count 个以 \0 结尾的路径
+pub fn parse_nt_file(desc: &[u8]) -> Result<Vec<FileMapping>, ElfError> {
+    let count = u64_at(desc, 0)? as usize;
+    let page_size = u64_at(desc, 8)?;
+    let names_off = count.checked_mul(24)
+        .and_then(|v| v.checked_add(16))
+        .filter(|v| *v <= desc.len())
+        .ok_or(ElfError::BadNote(format!("NT_FILE count {count} too large")))?;
+
+    let mut names = desc[names_off..].split(|b| *b == 0);
+    let mut res = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 16 + i * 24;
+        let name = names.next()
+            .ok_or(ElfError::BadNote(format!("NT_FILE missing name {i}")))?;
+        res.push(FileMapping {
+            start: u64_at(desc, entry)?,
+            end: u64_at(desc, entry + 8)?,
+            file_offset: u64_at(desc, entry + 16)? * page_size,
+            path: String::from_utf8_lossy(name).into_owned(),
+        });
+    }
+    Ok(res)
+}
+
+/// 生成 NT_FILE 的 desc
+pub fn build_nt_file(files: &[FileMapping], page_size: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    out.extend_from_slice(&page_size.to_le_bytes());
+    for f in files {
+        out.extend_from_slice(&f.start.to_le_bytes());
+        out.extend_from_slice(&f.end.to_le_bytes());
+        out.extend_from_slice(&(f.file_offset / page_size).to_le_bytes());
+    }
+    for f in files {
+        out.extend_from_slice(f.path.as_bytes());
+        out.push(0);
+    }
+    out
+}
@@ -0,0 +1,261 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use crate::memory::{from_bytes, read_lossy, MemoryReader};
+use crate::process::{module, MapRange};
+
+/// 每一列按什么类型显示
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Column {
+    #[default]
+    Byte,
+    Word,
+    Dword,
+    Qword,
+    Float,
+    Double,
+}
+
+impl Column {
+    pub fn size(self) -> usize {
+        match self {
+            Column::Byte => 1,
+            Column::Word => 2,
+            Column::Dword | Column::Float => 4,
+            Column::Qword | Column::Double => 8,
+        }
+    }
+
+    fn format(self, cell: &[Option<u8>]) -> String {
+        let width = match self {
+            Column::Float | Column::Double => 12,
+            _ => self.size() * 2,
+        };
+        let Some(bytes) = cell.iter().copied().collect::<Option<Vec<u8>>>() else {
+            return format!("{:?>width$}", "");
+        };
+        match self {
+            Column::Byte => format!("{:02x}", bytes[0]),
+            Column::Word => format!("{:04x}", from_bytes::<u16>(&bytes).unwrap()),
+            Column::Dword => format!("{:08x}", from_bytes::<u32>(&bytes).unwrap()),
+            Column::Qword => format!("{:016x}", from_bytes::<u64>(&bytes).unwrap()),
+            Column::Float => format!("{:>width$}", format_float(from_bytes::<f32>(&bytes).unwrap() as f64)),
+            Column::Double => format!("{:>width$}", format_float(from_bytes::<f64>(&bytes).unwrap())),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "byte" | "u8" => Column::Byte,
+            "word" | "u16" => Column::Word,
+            "dword" | "u32" => Column::Dword,
+            "qword" | "u64" => Column::Qword,
+            "float" | "f32" => Column::Float,
+            "double" | "f64" => Column::Double,
+            _ => return Err(format!("unknown column type {s:?}")),
+        })
+    }
+}
+
+fn format_float(v: f64) -> String {
+    if v != 0.0 && !(1e-4..1e8).contains(&v.abs()) {
+        format!("{v:.3e}")
+    } else {
+        format!("{v:.4}")
+    }
+}
+
+/// 右侧的文本栏
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sidebar {
+    None,
+    #[default]
+    Ascii,
+    Utf16,
+}
+
+/// 一行的内容，None 为读取失败的字节
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub address: usize,
+    pub bytes: Vec<Option<u8>>,
+    /// 与上一次渲染相比发生变化的字节
+    pub changed: Vec<bool>,
+    /// 在这一行中开始的 maps 区域
+    pub maps: Vec<MapRange>,
+    /// 行首所在的模块和偏移，或者所在区域的路径
+    pub location: Option<String>,
+}
+
+/// 十六进制查看器，保留上一次读到的内容用于标出变化
+#[derive(Debug, Clone)]
+pub struct HexDump {
+    pub column: Column,
+    /// 每行字节数
+    pub width: usize,
+    pub sidebar: Sidebar,
+    /// 变化的单元用 ANSI 颜色标出，否则在前面加 `*`
+    pub color: bool,
+    last: Option<(usize, Vec<Option<u8>>)>,
+}
+
+impl Default for HexDump {
+    fn default() -> Self {
+        HexDump {
+            column: Column::Byte,
+            width: 16,
+            sidebar: Sidebar::Ascii,
+            color: false,
+            last: None,
+        }
+    }
+}
+
+impl HexDump {
+    pub fn new() -> Self {
+        HexDump::default()
+    }
+
+    pub fn column(mut self, column: Column) -> Self {
+        self.column = column;
+        self
+    }
+
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn sidebar(mut self, sidebar: Sidebar) -> Self {
+        self.sidebar = sidebar;
+        self
+    }
+
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// 忘记上一次的内容，下一次渲染不标出变化
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// 按行读取 [address, address + len)，maps 用于标注区域和模块
+    pub fn rows<R: MemoryReader>(&mut self, reader: &R, maps: &[MapRange], address: usize, len: usize) -> Vec<Row> {
+        let bytes = read_lossy(reader, maps, address, len);
+        let modules = module::modules_from_maps(maps);
+        // 宽度至少为一个单元，且是单元大小的整数倍
+        let size = self.column.size();
+        let width = self.width.max(size).div_ceil(size) * size;
+
+        let previous = |addr: usize| -> Option<Option<u8>> {
+            let (start, last) = self.last.as_ref()?;
+            last.get(addr.checked_sub(*start)?).copied()
+        };
+
+        let mut rows = Vec::new();
+        for (i, chunk) in bytes.chunks(width).enumerate() {
+            let row_addr = address + i * width;
+            let row_end = row_addr + chunk.len();
+            let changed = chunk.iter().enumerate()
+                .map(|(j, b)| previous(row_addr + j).is_some_and(|p| p != *b))
+                .collect();
+            let location = match modules.iter().find(|m| m.contains(row_addr)) {
+                Some(m) => Some(format!("{}+{:#x}", m.name, row_addr - m.base)),
+                None => maps.iter()
+                    .find(|m| m.address.0 <= row_addr && row_addr < m.address.1 && !m.pathname.is_empty())
+                    .map(|m| m.pathname.clone()),
+            };
+            rows.push(Row {
+                address: row_addr,
+                bytes: chunk.to_vec(),
+                changed,
+                maps: maps.iter().filter(|m| row_addr <= m.address.0 && m.address.0 < row_end).cloned().collect(),
+                location,
+            });
+        }
+
+        self.last = Some((address, bytes));
+        rows
+    }
+
+    /// 渲染成文本，每行一条，区域开始处插入一行分隔
+    pub fn render<R: MemoryReader>(&mut self, reader: &R, maps: &[MapRange], address: usize, len: usize) -> String {
+        let rows = self.rows(reader, maps, address, len);
+        let mut out = String::new();
+        for row in rows.iter() {
+            for map in row.maps.iter() {
+                writeln!(out, "-- {map}").unwrap();
+            }
+            out.push_str(&self.format_row(row));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// 格式化一行：地址、各列、文本栏、位置
+    pub fn format_row(&self, row: &Row) -> String {
+        let size = self.column.size();
+        let mut line = format!("{:016x} ", row.address);
+
+        for (cell, changed) in row.bytes.chunks(size).zip(row.changed.chunks(size)) {
+            let text = if cell.len() == size {
+                self.column.format(cell)
+            } else {
+                // 末尾不足一个单元的部分按字节显示
+                cell.iter().map(|b| b.map_or("??".to_string(), |b| format!("{b:02x}"))).collect()
+            };
+            let changed = changed.iter().any(|c| *c);
+            match (changed, self.color) {
+                (true, true) => write!(line, " \x1b[1;31m{text}\x1b[0m").unwrap(),
+                (true, false) => write!(line, "*{text}").unwrap(),
+                (false, _) => write!(line, " {text}").unwrap(),
+            }
+        }
+
+        // 最后一行不满时补齐，保持文本栏对齐
+        let width = self.width.max(size).div_ceil(size) * size;
+        if row.bytes.len() < width {
+            let cell_width = 1 + match self.column {
+                Column::Float | Column::Double => 12,
+                _ => size * 2,
+            };
+            let rem = row.bytes.len() % size;
+            let used = row.bytes.len() / size * cell_width + if rem > 0 { 1 + rem * 2 } else { 0 };
+            line.push_str(&" ".repeat(width / size * cell_width - used));
+        }
+
+        match self.sidebar {
+            Sidebar::None => {},
+            Sidebar::Ascii => write!(line, "  |{}|", ascii(&row.bytes)).unwrap(),
+            Sidebar::Utf16 => write!(line, "  |{}|", utf16(&row.bytes)).unwrap(),
+        }
+        if let Some(location) = row.location.as_ref() {
+            write!(line, "  {location}").unwrap();
+        }
+        line
+    }
+}
+
+fn ascii(bytes: &[Option<u8>]) -> String {
+    bytes.iter().map(|b| match b {
+        Some(b) if (0x20..0x7f).contains(b) => *b as char,
+        Some(_) => '.',
+        None => '?',
+    }).collect()
+}
+
+fn utf16(bytes: &[Option<u8>]) -> String {
+    bytes.chunks(2).map(|pair| match pair {
+        [Some(lo), Some(hi)] => match char::from_u32(from_bytes::<u16>(&[*lo, *hi]).unwrap() as u32) {
+            Some(c) if !c.is_control() => c,
+            _ => '.',
+        },
+        [_, _] => '?',
+        _ => ' ',
+    }).collect()
+}
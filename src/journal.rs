@@ -0,0 +1,262 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::memory::{MemoryError, MemoryReader, MemoryWriter, Pod};
+
+#[derive(Debug)]
+pub enum JournalError {
+    IoError(String),
+    ParseError { line: usize, reason: String },
+    NothingToUndo,
+    NothingToRedo,
+    UnknownCheckpoint(String),
+    /// 检查点名称为空或含有空白、`#`，保存后无法读回
+    InvalidCheckpoint(String),
+    /// 撤销或重做时写入失败，position 停在失败的那一条之前
+    WriteError { address: usize, reason: String },
+}
+
+impl std::fmt::Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// 一次写入的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub address: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+    pub time: SystemTime,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    edits: Vec<Edit>,
+    /// 已生效的记录数，edits[position..] 是可以重做的部分
+    position: usize,
+    checkpoints: Vec<(String, usize)>,
+}
+
+/// 记录所有写入的包装，支持撤销、重做和回到检查点
+///
+/// 写入前先读出原始字节，撤销时写回。撤销后再写入会丢弃可以重做的记录。
+/// 可以保存为文本，重启后用 load 读回并继续撤销：
+///
+/// ```text
+/// # mempoll journal
+/// edit 0x7f0000001000 1760000000000000000 0a000000 63000000
+/// checkpoint before-hp 1
+/// position 1
+/// ```
+///
+/// edit 行依次为地址、写入时间（UNIX 纳秒）、原始字节、写入字节。
+pub struct Journal<'a, M: MemoryReader + MemoryWriter> {
+    mem: &'a M,
+    history: RefCell<History>,
+}
+
+impl<'a, M: MemoryReader + MemoryWriter> Journal<'a, M> {
+    pub fn new(mem: &'a M) -> Self {
+        Journal { mem, history: RefCell::new(History::default()) }
+    }
+
+    /// 所有记录，包括可以重做的
+    pub fn edits(&self) -> Vec<Edit> {
+        self.history.borrow().edits.clone()
+    }
+
+    pub fn position(&self) -> usize {
+        self.history.borrow().position
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position() > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        let history = self.history.borrow();
+        history.position < history.edits.len()
+    }
+
+    /// 在当前位置建立检查点，同名的会被覆盖
+    pub fn checkpoint(&self, name: &str) -> Result<(), JournalError> {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '#') {
+            return Err(JournalError::InvalidCheckpoint(name.to_string()));
+        }
+        let mut history = self.history.borrow_mut();
+        let position = history.position;
+        history.checkpoints.retain(|(n, _)| n != name);
+        history.checkpoints.push((name.to_string(), position));
+        Ok(())
+    }
+
+    pub fn checkpoints(&self) -> Vec<(String, usize)> {
+        self.history.borrow().checkpoints.clone()
+    }
+
+    /// 撤销最近一次写入
+    pub fn undo(&self) -> Result<Edit, JournalError> {
+        let mut history = self.history.borrow_mut();
+        if history.position == 0 {
+            return Err(JournalError::NothingToUndo);
+        }
+        let edit = history.edits[history.position - 1].clone();
+        self.mem.write_exact(edit.address, &edit.old)
+            .map_err(|e| JournalError::WriteError { address: edit.address, reason: e.to_string() })?;
+        history.position -= 1;
+        Ok(edit)
+    }
+
+    /// 重做最近一次撤销的写入
+    pub fn redo(&self) -> Result<Edit, JournalError> {
+        let mut history = self.history.borrow_mut();
+        if history.position == history.edits.len() {
+            return Err(JournalError::NothingToRedo);
+        }
+        let edit = history.edits[history.position].clone();
+        self.mem.write_exact(edit.address, &edit.new)
+            .map_err(|e| JournalError::WriteError { address: edit.address, reason: e.to_string() })?;
+        history.position += 1;
+        Ok(edit)
+    }
+
+    /// 撤销检查点之后的所有写入，返回撤销的数量；检查点之前已被撤销的部分会重做
+    pub fn revert_to(&self, name: &str) -> Result<usize, JournalError> {
+        let target = self.history.borrow().checkpoints.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, p)| *p)
+            .ok_or(JournalError::UnknownCheckpoint(name.to_string()))?;
+        let mut count = 0;
+        while self.position() > target {
+            self.undo()?;
+            count += 1;
+        }
+        while self.position() < target {
+            self.redo()?;
+        }
+        Ok(count)
+    }
+
+    /// 撤销所有写入，返回撤销的数量
+    pub fn revert_all(&self) -> Result<usize, JournalError> {
+        let mut count = 0;
+        while self.can_undo() {
+            self.undo()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn to_text(&self) -> String {
+        let history = self.history.borrow();
+        let mut text = String::from("# mempoll journal\n");
+        for edit in history.edits.iter() {
+            let nanos = edit.time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+            text += &format!("edit {:#x} {} {} {}\n", edit.address, nanos, to_hex(&edit.old), to_hex(&edit.new));
+        }
+        for (name, position) in history.checkpoints.iter() {
+            text += &format!("checkpoint {} {}\n", name, position);
+        }
+        text += &format!("position {}\n", history.position);
+        text
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), JournalError> {
+        std::fs::write(path, self.to_text()).map_err(|e| JournalError::IoError(e.to_string()))
+    }
+
+    /// 读回保存的记录，不会写入内存
+    pub fn parse(mem: &'a M, text: &str) -> Result<Self, JournalError> {
+        let mut history = History::default();
+        let mut position = None;
+        for (i, raw) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |reason: String| JournalError::ParseError { line: line_no, reason };
+            let line = raw.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                ["edit", address, nanos, old, new] => {
+                    let address = address.strip_prefix("0x").and_then(|a| usize::from_str_radix(a, 16).ok())
+                        .ok_or(err(format!("bad address {address:?}")))?;
+                    let nanos = nanos.parse().map_err(|_| err(format!("bad time {nanos:?}")))?;
+                    let old = from_hex(old).ok_or(err(format!("bad bytes {old:?}")))?;
+                    let new = from_hex(new).ok_or(err(format!("bad bytes {new:?}")))?;
+                    if old.len() != new.len() {
+                        return Err(err("old and new have different lengths".to_string()));
+                    }
+                    history.edits.push(Edit { address, old, new, time: UNIX_EPOCH + Duration::from_nanos(nanos) });
+                },
+                ["checkpoint", name, p] => {
+                    let p = p.parse().map_err(|_| err(format!("bad position {p:?}")))?;
+                    history.checkpoints.push((name.to_string(), p));
+                },
+                ["position", p] => {
+                    position = Some(p.parse().map_err(|_| err(format!("bad position {p:?}")))?);
+                },
+                _ => return Err(err(format!("unexpected line {line:?}"))),
+            }
+        }
+
+        history.position = position.unwrap_or(history.edits.len());
+        let len = history.edits.len();
+        if history.position > len || history.checkpoints.iter().any(|(_, p)| *p > len) {
+            return Err(JournalError::ParseError { line: 0, reason: format!("position beyond {len} edits") });
+        }
+        Ok(Journal { mem, history: RefCell::new(history) })
+    }
+
+    pub fn load<P: AsRef<Path>>(mem: &'a M, path: P) -> Result<Self, JournalError> {
+        let text = std::fs::read_to_string(path).map_err(|e| JournalError::IoError(e.to_string()))?;
+        Self::parse(mem, &text)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+impl<M: MemoryReader + MemoryWriter> MemoryReader for Journal<'_, M> {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        self.mem.read(address)
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        self.mem.readbuf(address, buf)
+    }
+}
+
+impl<M: MemoryReader + MemoryWriter> MemoryWriter for Journal<'_, M> {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        self.writebuf(address, bytemuck::bytes_of(value)).map(|_| ())
+    }
+
+    /// 读不到原始字节时不写入；写入失败时尽量恢复原始字节，不记录
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        let mut old = vec![0; buf.len()];
+        self.mem.read_exact(address, &mut old)?;
+        if let Err(e) = self.mem.write_exact(address, buf) {
+            let _ = self.mem.write_exact(address, &old);
+            return Err(e);
+        }
+
+        let mut history = self.history.borrow_mut();
+        let position = history.position;
+        history.edits.truncate(position);
+        history.checkpoints.retain(|(_, p)| *p <= position);
+        history.edits.push(Edit { address, old, new: buf.to_vec(), time: SystemTime::now() });
+        history.position += 1;
+        Ok(buf.len())
+    }
+}
@@ -1,8 +1,20 @@
 #![feature(portable_simd)]
 
+pub mod breakpoint;
+pub mod coredump;
+pub mod diff;
+pub mod elf;
+pub mod hexdump;
+pub mod journal;
 pub mod process;
+pub mod registers;
 pub mod memory;
+pub mod patch;
 pub mod searcher;
+pub mod structs;
+pub mod symbols;
+pub mod transaction;
+pub mod watchpoint;
 
 /*
 /// 读取进程的内存
@@ -1,21 +1,126 @@
+pub mod core_memory;
+pub mod force_memory;
+pub mod mock_memory;
 pub mod proc_memory;
 pub mod process_vm_memory;
 pub mod ptrace_memory;
+pub mod snapshot_memory;
+pub mod verify_memory;
 
+pub use bytemuck::{self, Pod, Zeroable};
+use crate::process::MapRange;
+
+/// 远程读写的类型必须是 Pod：任意字节都是合法值，没有填充
+///
+/// bool、char、枚举和引用不是 Pod，需要先按整数读出再转换。
+/// 自定义结构体用 `#[repr(C)]` 加 `#[derive(Clone, Copy, Pod, Zeroable)]`。
+/// derive 展开后引用 `::bytemuck`，没有直接依赖 bytemuck 的 crate 要用 `#[bytemuck(crate = ...)]` 指向这里的重新导出：
+///
+/// ```
+/// use mempoll::memory::mock_memory::MockMemory;
+/// use mempoll::memory::{MemoryReader, Pod, Zeroable};
+///
+/// #[repr(C)]
+/// #[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+/// #[bytemuck(crate = "mempoll::memory::bytemuck")]
+/// struct Vec3 {
+///     x: f32,
+///     y: f32,
+///     z: f32,
+/// }
+///
+/// let mut mem = MockMemory::new();
+/// let data = [1.0f32, 2.0, 3.0].iter().flat_map(|f| f.to_ne_bytes()).collect();
+/// mem.add_rw(0x1000, data);
+/// assert_eq!(mem.read::<Vec3>(0x1000).unwrap(), Vec3 { x: 1.0, y: 2.0, z: 3.0 });
+/// ```
 pub trait MemoryReader {
-    fn read<T: Sized + Copy>(&self, address: usize) -> Result<T, MemoryError>;
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError>;
     fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError>;
+
+    /// 读满 buf，readbuf 可能只返回一部分
+    fn read_exact(&self, address: usize, buf: &mut [u8]) -> Result<(), MemoryError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let len = self.readbuf(address + done, &mut buf[done..])?;
+            if len == 0 {
+                return Err(MemoryError::ShortReadError(format!("Short read at {:#x}", address + done)));
+            }
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// 连续读取 count 个 T
+    fn read_array<T: Pod>(&self, address: usize, count: usize) -> Result<Vec<T>, MemoryError> {
+        let size = std::mem::size_of::<T>();
+        let mut buf = vec![0u8; size * count];
+        self.read_exact(address, &mut buf)?;
+        Ok(buf.chunks_exact(size).map(bytemuck::pod_read_unaligned).collect())
+    }
 }
 
 pub trait MemoryWriter {
-    fn write<T: Sized + Copy>(&self, address: usize, value: &T) -> Result<(), MemoryError>;
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError>;
     fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError>;
+
+    /// 写完整个 buf，writebuf 可能只写入一部分
+    fn write_exact(&self, address: usize, buf: &[u8]) -> Result<(), MemoryError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let len = self.writebuf(address + done, &buf[done..])?;
+            if len == 0 {
+                return Err(MemoryError::ShortWriteError(format!("Short write at {:#x}", address + done)));
+            }
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// 连续写入 values
+    fn write_array<T: Pod>(&self, address: usize, values: &[T]) -> Result<(), MemoryError> {
+        self.write_exact(address, bytemuck::cast_slice(values))
+    }
+}
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// 读取一段内存，读不到的部分为 None，跳到下一页或 maps 中下一个区域的开始
+pub fn read_lossy<R: MemoryReader>(reader: &R, maps: &[MapRange], address: usize, len: usize) -> Vec<Option<u8>> {
+    let mut res = Vec::with_capacity(len);
+    let mut buf = vec![0u8; len];
+    while res.len() < len {
+        let pos = address + res.len();
+        match reader.readbuf(pos, &mut buf[res.len()..]) {
+            Ok(n) if n > 0 => {
+                let done = res.len();
+                res.extend(buf[done..done + n].iter().map(|b| Some(*b)));
+            },
+            _ => {
+                let next = maps.iter().map(|m| m.address.0).filter(|start| *start > pos)
+                    .fold((pos / PAGE_SIZE + 1) * PAGE_SIZE, std::cmp::min);
+                let skip = std::cmp::min(next - pos, len - res.len());
+                res.extend(std::iter::repeat_n(None, skip));
+            },
+        }
+    }
+    res
+}
+
+/// 从任意偏移的字节中取出 T，不要求对齐，长度不够时返回 None
+pub fn from_bytes<T: Pod>(buf: &[u8]) -> Option<T> {
+    buf.get(..std::mem::size_of::<T>()).map(bytemuck::pod_read_unaligned)
 }
 
 #[derive(Debug)]
 pub enum MemoryError {
     IoError(String),
 
+    ShortReadError(String),
+    ShortWriteError(String),
+    ForceWriteError(String),
+    VerifyError(String),
+
     PreadError(String),
     PwriteError(String),
 
@@ -24,6 +129,10 @@ pub enum MemoryError {
     PtraceDettachError(String),
     PtraceReadError(String),
     PtraceWriteError(String),
+    PtraceRegsError(String),
+    PtraceSyscallError(String),
+    WatchpointError(String),
+    BreakpointError(String),
 
     ProcessVmError(String),
     ProcessVmReadError(String),
@@ -33,6 +142,15 @@ pub enum MemoryError {
     ProcReadError(String),
     ProcWriteError(String),
     ProcUninitError(String),
+
+    CoreFileError(String),
+    CoreParseError(String),
+    CoreReadError(String),
+
+    MockReadError(String),
+    MockWriteError(String),
+
+    SnapshotReadError(String),
 }
 
 impl std::fmt::Display for MemoryError {
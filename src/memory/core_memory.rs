@@ -0,0 +1,217 @@
+use std::fs::File;
+use std::path::Path;
+use crate::coredump::PR_PID_OFFSET;
+use crate::elf::{self, ElfHeader, FileMapping, ProgramHeader, SectionHeader};
+use crate::process::{permissions, MapRange, Process};
+use crate::searcher::{MemorySearcher, SearchError, SearchRule};
+use super::{MemoryError, MemoryReader, Pod};
+
+/// core 文件中一个 PT_LOAD 段
+#[derive(Debug, Clone)]
+struct CoreSegment {
+    vaddr: usize,
+    memsz: usize,
+    offset: u64,
+    filesz: usize,
+}
+
+/// ELF core 文件后端，只读
+pub struct CoreMemory {
+    pub process: Process,
+    pub machine: u16,
+    file: File,
+    segments: Vec<CoreSegment>,
+}
+
+fn pread_exact(file: &File, buf: &mut [u8], offset: u64) -> Result<(), MemoryError> {
+    let mut done = 0;
+    while done < buf.len() {
+        let len = nix::sys::uio::pread(file, &mut buf[done..], (offset + done as u64) as i64)
+            .map_err(|e| MemoryError::PreadError(e.to_string()))?;
+        if len == 0 {
+            return Err(MemoryError::CoreParseError(format!("Unexpected EOF at {:#x}", offset + done as u64)));
+        }
+        done += len;
+    }
+    Ok(())
+}
+
+/// 检查 [offset, offset + size) 在文件内，core 文件中的大小不可信，分配前必须检查
+fn check_range(offset: u64, size: u64, file_len: u64) -> Result<(), MemoryError> {
+    match offset.checked_add(size) {
+        Some(end) if end <= file_len => Ok(()),
+        _ => Err(MemoryError::CoreParseError(format!("Range {offset:#x}+{size:#x} beyond file length {file_len:#x}"))),
+    }
+}
+
+impl CoreMemory {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MemoryError> {
+        let file = File::open(path).map_err(|e| MemoryError::CoreFileError(e.to_string()))?;
+        let file_len = file.metadata().map_err(|e| MemoryError::CoreFileError(e.to_string()))?.len();
+
+        let mut ehdr_raw = [0u8; elf::EHDR_SIZE];
+        pread_exact(&file, &mut ehdr_raw, 0)?;
+        let ehdr = ElfHeader::parse(&ehdr_raw).map_err(|e| MemoryError::CoreParseError(e.to_string()))?;
+        if ehdr.e_type != elf::ET_CORE {
+            return Err(MemoryError::CoreParseError(format!("Not a core file, e_type: {}", ehdr.e_type)));
+        }
+        if (ehdr.e_phentsize as usize) < elf::PHDR_SIZE {
+            return Err(MemoryError::CoreParseError(format!("Bad e_phentsize: {}", ehdr.e_phentsize)));
+        }
+
+        let phnum = if ehdr.e_phnum == elf::PN_XNUM {
+            check_range(ehdr.e_shoff, elf::SHDR_SIZE as u64, file_len)?;
+            let mut shdr_raw = [0u8; elf::SHDR_SIZE];
+            pread_exact(&file, &mut shdr_raw, ehdr.e_shoff)?;
+            SectionHeader::parse(&shdr_raw).map_err(|e| MemoryError::CoreParseError(e.to_string()))?.sh_info as u64
+        } else {
+            ehdr.e_phnum as u64
+        };
+        let phdrs_size = ehdr.e_phentsize as u64 * phnum;
+        check_range(ehdr.e_phoff, phdrs_size, file_len)?;
+        let mut phdrs_raw = vec![0u8; phdrs_size as usize];
+        pread_exact(&file, &mut phdrs_raw, ehdr.e_phoff)?;
+
+        let mut pid = 0u32;
+        let mut files = Vec::<FileMapping>::new();
+        let mut loads = Vec::<ProgramHeader>::new();
+        for raw in phdrs_raw.chunks_exact(ehdr.e_phentsize as usize) {
+            let phdr = ProgramHeader::parse(raw).map_err(|e| MemoryError::CoreParseError(e.to_string()))?;
+            match phdr.p_type {
+                elf::PT_LOAD => {
+                    check_range(phdr.p_offset, phdr.p_filesz, file_len)?;
+                    loads.push(phdr);
+                },
+                elf::PT_NOTE => {
+                    check_range(phdr.p_offset, phdr.p_filesz, file_len)?;
+                    let mut data = vec![0u8; phdr.p_filesz as usize];
+                    pread_exact(&file, &mut data, phdr.p_offset)?;
+                    for note in elf::notes(&data) {
+                        let note = note.map_err(|e| MemoryError::CoreParseError(e.to_string()))?;
+                        if note.name != b"CORE" {
+                            continue;
+                        }
+                        match note.n_type {
+                            elf::NT_FILE => {
+                                files = elf::parse_nt_file(note.desc).map_err(|e| MemoryError::CoreParseError(e.to_string()))?;
+                            },
+                            // elf_prstatus.pr_pid，第一个线程就是主线程
+                            elf::NT_PRSTATUS if pid == 0 => {
+                                pid = elf::u32_at(note.desc, PR_PID_OFFSET).map_err(|e| MemoryError::CoreParseError(e.to_string()))?;
+                            },
+                            _ => {}
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let mut process = Process::new(pid);
+        let mut segments = Vec::with_capacity(loads.len());
+        for phdr in loads {
+            let start = phdr.p_vaddr as usize;
+            let end = start.checked_add(phdr.p_memsz as usize)
+                .ok_or(MemoryError::CoreParseError(format!("Segment {start:#x}+{:#x} overflows", phdr.p_memsz)))?;
+
+            let mut perms = 0u8;
+            if phdr.p_flags & elf::PF_R != 0 { perms |= permissions::READABLE; }
+            if phdr.p_flags & elf::PF_W != 0 { perms |= permissions::WRITABLTE; }
+            if phdr.p_flags & elf::PF_X != 0 { perms |= permissions::EXECUTABLE; }
+
+            let (pathname, offset) = match files.iter().find(|f| f.start as usize <= start && start < f.end as usize) {
+                Some(f) => (f.path.clone(), f.file_offset.saturating_add(start as u64 - f.start) as usize),
+                None => (String::new(), 0),
+            };
+
+            process.maps.push(MapRange::from_parts((start, end), perms, offset, pathname));
+
+            segments.push(CoreSegment {
+                vaddr: start,
+                memsz: phdr.p_memsz as usize,
+                offset: phdr.p_offset,
+                filesz: phdr.p_filesz as usize,
+            });
+        }
+
+        process.classify();
+
+        Ok(CoreMemory {
+            process,
+            machine: ehdr.e_machine,
+            file,
+            segments,
+        })
+    }
+
+    fn segment(&self, address: usize) -> Option<&CoreSegment> {
+        self.segments.iter().find(|s| s.vaddr <= address && address - s.vaddr < s.memsz)
+    }
+}
+
+impl MemoryReader for CoreMemory {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        let mut res = T::zeroed();
+        let size = std::mem::size_of::<T>();
+
+        let len = self.readbuf(address, bytemuck::bytes_of_mut(&mut res))?;
+        if len != size {
+            Err(MemoryError::CoreReadError(format!("Short read, result: {len}")))
+        } else {
+            Ok(res)
+        }
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let Some(seg) = address.checked_add(done).and_then(|curr| self.segment(curr)) else {
+                break;
+            };
+            let seg_off = address + done - seg.vaddr;
+            let len = std::cmp::min(buf.len() - done, seg.memsz - seg_off);
+            let dst = &mut buf[done..done + len];
+
+            // filesz 之后的部分在 core 中没有保存，按 0 处理
+            let in_file = seg.filesz.saturating_sub(seg_off).min(len);
+            if in_file > 0 {
+                pread_exact(&self.file, &mut dst[..in_file], seg.offset + seg_off as u64)?;
+            }
+            dst[in_file..].fill(0);
+            done += len;
+        }
+
+        if done == 0 {
+            Err(MemoryError::CoreReadError(format!("Address {address:#x} not in core")))
+        } else {
+            Ok(done)
+        }
+    }
+}
+
+impl MemorySearcher for CoreMemory {
+    fn search<T: SearchRule, const N: usize>(&self, rule: T, filter: Option<impl Fn(&MapRange) -> bool>) -> Result<Vec<usize>, SearchError>
+    {
+        let mut buff = Box::new([0u8;N]);
+        let mut res = Vec::<usize>::new();
+        for map in self.process.maps.iter() {
+            if !map.readable() {
+                continue;
+            }
+            if let Some(f) = filter.as_ref() && !f(map) {
+                continue;
+            }
+            let addr = map.address;
+            let mut offset = 0;
+            while offset + addr.0 < addr.1 {
+                let len = std::cmp::min(N, addr.1 - addr.0 - offset);
+                let read_bytes = self.readbuf(addr.0 + offset, &mut buff[..len])
+                    .map_err(|e|SearchError::ReadError(e.to_string()))?;
+                res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
+                offset += read_bytes;
+            }
+        }
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,162 @@
+use std::cell::Cell;
+use nix::libc;
+use crate::process::{MapRange, Process};
+
+use super::{MemoryError, MemoryReader, MemoryWriter, Pod};
+use super::proc_memory::ProcMemory;
+use super::process_vm_memory::ProcessVmMemory;
+use super::ptrace_memory::PtraceMemory;
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// 写入的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteStrategy {
+    /// process_vm_writev，只能写可写的页面
+    Direct,
+    /// 写 /proc/pid/mem，内核忽略页面保护
+    ProcMem,
+    /// PTRACE_POKEDATA，同样忽略页面保护
+    PtracePoke,
+    /// 用远程系统调用临时 mprotect 成可写，写完恢复原来的权限
+    Mprotect,
+}
+
+/// 可以写只读、可执行页面的后端，按顺序尝试各种写入方式
+///
+/// PtracePoke 和 Mprotect 需要 with_ptrace，没有时跳过。读取用 process_vm_readv。
+pub struct ForceMemory<'a> {
+    pub process: Process,
+    vm: ProcessVmMemory,
+    /// 打不开时为 None，跳过 ProcMem
+    proc: Option<ProcMemory>,
+    ptrace: Option<&'a PtraceMemory>,
+    strategies: Vec<WriteStrategy>,
+    last: Cell<Option<WriteStrategy>>,
+}
+
+impl<'a> ForceMemory<'a> {
+    pub fn new(pid: u32) -> Self {
+        let mut proc = ProcMemory::new(pid);
+        ForceMemory {
+            process: Process::new(pid),
+            vm: ProcessVmMemory::new(pid),
+            proc: proc.open().ok().map(|_| proc),
+            ptrace: None,
+            strategies: vec![WriteStrategy::Direct, WriteStrategy::ProcMem, WriteStrategy::PtracePoke, WriteStrategy::Mprotect],
+            last: Cell::new(None),
+        }
+    }
+
+    /// 使用已附加的 PtraceMemory 做 POKE 和远程 mprotect
+    pub fn with_ptrace(mut self, ptrace: &'a PtraceMemory) -> Self {
+        self.ptrace = Some(ptrace);
+        self
+    }
+
+    /// 依次尝试的方式，默认 Direct、ProcMem、PtracePoke、Mprotect
+    pub fn strategies(mut self, strategies: &[WriteStrategy]) -> Self {
+        self.strategies = strategies.to_vec();
+        self
+    }
+
+    /// 上一次成功写入使用的方式
+    pub fn last_strategy(&self) -> Option<WriteStrategy> {
+        self.last.get()
+    }
+
+    /// 写满 buf，返回成功的方式，全部失败时返回每种方式的错误
+    pub fn force_writebuf(&self, address: usize, buf: &[u8]) -> Result<WriteStrategy, MemoryError> {
+        let mut errors = Vec::new();
+        for strategy in self.strategies.iter().copied() {
+            match self.write_with(strategy, address, buf) {
+                Ok(()) => {
+                    self.last.set(Some(strategy));
+                    return Ok(strategy);
+                },
+                Err(e) => errors.push(format!("{:?}: {}", strategy, e)),
+            }
+        }
+        Err(MemoryError::ForceWriteError(format!("{address:#x}: {}", errors.join(", "))))
+    }
+
+    fn write_with(&self, strategy: WriteStrategy, address: usize, buf: &[u8]) -> Result<(), MemoryError> {
+        match strategy {
+            WriteStrategy::Direct => self.vm.write_exact(address, buf),
+            WriteStrategy::ProcMem => match self.proc.as_ref() {
+                Some(proc) => proc.write_exact(address, buf),
+                None => Err(MemoryError::ProcUninitError("Cannot open /proc/pid/mem".to_string())),
+            },
+            WriteStrategy::PtracePoke => self.ptrace()?.write_exact(address, buf),
+            WriteStrategy::Mprotect => self.mprotect_write(address, buf),
+        }
+    }
+
+    fn ptrace(&self) -> Result<&'a PtraceMemory, MemoryError> {
+        self.ptrace.ok_or(MemoryError::PtraceError("No ptrace backend".to_string()))
+    }
+
+    /// 把覆盖的不可写映射临时加上写权限，直接写入后恢复
+    fn mprotect_write(&self, address: usize, buf: &[u8]) -> Result<(), MemoryError> {
+        let ptrace = self.ptrace()?;
+        let start = address / PAGE_SIZE * PAGE_SIZE;
+        let end = (address + buf.len()).next_multiple_of(PAGE_SIZE);
+        let maps = self.process.read_maps().map_err(|e|MemoryError::ForceWriteError(format!("{:?}", e)))?;
+
+        let mut changed = Vec::new();
+        for map in maps.iter().filter(|m| m.address.0 < end && start < m.address.1 && !m.writable()) {
+            let from = std::cmp::max(start, map.address.0);
+            let to = std::cmp::min(end, map.address.1);
+            let prot = prot(map);
+            if let Err(e) = ptrace.remote_mprotect(from, to - from, prot | libc::PROT_WRITE) {
+                restore(ptrace, &changed);
+                return Err(e);
+            }
+            changed.push((from, to - from, prot));
+        }
+        let res = self.vm.write_exact(address, buf);
+        restore(ptrace, &changed);
+        res
+    }
+}
+
+fn prot(map: &MapRange) -> i32 {
+    let mut prot = libc::PROT_NONE;
+    if map.readable() {
+        prot |= libc::PROT_READ;
+    }
+    if map.writable() {
+        prot |= libc::PROT_WRITE;
+    }
+    if map.executable() {
+        prot |= libc::PROT_EXEC;
+    }
+    prot
+}
+
+/// 恢复 mprotect 之前的权限，尽力而为
+fn restore(ptrace: &PtraceMemory, changed: &[(usize, usize, i32)]) {
+    for (address, len, prot) in changed {
+        let _ = ptrace.remote_mprotect(*address, *len, *prot);
+    }
+}
+
+impl MemoryReader for ForceMemory<'_> {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        self.vm.read(address)
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        self.vm.readbuf(address, buf)
+    }
+}
+
+impl MemoryWriter for ForceMemory<'_> {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        self.force_writebuf(address, bytemuck::bytes_of(value)).map(|_| ())
+    }
+
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        self.force_writebuf(address, buf).map(|_| buf.len())
+    }
+}
@@ -0,0 +1,147 @@
+use std::cell::RefCell;
+use crate::process::{permissions, MapRange, Process};
+use crate::searcher::{MemorySearcher, SearchError, SearchRule};
+use super::{MemoryError, MemoryReader, Pod, MemoryWriter};
+
+/// (起始地址, 数据)
+type Region = (usize, RefCell<Vec<u8>>);
+
+/// 内存中的假进程，按 MapRange 划分区域，用于测试
+#[derive(Default)]
+pub struct MockMemory {
+    pub process: Process,
+    regions: Vec<Region>,
+}
+
+impl MockMemory {
+    pub fn new() -> Self {
+        MockMemory {
+            process: Process::new(0),
+            regions: Vec::new(),
+        }
+    }
+
+    /// 添加一段区域，perms 使用 process::permissions 中的位
+    pub fn add_region(&mut self, start: usize, data: Vec<u8>, perms: u8, pathname: &str) -> &mut Self {
+        let end = start + data.len();
+        self.process.maps.push(MapRange::from_parts((start, end), perms, 0, pathname.to_string()));
+        self.process.maps.sort_by_key(|m| m.address.0);
+        self.process.classify();
+        self.regions.push((start, RefCell::new(data)));
+        self.regions.sort_by_key(|r| r.0);
+        self
+    }
+
+    /// 添加一段可读写的匿名区域
+    pub fn add_rw(&mut self, start: usize, data: Vec<u8>) -> &mut Self {
+        self.add_region(start, data, permissions::READABLE | permissions::WRITABLTE, "")
+    }
+
+    fn region(&self, address: usize) -> Option<(&MapRange, &Region)> {
+        let map = self.process.maps.iter().find(|m| m.address.0 <= address && address < m.address.1)?;
+        let region = self.regions.iter().find(|r| r.0 == map.address.0)?;
+        Some((map, region))
+    }
+}
+
+impl MemoryReader for MockMemory {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        let mut res = T::zeroed();
+        let size = std::mem::size_of::<T>();
+
+        let len = self.readbuf(address, bytemuck::bytes_of_mut(&mut res))?;
+        if len != size {
+            Err(MemoryError::MockReadError(format!("Short read, result: {len}")))
+        } else {
+            Ok(res)
+        }
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let curr = address + done;
+            let Some((map, (start, data))) = self.region(curr) else {
+                break;
+            };
+            if !map.readable() {
+                break;
+            }
+            let data = data.borrow();
+            let off = curr - start;
+            let len = std::cmp::min(buf.len() - done, data.len() - off);
+            buf[done..done + len].copy_from_slice(&data[off..off + len]);
+            done += len;
+        }
+
+        if done == 0 {
+            Err(MemoryError::MockReadError(format!("Address {address:#x} not readable")))
+        } else {
+            Ok(done)
+        }
+    }
+}
+
+impl MemoryWriter for MockMemory {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        let size = std::mem::size_of::<T>();
+
+        let len = self.writebuf(address, bytemuck::bytes_of(value))?;
+        if len != size {
+            Err(MemoryError::MockWriteError(format!("Short write, result: {len}")))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let curr = address + done;
+            let Some((map, (start, data))) = self.region(curr) else {
+                break;
+            };
+            if !map.writable() {
+                break;
+            }
+            let mut data = data.borrow_mut();
+            let off = curr - start;
+            let len = std::cmp::min(buf.len() - done, data.len() - off);
+            data[off..off + len].copy_from_slice(&buf[done..done + len]);
+            done += len;
+        }
+
+        if done == 0 {
+            Err(MemoryError::MockWriteError(format!("Address {address:#x} not writable")))
+        } else {
+            Ok(done)
+        }
+    }
+}
+
+impl MemorySearcher for MockMemory {
+    fn search<T: SearchRule, const N: usize>(&self, rule: T, filter: Option<impl Fn(&MapRange) -> bool>) -> Result<Vec<usize>, SearchError>
+    {
+        let mut buff = Box::new([0u8;N]);
+        let mut res = Vec::<usize>::new();
+        for map in self.process.maps.iter() {
+            if !map.readable() {
+                continue;
+            }
+            if let Some(f) = filter.as_ref() && !f(map) {
+                continue;
+            }
+            let addr = map.address;
+            let mut offset = 0;
+            while offset + addr.0 < addr.1 {
+                let len = std::cmp::min(N, addr.1 - addr.0 - offset);
+                let read_bytes = self.readbuf(addr.0 + offset, &mut buff[..len])
+                    .map_err(|e|SearchError::ReadError(e.to_string()))?;
+                res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
+                offset += read_bytes;
+            }
+        }
+
+        Ok(res)
+    }
+}
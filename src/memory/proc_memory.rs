@@ -2,9 +2,8 @@ use crate::process::{MapRange, Process};
 use crate::searcher::{MemorySearcher, SearchError, SearchRule};
 use std::io::IoSlice;
 use std::os::fd::AsFd;
-use std::{fs::File, io::IoSliceMut};
-use std::mem::MaybeUninit;
-use super::{MemoryError, MemoryReader, MemoryWriter};
+use std::{fs::{File, OpenOptions}, io::IoSliceMut};
+use super::{MemoryError, MemoryReader, Pod, MemoryWriter};
 
 pub struct ProcMemory {
     pub process: Process,
@@ -19,10 +18,14 @@ impl ProcMemory {
         }
     }
 
+    /// 以读写方式打开 /proc/pid/mem，没有写权限时退回只读
     pub fn open(&mut self) -> Result<(), MemoryError> {
         if self.file.is_none() {
             let path = format!("/proc/{}/mem", self.process.pid);
-            self.file = Some(File::open(path).map_err(|e| MemoryError::ProcMemError(e.to_string()))?)
+            let file = OpenOptions::new().read(true).write(true).open(&path)
+                .or_else(|_| File::open(&path))
+                .map_err(|e| MemoryError::ProcMemError(e.to_string()))?;
+            self.file = Some(file);
         }
 
         Ok(())
@@ -30,23 +33,20 @@ impl ProcMemory {
 }
 
 impl MemoryReader for ProcMemory {
-    fn read<T: Sized + Copy>(&self, address: usize) -> Result<T, MemoryError> {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
         match self.file {
             Some(_) => {
                 let fd = self.file.as_ref().unwrap();
-                let mut res = MaybeUninit::<T>::uninit();
+                let mut res = T::zeroed();
                 let size = std::mem::size_of::<T>();
 
-                let len = nix::sys::uio::pread(fd, unsafe {
-                    std::slice::from_raw_parts_mut(res.as_mut_ptr() as *mut u8, size)
-                }, address as i64).map_err(|e|MemoryError::PreadError(e.to_string()))?;
+                let len = nix::sys::uio::pread(fd, bytemuck::bytes_of_mut(&mut res), address as i64)
+                    .map_err(|e|MemoryError::PreadError(e.to_string()))?;
 
                 if len != size {
                     Err(MemoryError::ProcReadError(format!("Short pread, result: {len}").to_string()))
                 } else {
-                    Ok(unsafe {
-                        res.assume_init()
-                    })
+                    Ok(res)
                 }
             },
             None => Err(MemoryError::ProcUninitError("Uninit file".to_string()))
@@ -58,9 +58,9 @@ impl MemoryReader for ProcMemory {
             Some(_) => {
                 let fd = self.file.as_ref().unwrap();
                 let mut bufs = [ IoSliceMut::new(buf) ];
-                let len = nix::sys::uio::preadv(fd.as_fd(), bufs.as_mut_slice(), address as i64).unwrap();//.map_err(|e|MemoryError::PreadError(e.to_string()))?;
+                let len = nix::sys::uio::preadv(fd.as_fd(), bufs.as_mut_slice(), address as i64).map_err(|e|MemoryError::PreadError(e.to_string()))?;
 
-                if len <= 0 {
+                if len == 0 {
                     Err(MemoryError::ProcReadError(format!("Short read, result: {len}").to_string()))
                 }else{
                     Ok(len)
@@ -72,15 +72,14 @@ impl MemoryReader for ProcMemory {
 }
 
 impl MemoryWriter for ProcMemory {
-    fn write<T: Sized>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
         match self.file {
             Some(_) => {
                 let fd = self.file.as_ref().unwrap();
                 let size = std::mem::size_of::<T>();
 
-                let len = nix::sys::uio::pwrite(fd, unsafe {
-                    std::slice::from_raw_parts(value as *const T as *const u8, size)
-                }, address as i64).map_err(|e|MemoryError::PwriteError(e.to_string()))?;
+                let len = nix::sys::uio::pwrite(fd, bytemuck::bytes_of(value), address as i64)
+                    .map_err(|e|MemoryError::PwriteError(e.to_string()))?;
 
                 if len != size {
                     Err(MemoryError::ProcWriteError("Short pwrite".to_string()))
@@ -97,7 +96,7 @@ impl MemoryWriter for ProcMemory {
             Some(_) => {
                 let fd = self.file.as_ref().unwrap();
                 let bufs = [ IoSlice::new(buf) ];
-                nix::sys::uio::pwritev(fd, &bufs, address as i64).map_err(|e|MemoryError::PreadError(e.to_string()))
+                nix::sys::uio::pwritev(fd, &bufs, address as i64).map_err(|e|MemoryError::PwriteError(e.to_string()))
             },
             None => Err(MemoryError::ProcUninitError("Uninit file".to_string()))
         }
@@ -109,35 +108,24 @@ impl MemorySearcher for ProcMemory {
     {
         let mut buff = Box::new([0u8;N]);
         let mut res = Vec::<usize>::new();
-        match filter {
-            Some(f) => {
-                for i in 0..self.process.maps.len() {
-                    if f(&self.process.maps[i]) {
-                        let addr = self.process.maps[i].address;
-                        let mut offset = 0;
-                        while offset + addr.0 < addr.1 {
-                            let read_bytes = self.readbuf(addr.0 + offset, buff.as_mut_slice())
-                                .map_err(|e|SearchError::ReadError(e.to_string()))?;
-                            res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
-                            offset += read_bytes;
-                        }
-                    }
-                }
-            },
-            None => {
-                for i in 0..self.process.maps.len() {
-                    let addr = self.process.maps[i].address;
-                    let mut offset = 0;
-                    while offset + addr.0 < addr.1 {
-                        let read_bytes = self.readbuf(addr.0 + offset, buff.as_mut_slice())
-                            .map_err(|e|SearchError::ReadError(e.to_string()))?;
-                        res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
-                        offset += read_bytes;
-                    }
-                }
+        for map in self.process.maps.iter() {
+            if !map.readable() {
+                continue;
+            }
+            if let Some(f) = filter.as_ref() && !f(map) {
+                continue;
+            }
+            let addr = map.address;
+            let mut offset = 0;
+            while offset + addr.0 < addr.1 {
+                let len = std::cmp::min(N, addr.1 - addr.0 - offset);
+                let read_bytes = self.readbuf(addr.0 + offset, &mut buff[..len])
+                    .map_err(|e|SearchError::ReadError(e.to_string()))?;
+                res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
+                offset += read_bytes;
             }
         }
 
         Ok(res)
     }
-}
\ No newline at end of file
+}
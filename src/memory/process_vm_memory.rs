@@ -1,11 +1,11 @@
-use std::{io::{IoSlice, IoSliceMut}, mem::{size_of, MaybeUninit}};
+use std::{io::{IoSlice, IoSliceMut}, mem::size_of};
 use nix::{sys::uio::{process_vm_readv, process_vm_writev, RemoteIoVec, }, unistd::Pid};
 use crate::{process::{MapRange, Process}, searcher::{MemorySearcher, SearchError, SearchRule}};
 
-use super::{MemoryError, MemoryReader, MemoryWriter};
+use super::{MemoryError, MemoryReader, Pod, MemoryWriter};
 
 pub struct ProcessVmMemory {
-    process: Process,
+    pub process: Process,
 }
 
 impl ProcessVmMemory {
@@ -17,13 +17,11 @@ impl ProcessVmMemory {
 }
 
 impl MemoryReader for ProcessVmMemory {
-    fn read<T: Sized + Copy>(&self, address: usize) -> Result<T, MemoryError> {
-        let mut res = MaybeUninit::<T>::uninit();
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        let mut res = T::zeroed();
         let size = size_of::<T>();
 
-        let mut local_iov = [ IoSliceMut::new( unsafe {
-            std::slice::from_raw_parts_mut(res.as_mut_ptr() as *mut u8, size)
-        }) ];
+        let mut local_iov = [ IoSliceMut::new(bytemuck::bytes_of_mut(&mut res)) ];
 
         let remote_iov = [ RemoteIoVec{
             base: address,
@@ -38,9 +36,7 @@ impl MemoryReader for ProcessVmMemory {
         }else if len != size {
             Err(MemoryError::ProcessVmReadError("Short read".to_string()))
         }else{
-            Ok(unsafe {
-                res.assume_init()
-            })
+            Ok(res)
         }
     }
 
@@ -66,11 +62,9 @@ impl MemoryReader for ProcessVmMemory {
 }
 
 impl MemoryWriter for ProcessVmMemory {
-    fn write<T: Sized + Copy>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
         let size = size_of::<T>();
-        let local_iov = [ IoSlice::new(unsafe {
-            std::slice::from_raw_parts(value as *const T as *const u8, size)
-        }) ]; 
+        let local_iov = [ IoSlice::new(bytemuck::bytes_of(value)) ];
         let remote_iov = [ RemoteIoVec{
             base: address,
             len: size
@@ -114,37 +108,24 @@ impl MemorySearcher for ProcessVmMemory {
     {
         let mut buff = Box::new([0u8;N]);
         let mut res = Vec::<usize>::new();
-        match filter {
-            Some(f) => {
-                for i in 0..self.process.maps.len() {
-                    if f(&self.process.maps[i]) {
-                        let addr = self.process.maps[i].address;
-                        let len = addr.1 - addr.0 + 1;
-                        let mut offset = 0;
-                        while offset < len {
-                            let read_bytes = self.readbuf(addr.0 + offset, buff.as_mut_slice())
-                                .map_err(|e|SearchError::ReadError(e.to_string()))?;
-                            offset += read_bytes;
-                            res.extend(rule.search(buff.as_slice(), read_bytes));
-                        }
-                    }
-                }
-            },
-            None => {
-                for i in 0..self.process.maps.len() {
-                    let addr = self.process.maps[i].address;
-                    let len = addr.1 - addr.0 + 1;
-                    let mut offset = 0;
-                    while offset < len {
-                        let read_bytes = self.readbuf(addr.0 + offset, buff.as_mut_slice())
-                            .map_err(|e|SearchError::ReadError(e.to_string()))?;
-                        offset += read_bytes;
-                        res.extend(rule.search(buff.as_slice(), read_bytes));
-                    }
-                }
+        for map in self.process.maps.iter() {
+            if !map.readable() {
+                continue;
+            }
+            if let Some(f) = filter.as_ref() && !f(map) {
+                continue;
+            }
+            let addr = map.address;
+            let mut offset = 0;
+            while offset + addr.0 < addr.1 {
+                let len = std::cmp::min(N, addr.1 - addr.0 - offset);
+                let read_bytes = self.readbuf(addr.0 + offset, &mut buff[..len])
+                    .map_err(|e|SearchError::ReadError(e.to_string()))?;
+                res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
+                offset += read_bytes;
             }
         }
 
         Ok(res)
     }
-}
\ No newline at end of file
+}
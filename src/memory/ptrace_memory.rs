@@ -1,100 +1,584 @@
-use std::mem::MaybeUninit;
+use std::cell::RefCell;
 
 use nix::{libc, sys};
+use nix::sys::signal::Signal;
+use nix::sys::wait::{WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
+use crate::coredump::ThreadStatus;
 use crate::process::{MapRange, Process};
+use crate::registers::{self, FpRegs, GeneralRegisters, RegisterRoot, RegisterSet, Regs};
 use crate::searcher::{MemorySearcher, SearchError, SearchRule};
 
-use super::{MemoryError, MemoryReader, MemoryWriter};
+use super::{MemoryError, MemoryReader, Pod, MemoryWriter};
 
+/// 被跟踪线程的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceState {
+    Running,
+    /// signal 为停止时截获、恢复时需要重新发送的信号
+    Stopped { signal: Option<Signal> },
+}
+
+/// SIGTRAP 的 si_code，见 siginfo.h
+pub const TRAP_BRKPT: i32 = 1;
+/// 单步
+pub const TRAP_TRACE: i32 = 2;
+/// 硬件断点
+pub const TRAP_HWBKPT: i32 = 4;
+/// x86 上 int3 产生的 SIGTRAP
+pub const SI_KERNEL: i32 = 0x80;
+
+/// offsetof(struct user, u_debugreg)
+#[cfg(target_arch = "x86_64")]
+const DEBUGREG_OFFSET: usize = 848;
+/// eflags.RF，恢复时跳过当前指令上的执行断点
+#[cfg(target_arch = "x86_64")]
+const EFLAGS_RF: u64 = 1 << 16;
+
+/// 系统调用指令，aarch64 为 svc #0
+#[cfg(target_arch = "aarch64")]
+const SYSCALL_INSN: &[u8] = &[0x01, 0x00, 0x00, 0xd4];
+/// 系统调用指令，x86_64 为 syscall
+#[cfg(not(target_arch = "aarch64"))]
+const SYSCALL_INSN: &[u8] = &[0x0f, 0x05];
+
+/// 调试异常造成的一次停止
+///
+/// 线程停下时立刻记录，对应的 SIGTRAP 不会在恢复时发给目标。
+/// 在被 take_traps 取走之前线程保持停止，cont 不会恢复它。
+#[derive(Debug, Clone)]
+pub struct Trap {
+    pub tid: u32,
+    /// siginfo 的 si_code
+    pub code: i32,
+    /// 停下时的寄存器
+    pub regs: Regs,
+    /// 硬件断点命中时的 DR6，记录后清零
+    pub debug_status: u64,
+}
+
+/// 基于 PTRACE_SEIZE 的后端
+///
+/// seize 不会让线程停下，读写前用 PTRACE_INTERRUPT 停住主线程，完成后再恢复。
+/// 需要连续操作时先调用 stop_world，结束后调用 resume。
+/// 两次操作之间目标收到的信号会让线程停在信号投递处，直到下一次 stop 或 poll_stops 处理它，
+/// 长时间不操作时应定期调用 poll_stops。
+/// drop 时自动 detach。ptrace 请求只能来自 seize 的那个线程，不要跨线程使用。
 pub struct PtraceMemory {
-    process: Process,
-    is_attach: bool,
+    pub process: Process,
+    /// 已 seize 的线程，主线程在最前
+    threads: RefCell<Vec<(u32, TraceState)>>,
+    /// 还没被取走的调试异常
+    traps: RefCell<Vec<Trap>>,
+}
+
+fn tgkill(pid: u32, tid: u32, signal: Signal) -> Result<(), MemoryError> {
+    let res = unsafe { libc::syscall(libc::SYS_tgkill, pid as libc::pid_t, tid as libc::pid_t, signal as libc::c_int) };
+    if res < 0 {
+        return Err(MemoryError::PtraceError(nix::errno::Errno::last().to_string()));
+    }
+    Ok(())
 }
 
 impl PtraceMemory {
     pub fn new(pid: u32) -> Self {
-        PtraceMemory { 
+        PtraceMemory {
             process: Process::new(pid),
-            is_attach: false,
+            threads: RefCell::new(Vec::new()),
+            traps: RefCell::new(Vec::new()),
         }
     }
 
+    fn seize(&self, tid: u32) -> Result<(), nix::errno::Errno> {
+        sys::ptrace::seize(Pid::from_raw(tid as i32), sys::ptrace::Options::empty())?;
+        self.threads.borrow_mut().push((tid, TraceState::Running));
+        Ok(())
+    }
+
+    /// seize 主线程，已经附加时什么都不做
     pub fn attach(&self) -> Result<(), MemoryError> {
-        if self.is_attach {
-            Ok(())
-        } else {    
-            let pid = Pid::from_raw(self.process.pid as i32);
-            sys::ptrace::attach(pid).map_err(|e|MemoryError::PtraceError(e.to_string()))?;
-            sys::wait::waitpid(pid, None).map_err(|e|MemoryError::PtraceAttachError(e.to_string()))?;
-            Ok(())
+        if self.is_attached() {
+            return Ok(());
         }
+        self.seize(self.process.pid).map_err(|e|MemoryError::PtraceAttachError(e.to_string()))
     }
 
-    pub fn dettach(&self) -> Result<(), MemoryError> {
-        if self.is_attach {
-            let pid = Pid::from_raw(self.process.pid as i32);
-            sys::ptrace::detach(pid, None).map_err(|e|MemoryError::PtraceDettachError(e.to_string()))
-        }else{
-            Ok(())
+    pub fn is_attached(&self) -> bool {
+        !self.threads.borrow().is_empty()
+    }
+
+    /// 附加到主线程以外的所有线程，返回新附加的线程
+    pub fn attach_threads(&self) -> Result<Vec<u32>, MemoryError> {
+        self.attach()?;
+        let tids = self.process.tids().map_err(|e|MemoryError::PtraceAttachError(format!("{:?}", e)))?;
+        let mut res = Vec::new();
+        for tid in tids {
+            if self.state(tid).is_some() {
+                continue;
+            }
+            match self.seize(tid) {
+                Ok(_) => res.push(tid),
+                // 线程已经退出
+                Err(nix::errno::Errno::ESRCH) => continue,
+                Err(e) => return Err(MemoryError::PtraceAttachError(e.to_string())),
+            }
         }
+        Ok(res)
     }
-}
 
-impl MemoryReader for PtraceMemory {
-    fn read<T: Sized + Copy>(&self, address: usize) -> Result<T, MemoryError> {
+    /// 已附加的线程，主线程在最前
+    pub fn attached_threads(&self) -> Vec<u32> {
+        self.threads.borrow().iter().map(|t| t.0).collect()
+    }
+
+    pub fn state(&self, tid: u32) -> Option<TraceState> {
+        self.threads.borrow().iter().find(|t| t.0 == tid).map(|t| t.1)
+    }
+
+    pub fn thread_states(&self) -> Vec<(u32, TraceState)> {
+        self.threads.borrow().clone()
+    }
+
+    fn set_state(&self, tid: u32, state: Option<TraceState>) {
+        let mut threads = self.threads.borrow_mut();
+        match state {
+            Some(state) => threads.iter_mut().filter(|t| t.0 == tid).for_each(|t| t.1 = state),
+            None => threads.retain(|t| t.0 != tid),
+        }
+    }
+
+    /// 停住一个线程，返回它之前是否在运行
+    pub fn stop(&self, tid: u32) -> Result<bool, MemoryError> {
+        match self.state(tid) {
+            None => return Err(MemoryError::PtraceError(format!("Thread {tid} not attached"))),
+            Some(TraceState::Stopped { .. }) => return Ok(false),
+            Some(TraceState::Running) => {},
+        }
+        let pid = Pid::from_raw(tid as i32);
+        sys::ptrace::interrupt(pid).map_err(|e|MemoryError::PtraceError(e.to_string()))?;
+        let mut kept = None;
+        loop {
+            let status = sys::wait::waitpid(pid, Some(WaitPidFlag::__WALL))
+                .map_err(|e|MemoryError::PtraceError(e.to_string()))?;
+            match status {
+                WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_STOP) => {
+                    self.set_state(tid, Some(TraceState::Stopped { signal: kept }));
+                    return Ok(true);
+                },
+                // 先收到了信号，INTERRUPT 还没生效。截下信号后不带信号继续，线程随即进入 PTRACE_EVENT_STOP，
+                // 不等它的话之后 cont 会让线程立刻停在没人处理的 PTRACE_EVENT_STOP 上
+                WaitStatus::Stopped(_, signal) => {
+                    if let Some(signal) = self.signal_stop(tid, signal)? {
+                        match kept {
+                            None => kept = Some(signal),
+                            // 只能保存一个信号，其余的重新排队，恢复后再投递
+                            Some(_) => tgkill(self.process.pid, tid, signal)?,
+                        }
+                    }
+                    match sys::ptrace::cont(pid, None) {
+                        Ok(_) | Err(nix::errno::Errno::ESRCH) => {},
+                        Err(e) => return Err(MemoryError::PtraceError(e.to_string())),
+                    }
+                },
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                    self.set_state(tid, None);
+                    return Err(MemoryError::PtraceError(format!("Thread {tid} exited")));
+                },
+                _ => continue,
+            }
+        }
+    }
+
+    /// 恢复一个停住的线程，有未取走的 Trap 时保持停止
+    pub fn cont(&self, tid: u32) -> Result<(), MemoryError> {
+        let Some(TraceState::Stopped { signal }) = self.state(tid) else {
+            return Ok(());
+        };
+        if self.traps.borrow().iter().any(|t| t.tid == tid) {
+            return Ok(());
+        }
+        match sys::ptrace::cont(Pid::from_raw(tid as i32), signal) {
+            Ok(_) => self.set_state(tid, Some(TraceState::Running)),
+            Err(nix::errno::Errno::ESRCH) => self.set_state(tid, None),
+            Err(e) => return Err(MemoryError::PtraceError(e.to_string())),
+        }
+        Ok(())
+    }
+
+    /// 单步执行一条指令，线程必须已停住
+    pub fn step(&self, tid: u32) -> Result<(), MemoryError> {
+        let Some(TraceState::Stopped { mut signal }) = self.state(tid) else {
+            return Err(MemoryError::PtraceError(format!("Thread {tid} not stopped")));
+        };
+        let pid = Pid::from_raw(tid as i32);
+        loop {
+            sys::ptrace::step(pid, None).map_err(|e|MemoryError::PtraceError(e.to_string()))?;
+            let status = sys::wait::waitpid(pid, Some(WaitPidFlag::__WALL))
+                .map_err(|e|MemoryError::PtraceError(e.to_string()))?;
+            match status {
+                WaitStatus::Stopped(_, Signal::SIGTRAP) => break,
+                // 单步之前先来了信号，留到恢复时发送，再重新单步
+                WaitStatus::Stopped(_, s) => signal = Some(s),
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                    self.set_state(tid, None);
+                    return Err(MemoryError::PtraceError(format!("Thread {tid} exited")));
+                },
+                _ => continue,
+            }
+        }
+        self.set_state(tid, Some(TraceState::Stopped { signal }));
+        Ok(())
+    }
+
+    /// 附加并停住所有线程，直到调用 resume
+    pub fn stop_world(&self) -> Result<(), MemoryError> {
+        self.attach_threads()?;
+        for tid in self.attached_threads() {
+            match self.stop(tid) {
+                Ok(_) => {},
+                // 其它线程可能在中途退出
+                Err(_) if tid != self.process.pid && self.state(tid).is_none() => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// 恢复所有停住的线程
+    pub fn resume(&self) -> Result<(), MemoryError> {
+        for tid in self.attached_threads() {
+            self.cont(tid)?;
+        }
+        Ok(())
+    }
+
+    /// 停住所有线程执行 f，之后只恢复这次停住的线程，已经 stop_world 的批量操作不受影响
+    pub fn stopped<R>(&self, f: impl FnOnce(&Self) -> Result<R, MemoryError>) -> Result<R, MemoryError> {
+        let was_stopped = self.thread_states().into_iter()
+            .filter(|(_, s)| *s != TraceState::Running)
+            .map(|(tid, _)| tid)
+            .collect::<Vec<_>>();
+        let res = self.stop_world().and_then(|_| f(self));
+        for tid in self.attached_threads() {
+            if !was_stopped.contains(&tid) {
+                self.cont(tid)?;
+            }
+        }
+        res
+    }
+
+    /// 线程停住时执行 f，原本在运行的话之后恢复
+    pub fn with_thread_stopped<R>(&self, tid: u32, f: impl FnOnce() -> Result<R, MemoryError>) -> Result<R, MemoryError> {
+        let was_running = self.stop(tid)?;
+        let res = f();
+        if was_running {
+            self.cont(tid)?;
+        }
+        res
+    }
+
+    /// 主线程停住时执行 f；已经被 stop_world 或 Trap 停住时直接执行，不再 interrupt
+    fn with_leader_stopped<R>(&self, f: impl FnOnce() -> Result<R, MemoryError>) -> Result<R, MemoryError> {
+        if let Some(TraceState::Stopped { .. }) = self.state(self.process.pid) {
+            return f();
+        }
         self.attach()?;
+        self.with_thread_stopped(self.process.pid, f)
+    }
 
-        let pid = Pid::from_raw(self.process.pid as i32);
-        let size = std::mem::size_of::<T>();
-        let word_size = std::mem::size_of::<libc::c_long>();
-        let mut res = MaybeUninit::<T>::uninit();
-        let ptr = res.as_mut_ptr() as *mut u8;
+    /// 不阻塞地检查运行中的线程是否自己停下了，返回新停下的线程
+    ///
+    /// 停下时截获的信号记在状态里，cont 时重新发送；不想发送的用 set_signal 清掉。
+    /// 调试异常不会发送给目标，用 take_traps 取走。
+    pub fn poll_stops(&self) -> Result<Vec<(u32, Option<Signal>)>, MemoryError> {
+        let mut res = Vec::new();
+        for (tid, state) in self.thread_states() {
+            if state != TraceState::Running {
+                continue;
+            }
+            let status = match sys::wait::waitpid(Pid::from_raw(tid as i32), Some(WaitPidFlag::__WALL | WaitPidFlag::WNOHANG)) {
+                Ok(status) => status,
+                Err(nix::errno::Errno::ECHILD) => {
+                    self.set_state(tid, None);
+                    continue;
+                },
+                Err(e) => return Err(MemoryError::PtraceError(e.to_string())),
+            };
+            let signal = match status {
+                WaitStatus::Stopped(_, signal) => self.signal_stop(tid, signal)?,
+                WaitStatus::PtraceEvent(..) => {
+                    self.set_state(tid, Some(TraceState::Stopped { signal: None }));
+                    None
+                },
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                    self.set_state(tid, None);
+                    continue;
+                },
+                _ => continue,
+            };
+            res.push((tid, signal));
+        }
+        Ok(res)
+    }
 
-        let mut offset = 0;
+    /// 线程因信号停下，调试异常记为 Trap，其它信号留到恢复时发送，返回要发送的信号
+    fn signal_stop(&self, tid: u32, signal: Signal) -> Result<Option<Signal>, MemoryError> {
+        self.set_state(tid, Some(TraceState::Stopped { signal: None }));
+        if signal != Signal::SIGTRAP {
+            self.set_signal(tid, Some(signal));
+            return Ok(Some(signal));
+        }
+        let code = sys::ptrace::getsiginfo(Pid::from_raw(tid as i32))
+            .map_err(|e|MemoryError::PtraceError(e.to_string()))?
+            .si_code;
+        if ![TRAP_BRKPT, TRAP_TRACE, TRAP_HWBKPT, SI_KERNEL].contains(&code) {
+            // kill 之类发来的 SIGTRAP
+            self.set_signal(tid, Some(signal));
+            return Ok(Some(signal));
+        }
+        let regs = self.regs(tid)?;
+        let debug_status = if code == TRAP_HWBKPT { self.hw_trap(tid, &regs)? } else { 0 };
+        self.traps.borrow_mut().push(Trap { tid, code, regs, debug_status });
+        Ok(None)
+    }
 
-        while offset < size {
-            let curr_addr = address + offset;
-            let aligned_addr = curr_addr - (curr_addr % word_size);
-            let offset1 = curr_addr % word_size;
-            let word = sys::ptrace::read(pid, (aligned_addr) as sys::ptrace::AddressType)
-                .map_err(|e|MemoryError::PtraceReadError(e.to_string()))?;
-            let bytes = word.to_ne_bytes();
+    /// 读出并清零 DR6，设置 RF 以免恢复后在同一条指令上再次触发执行断点
+    #[cfg(target_arch = "x86_64")]
+    fn hw_trap(&self, tid: u32, regs: &Regs) -> Result<u64, MemoryError> {
+        let status = self.debug_reg(tid, 6)?;
+        self.set_debug_reg(tid, 6, 0)?;
+        let mut regs = *regs;
+        regs.eflags |= EFLAGS_RF;
+        self.set_regs(tid, &regs)?;
+        Ok(status)
+    }
 
-            if aligned_addr < address {
-                //in the begin of T
-                let bytes_to_copy = std::cmp::min(word_size - offset1, size);
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        bytes.as_ptr().add(offset1),
-                        ptr,
-                        bytes_to_copy,
-                    );
-                }
-                offset += bytes_to_copy;
-            }else{
-                let bytes_to_copy = std::cmp::min(word_size, size - offset);
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        bytes.as_ptr(),
-                        ptr.add(offset),
-                        bytes_to_copy,
-                    );
-                }
-                offset += bytes_to_copy;
+    #[cfg(not(target_arch = "x86_64"))]
+    fn hw_trap(&self, _tid: u32, _regs: &Regs) -> Result<u64, MemoryError> {
+        Ok(0)
+    }
+
+    /// 取走满足 pred 的 Trap，其余的留给别的使用者
+    pub fn take_traps(&self, pred: impl Fn(&Trap) -> bool) -> Vec<Trap> {
+        let mut traps = self.traps.borrow_mut();
+        let (res, rest) = traps.drain(..).partition(pred);
+        *traps = rest;
+        res
+    }
+
+    /// 读取调试寄存器 DR0 - DR7
+    #[cfg(target_arch = "x86_64")]
+    pub fn debug_reg(&self, tid: u32, index: usize) -> Result<u64, MemoryError> {
+        let offset = DEBUGREG_OFFSET + index * 8;
+        self.with_thread_stopped(tid, || {
+            sys::ptrace::read_user(Pid::from_raw(tid as i32), offset as sys::ptrace::AddressType)
+                .map(|v| v as u64)
+                .map_err(|e|MemoryError::PtraceRegsError(format!("DR{index}: {e}")))
+        })
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    pub fn set_debug_reg(&self, tid: u32, index: usize, value: u64) -> Result<(), MemoryError> {
+        let offset = DEBUGREG_OFFSET + index * 8;
+        self.with_thread_stopped(tid, || {
+            sys::ptrace::write_user(Pid::from_raw(tid as i32), offset as sys::ptrace::AddressType, value as i64)
+                .map_err(|e|MemoryError::PtraceRegsError(format!("DR{index}: {e}")))
+        })
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    pub fn debug_reg(&self, _tid: u32, _index: usize) -> Result<u64, MemoryError> {
+        Err(MemoryError::PtraceRegsError("Debug registers only supported on x86_64".to_string()))
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    pub fn set_debug_reg(&self, _tid: u32, _index: usize, _value: u64) -> Result<(), MemoryError> {
+        Err(MemoryError::PtraceRegsError("Debug registers only supported on x86_64".to_string()))
+    }
+
+    /// 修改停住的线程恢复时要发送的信号
+    pub fn set_signal(&self, tid: u32, signal: Option<Signal>) {
+        if let Some(TraceState::Stopped { .. }) = self.state(tid) {
+            self.set_state(tid, Some(TraceState::Stopped { signal }));
+        }
+    }
+
+    pub fn dettach(&self) -> Result<(), MemoryError> {
+        // PTRACE_DETACH 要求线程处于停止状态
+        for tid in self.attached_threads() {
+            let signal = match self.stop(tid) {
+                Ok(_) => match self.state(tid) {
+                    Some(TraceState::Stopped { signal }) => signal,
+                    _ => None,
+                },
+                Err(_) if self.state(tid).is_none() => continue,
+                Err(e) => return Err(e),
+            };
+            match sys::ptrace::detach(Pid::from_raw(tid as i32), signal) {
+                Ok(_) | Err(nix::errno::Errno::ESRCH) => self.set_state(tid, None),
+                Err(e) => return Err(MemoryError::PtraceDettachError(e.to_string())),
             }
         }
-        if offset == size {
-            Ok(unsafe {
-                res.assume_init()
+        Ok(())
+    }
+
+    /// 用 PTRACE_GETREGSET 读取线程的一组寄存器，线程在运行时临时停住
+    pub fn regset<T: RegisterSet>(&self, tid: u32) -> Result<T, MemoryError> {
+        let was_running = self.stop(tid)?;
+        let mut res = T::zeroed();
+        let buf = bytemuck::bytes_of_mut(&mut res);
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let ret = unsafe {
+            libc::ptrace(libc::PTRACE_GETREGSET, tid as libc::pid_t, T::NT_TYPE as usize, &mut iov as *mut libc::iovec)
+        };
+        let err = nix::errno::Errno::last();
+        if was_running {
+            self.cont(tid)?;
+        }
+        if ret < 0 {
+            Err(MemoryError::PtraceRegsError(err.to_string()))
+        } else if iov.iov_len != std::mem::size_of::<T>() {
+            Err(MemoryError::PtraceRegsError(format!("Short regset, result: {}", iov.iov_len)))
+        } else {
+            Ok(res)
+        }
+    }
+
+    /// 用 PTRACE_SETREGSET 写入线程的一组寄存器
+    pub fn set_regset<T: RegisterSet>(&self, tid: u32, value: &T) -> Result<(), MemoryError> {
+        let was_running = self.stop(tid)?;
+        let mut value = *value;
+        let buf = bytemuck::bytes_of_mut(&mut value);
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let ret = unsafe {
+            libc::ptrace(libc::PTRACE_SETREGSET, tid as libc::pid_t, T::NT_TYPE as usize, &mut iov as *mut libc::iovec)
+        };
+        let err = nix::errno::Errno::last();
+        if was_running {
+            self.cont(tid)?;
+        }
+        if ret < 0 {
+            Err(MemoryError::PtraceRegsError(err.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 通用寄存器
+    pub fn regs(&self, tid: u32) -> Result<Regs, MemoryError> {
+        self.regset(tid)
+    }
+
+    pub fn set_regs(&self, tid: u32, regs: &Regs) -> Result<(), MemoryError> {
+        self.set_regset(tid, regs)
+    }
+
+    /// 浮点和 SIMD 寄存器
+    pub fn fp_regs(&self, tid: u32) -> Result<FpRegs, MemoryError> {
+        self.regset(tid)
+    }
+
+    pub fn set_fp_regs(&self, tid: u32, regs: &FpRegs) -> Result<(), MemoryError> {
+        self.set_regset(tid, regs)
+    }
+
+    /// 停住所有线程后读取它们的通用寄存器，用于写 core
+    pub fn thread_status(&self) -> Result<Vec<ThreadStatus>, MemoryError> {
+        self.stopped(|m| {
+            m.attached_threads().into_iter()
+                .map(|tid| Ok(ThreadStatus::new(m.process.pid, tid, m.regs(tid)?)))
+                .collect()
+        })
+    }
+
+    /// 所有已附加线程中指向可读区域的寄存器值，需要先读取 maps
+    pub fn register_roots(&self) -> Result<Vec<RegisterRoot>, MemoryError> {
+        let mut res = Vec::new();
+        for tid in self.attached_threads() {
+            res.extend(registers::roots(tid, &self.regs(tid)?, &self.process.maps));
+        }
+        Ok(res)
+    }
+
+    /// 在主线程中执行一次系统调用，返回原始的返回值，失败时为负的 errno
+    ///
+    /// 保存寄存器，把系统调用指令临时写到第一个可执行映射的开头，单步执行后恢复代码和寄存器。
+    /// 期间停住所有线程，其它线程不会执行到临时写入的指令。
+    pub fn syscall(&self, number: i64, args: &[u64]) -> Result<i64, MemoryError> {
+        if args.len() > 6 {
+            return Err(MemoryError::PtraceSyscallError(format!("Too many syscall args: {}", args.len())));
+        }
+        let maps = self.process.read_maps().map_err(|e|MemoryError::PtraceSyscallError(format!("{:?}", e)))?;
+        let address = maps.iter().find(|m| m.executable()).map(|m| m.address.0)
+            .ok_or(MemoryError::PtraceSyscallError("No executable mapping".to_string()))?;
+        self.stopped(|m| m.syscall_at(address, number, args))
+    }
+
+    fn syscall_at(&self, address: usize, number: i64, args: &[u64]) -> Result<i64, MemoryError> {
+        let tid = self.process.pid;
+        let saved = self.regs(tid)?;
+        #[cfg(target_arch = "aarch64")]
+        let saved_nr = self.regset::<registers::Aarch64SyscallNo>(tid)?;
+        let mut code = vec![0; SYSCALL_INSN.len()];
+        self.peek(address, &mut code)?;
+        self.poke(address, SYSCALL_INSN)?;
+
+        let mut regs = saved;
+        regs.set_pc(address as u64);
+        regs.set_syscall(number as u64, args);
+        let res = self.set_regs(tid, &regs)
+            .and_then(|_| {
+                #[cfg(target_arch = "aarch64")]
+                self.set_regset(tid, &registers::Aarch64SyscallNo { nr: -1 })?;
+                self.step(tid)
             })
-        }else{
-            Err(MemoryError::ProcReadError(format!("Short read, result: {offset}").to_string()))
+            .and_then(|_| self.regs(tid));
+
+        // 不管成功与否都要恢复，先恢复寄存器，免得代码恢复失败时 pc 还指向临时写入的指令
+        let restored = self.set_regs(tid, &saved);
+        #[cfg(target_arch = "aarch64")]
+        let restored = restored.and(self.set_regset(tid, &saved_nr));
+        let code_restored = self.poke(address, &code);
+        restored?;
+        code_restored?;
+        Ok(res?.syscall_result())
+    }
+
+    /// 系统调用失败时转换成错误
+    fn check_syscall(name: &str, ret: i64) -> Result<u64, MemoryError> {
+        if (-4095..0).contains(&ret) {
+            Err(MemoryError::PtraceSyscallError(format!("{name}: {}", nix::errno::Errno::from_raw(-ret as i32))))
+        } else {
+            Ok(ret as u64)
         }
     }
 
-    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
-        self.attach()?;
+    /// 在目标中分配匿名私有映射，address 为 0 时由内核选择，返回映射地址
+    pub fn remote_mmap(&self, address: usize, len: usize, prot: i32) -> Result<usize, MemoryError> {
+        let flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS;
+        let args = [address as u64, len as u64, prot as u64, flags as u64, u64::MAX, 0];
+        let ret = self.syscall(libc::SYS_mmap, &args)?;
+        Self::check_syscall("mmap", ret).map(|v| v as usize)
+    }
+
+    pub fn remote_munmap(&self, address: usize, len: usize) -> Result<(), MemoryError> {
+        let ret = self.syscall(libc::SYS_munmap, &[address as u64, len as u64])?;
+        Self::check_syscall("munmap", ret).map(|_| ())
+    }
+
+    pub fn remote_mprotect(&self, address: usize, len: usize, prot: i32) -> Result<(), MemoryError> {
+        let ret = self.syscall(libc::SYS_mprotect, &[address as u64, len as u64, prot as u64])?;
+        Self::check_syscall("mprotect", ret).map(|_| ())
+    }
+
+    /// 按字读取，调用时主线程必须已停住
+    fn peek(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
 
         let word_size = std::mem::size_of::<libc::c_long>();
         let mut buff_offset: usize = 0;
@@ -111,8 +595,6 @@ impl MemoryReader for PtraceMemory {
 
             if aligned_addr < address {
                 let bytes_to_copy = std::cmp::min(word_size - offset, buf.len());
-                //buf[buff_offset..buff_offset + bytes_to_copy]
-                //.copy_from_slice(&data_bytes[offset..offset + bytes_to_copy]);
                 unsafe {
                     std::ptr::copy_nonoverlapping(data_bytes.as_ptr().add(offset), buf.as_mut_ptr(), bytes_to_copy);
                 }
@@ -132,16 +614,14 @@ impl MemoryReader for PtraceMemory {
             Err(MemoryError::ProcReadError(format!("Short read, result: {buff_offset}").to_string()))
         }
     }
-}
 
-impl MemoryWriter for PtraceMemory {
-    fn write<T: Sized + Copy>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
-        self.attach()?;
+    /// 按字写入，调用时主线程必须已停住
+    fn poke(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
 
         let pid = Pid::from_raw(self.process.pid as i32);
-        let size = std::mem::size_of::<T>();
+        let size = buf.len();
         let word_size = std::mem::size_of::<libc::c_long>();
-        let ptr = value as *const T as *const u8;
+        let ptr = buf.as_ptr();
         let mut offset: usize =0;
         let mut word_bytes = [0u8; std::mem::size_of::<libc::c_long>()];
 
@@ -165,9 +645,9 @@ impl MemoryWriter for PtraceMemory {
                     let bytes = word.to_ne_bytes();
                     unsafe {
                         std::ptr::copy_nonoverlapping(bytes.as_ptr(), word_bytes.as_mut_ptr(), word_size);
-                        std::ptr::copy_nonoverlapping(ptr, word_bytes.as_mut_ptr(), left);
+                        std::ptr::copy_nonoverlapping(ptr.add(offset), word_bytes.as_mut_ptr(), left);
                     }
-                    let data = libc::c_long::from_ne_bytes(bytes);
+                    let data = libc::c_long::from_ne_bytes(word_bytes);
                     sys::ptrace::write(pid, curr_addr as sys::ptrace::AddressType, data).map_err(|e|MemoryError::PtraceWriteError(e.to_string()))?;
                     offset += left;
                 }
@@ -175,119 +655,90 @@ impl MemoryWriter for PtraceMemory {
                 let aligned_addr = curr_addr - offset1;
                 let word = sys::ptrace::read(pid, aligned_addr as sys::ptrace::AddressType).map_err(|e|MemoryError::ProcWriteError(e.to_string()))?;
                 let bytes = word.to_ne_bytes();
-                let bytes_to_copy = std::cmp::min(word_size - offset1, size);
+                let bytes_to_copy = std::cmp::min(word_size - offset1, size - offset);
                 unsafe {
                     std::ptr::copy_nonoverlapping(bytes.as_ptr(), word_bytes.as_mut_ptr(), word_size);
-                    std::ptr::copy_nonoverlapping(ptr, word_bytes.as_mut_ptr().add(offset1), bytes_to_copy);
+                    std::ptr::copy_nonoverlapping(ptr.add(offset), word_bytes.as_mut_ptr().add(offset1), bytes_to_copy);
                 }
-                let data = libc::c_long::from_ne_bytes(bytes);
+                let data = libc::c_long::from_ne_bytes(word_bytes);
                 sys::ptrace::write(pid, aligned_addr as sys::ptrace::AddressType, data).map_err(|e|MemoryError::PtraceWriteError(e.to_string()))?;
                 offset += bytes_to_copy;
             }
         }
 
         if offset == size {
-            Ok(())
+            Ok(offset)
         } else {
             Err(MemoryError::PtraceWriteError(format!("Short written, result: {offset}").to_string()))
         }
     }
+}
 
-    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
-        self.attach()?;
+impl Drop for PtraceMemory {
+    fn drop(&mut self) {
+        let _ = self.dettach();
+    }
+}
 
-        let pid = Pid::from_raw(self.process.pid as i32);
-        let size = buf.len();
-        let word_size = std::mem::size_of::<libc::c_long>();
-        let ptr = buf.as_ptr();
-        let mut offset: usize =0;
-        let mut word_bytes = [0u8; std::mem::size_of::<libc::c_long>()];
+impl MemoryReader for PtraceMemory {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        let mut res = T::zeroed();
+        let size = std::mem::size_of::<T>();
 
-        while offset < size {
-            let curr_addr = address + offset;
-            let offset1 = curr_addr % word_size;
+        let len = self.readbuf(address, bytemuck::bytes_of_mut(&mut res))?;
+        if len == size {
+            Ok(res)
+        }else{
+            Err(MemoryError::PtraceReadError(format!("Short read, result: {len}")))
+        }
+    }
 
-            if offset1 == 0 {
-                let left = size - offset;
-                if left >= word_size {
-                    let raw_data = unsafe {
-                        std::slice::from_raw_parts(ptr.add(offset), word_size)
-                    };
-                    word_bytes.copy_from_slice(raw_data);
-                    let data = libc::c_long::from_ne_bytes(word_bytes);
-                    sys::ptrace::write(pid, curr_addr as sys::ptrace::AddressType, data).map_err(|e|MemoryError::PtraceWriteError(e.to_string()))?;
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        self.with_leader_stopped(|| self.peek(address, buf))
+    }
+}
 
-                    offset += word_size;
-                }else{
-                    let word = sys::ptrace::read(pid, curr_addr as sys::ptrace::AddressType).map_err(|e|MemoryError::ProcWriteError(e.to_string()))?;
-                    let bytes = word.to_ne_bytes();
-                    unsafe {
-                        std::ptr::copy_nonoverlapping(bytes.as_ptr(), word_bytes.as_mut_ptr(), word_size);
-                        std::ptr::copy_nonoverlapping(ptr, word_bytes.as_mut_ptr(), left);
-                    }
-                    let data = libc::c_long::from_ne_bytes(bytes);
-                    sys::ptrace::write(pid, curr_addr as sys::ptrace::AddressType, data).map_err(|e|MemoryError::PtraceWriteError(e.to_string()))?;
-                    offset += left;
-                }
-            } else {
-                let aligned_addr = curr_addr - offset1;
-                let word = sys::ptrace::read(pid, aligned_addr as sys::ptrace::AddressType).map_err(|e|MemoryError::ProcWriteError(e.to_string()))?;
-                let bytes = word.to_ne_bytes();
-                let bytes_to_copy = std::cmp::min(word_size - offset1, size);
-                unsafe {
-                    std::ptr::copy_nonoverlapping(bytes.as_ptr(), word_bytes.as_mut_ptr(), word_size);
-                    std::ptr::copy_nonoverlapping(ptr, word_bytes.as_mut_ptr().add(offset1), bytes_to_copy);
-                }
-                let data = libc::c_long::from_ne_bytes(bytes);
-                sys::ptrace::write(pid, aligned_addr as sys::ptrace::AddressType, data).map_err(|e|MemoryError::PtraceWriteError(e.to_string()))?;
-                offset += bytes_to_copy;
-            }
-        }
+impl MemoryWriter for PtraceMemory {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        let size = std::mem::size_of::<T>();
 
-        if offset == size {
-            Ok(offset)
+        let len = self.writebuf(address, bytemuck::bytes_of(value))?;
+        if len == size {
+            Ok(())
         } else {
-            Err(MemoryError::PtraceWriteError(format!("Short written, result: {offset}").to_string()))
+            Err(MemoryError::PtraceWriteError(format!("Short written, result: {len}")))
         }
     }
+
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        self.with_leader_stopped(|| self.poke(address, buf))
+    }
 }
 
 impl MemorySearcher for PtraceMemory {
     fn search<T: SearchRule, const N: usize>(&self, rule: T, filter: Option<impl Fn(&MapRange) -> bool>) -> Result<Vec<usize>, SearchError>
     {
         let mut buff = Box::new([0u8;N]);
-        let mut res = Vec::<usize>::new();
-        match filter {
-            Some(f) => {
-                for i in 0..self.process.maps.len() {
-                    if f(&self.process.maps[i]) {
-                        let addr = self.process.maps[i].address;
-                        let len = addr.1 - addr.0 + 1;
-                        let mut offset = 0;
-                        while offset < len {
-                            let read_bytes = self.readbuf(addr.0 + offset, buff.as_mut_slice())
-                                .map_err(|e|SearchError::ReadError(e.to_string()))?;
-                            offset += read_bytes;
-                            res.extend(rule.search(buff.as_slice(), read_bytes));
-                        }
-                    }
+        // 整个搜索过程只停一次
+        self.with_leader_stopped(|| {
+            let mut res = Vec::<usize>::new();
+            for map in self.process.maps.iter() {
+                if !map.readable() {
+                    continue;
                 }
-            },
-            None => {
-                for i in 0..self.process.maps.len() {
-                    let addr = self.process.maps[i].address;
-                    let len = addr.1 - addr.0 + 1;
-                    let mut offset = 0;
-                    while offset < len {
-                        let read_bytes = self.readbuf(addr.0 + offset, buff.as_mut_slice())
-                            .map_err(|e|SearchError::ReadError(e.to_string()))?;
-                        offset += read_bytes;
-                        res.extend(rule.search(buff.as_slice(), read_bytes));
-                    }
+                if let Some(f) = filter.as_ref() && !f(map) {
+                    continue;
+                }
+                let addr = map.address;
+                let mut offset = 0;
+                while offset + addr.0 < addr.1 {
+                    let len = std::cmp::min(N, addr.1 - addr.0 - offset);
+                    let read_bytes = self.peek(addr.0 + offset, &mut buff[..len])?;
+                    res.extend(rule.search(buff.as_slice(), read_bytes).map(|v|v+addr.0+offset));
+                    offset += read_bytes;
                 }
             }
-        }
-
-        Ok(res)
+            Ok(res)
+        }).map_err(|e|SearchError::ReadError(e.to_string()))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,134 @@
+use crate::process::{MapRange, Process};
+use crate::searcher::{MemorySearcher, SearchError, SearchRule};
+use super::{MemoryError, MemoryReader, Pod};
+
+const PAGE_SIZE: usize = 0x1000;
+const CHUNK_SIZE: usize = 0x10_0000;
+
+/// 某一时刻的内存快照，可以像进程一样读取和搜索
+///
+/// 读取失败的页不保存，之后读这些地址会返回错误。
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotMemory {
+    pub process: Process,
+    /// (起始地址, 数据)，按地址排序且互不重叠
+    segments: Vec<(usize, Vec<u8>)>,
+}
+
+impl SnapshotMemory {
+    /// 保存 process.maps 中可读且满足 filter 的区域
+    pub fn capture<R: MemoryReader>(reader: &R, process: &Process, filter: Option<impl Fn(&MapRange) -> bool>) -> Self {
+        let mut snapshot = SnapshotMemory {
+            process: process.clone(),
+            segments: Vec::new(),
+        };
+        snapshot.process.maps.retain(|m| m.readable() && filter.as_ref().is_none_or(|f| f(m)));
+        for map in snapshot.process.maps.iter() {
+            let (start, end) = map.address;
+            snapshot.segments.extend(read_segments(reader, start, end - start));
+        }
+        snapshot.segments.sort_by_key(|s| s.0);
+        snapshot
+    }
+
+    /// 只保存 [address, address + len)，不依赖 maps
+    pub fn capture_range<R: MemoryReader>(reader: &R, address: usize, len: usize) -> Self {
+        SnapshotMemory { segments: read_segments(reader, address, len), ..Default::default() }
+    }
+
+    /// 已保存的地址范围
+    pub fn ranges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.segments.iter().map(|(start, data)| (*start, start + data.len()))
+    }
+
+    /// 保存的字节数
+    pub fn size(&self) -> usize {
+        self.segments.iter().map(|s| s.1.len()).sum()
+    }
+
+    fn segment(&self, address: usize) -> Option<&(usize, Vec<u8>)> {
+        let i = self.segments.partition_point(|s| s.0 <= address).checked_sub(1)?;
+        let seg = &self.segments[i];
+        (address < seg.0 + seg.1.len()).then_some(seg)
+    }
+}
+
+/// 读取 [address, address + len)，读不到的页把结果分成多段
+fn read_segments<R: MemoryReader>(reader: &R, address: usize, len: usize) -> Vec<(usize, Vec<u8>)> {
+    let end = address + len;
+    let mut pos = address;
+    let mut segments = Vec::new();
+    let mut current: Option<(usize, Vec<u8>)> = None;
+    let mut buf = vec![0u8; std::cmp::min(len, CHUNK_SIZE)];
+
+    while pos < end {
+        let size = std::cmp::min(buf.len(), end - pos);
+        match reader.readbuf(pos, &mut buf[..size]) {
+            Ok(n) if n > 0 => {
+                current.get_or_insert_with(|| (pos, Vec::new())).1.extend_from_slice(&buf[..n]);
+                pos += n;
+            },
+            _ => {
+                segments.extend(current.take());
+                pos = std::cmp::min((pos / PAGE_SIZE + 1) * PAGE_SIZE, end);
+            },
+        }
+    }
+    segments.extend(current);
+    segments
+}
+
+impl MemoryReader for SnapshotMemory {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        let mut res = T::zeroed();
+        let size = std::mem::size_of::<T>();
+
+        let len = self.readbuf(address, bytemuck::bytes_of_mut(&mut res))?;
+        if len != size {
+            Err(MemoryError::SnapshotReadError(format!("Short read, result: {len}")))
+        } else {
+            Ok(res)
+        }
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let curr = address + done;
+            let Some((start, data)) = self.segment(curr) else {
+                break;
+            };
+            let off = curr - start;
+            let len = std::cmp::min(buf.len() - done, data.len() - off);
+            buf[done..done + len].copy_from_slice(&data[off..off + len]);
+            done += len;
+        }
+
+        if done == 0 {
+            Err(MemoryError::SnapshotReadError(format!("Address {address:#x} not in snapshot")))
+        } else {
+            Ok(done)
+        }
+    }
+}
+
+impl MemorySearcher for SnapshotMemory {
+    fn search<T: SearchRule, const N: usize>(&self, rule: T, filter: Option<impl Fn(&MapRange) -> bool>) -> Result<Vec<usize>, SearchError>
+    {
+        let mut res = Vec::<usize>::new();
+        for (start, data) in self.segments.iter() {
+            if let Some(f) = filter.as_ref() {
+                let map = self.process.maps.iter().find(|m| m.address.0 <= *start && *start < m.address.1);
+                if !map.is_some_and(f) {
+                    continue;
+                }
+            }
+            // 数据已经在内存中，直接按块搜索
+            for (i, chunk) in data.chunks(N).enumerate() {
+                res.extend(rule.search(chunk, chunk.len()).map(|v| v + start + i * N));
+            }
+        }
+
+        Ok(res)
+    }
+}
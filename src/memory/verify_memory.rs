@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::time::Duration;
+
+use super::{MemoryError, MemoryReader, MemoryWriter, Pod};
+
+/// 写入后的校验方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// 不校验，只相信写入的返回值
+    Off,
+    /// 写入后读回一次
+    Once,
+    /// 连续 stable 次读回都一致才算成功，不一致时重写，最多重试 retries 次
+    ///
+    /// 用于目标每帧都会重置的值，interval 为两次读回之间的间隔。
+    UntilStable { retries: usize, stable: usize, interval: Duration },
+}
+
+/// 读回时一段连续不一致的字节
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub address: usize,
+    pub expected: Vec<u8>,
+    pub found: Vec<u8>,
+}
+
+/// 一次写入的校验结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub address: usize,
+    pub len: usize,
+    /// 写入的次数，包括重试
+    pub attempts: usize,
+    /// 最后一次读回的不一致，为空表示成功
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// 找出 found 和 expected 中不一致的连续区间
+pub fn mismatches(address: usize, expected: &[u8], found: &[u8]) -> Vec<Mismatch> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < expected.len() {
+        if expected[i] == found[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < expected.len() && expected[i] != found[i] {
+            i += 1;
+        }
+        res.push(Mismatch { address: address + start, expected: expected[start..i].to_vec(), found: found[start..i].to_vec() });
+    }
+    res
+}
+
+/// 写入后读回校验的包装，可以包住任何后端
+///
+/// 通过 MemoryWriter 写入时校验失败返回 VerifyError，失败的结果记录下来，用 take_failures 取走。
+/// 写时复制的共享映射、立即被目标覆盖的值都会在这里暴露出来。
+pub struct VerifyMemory<'a, M: MemoryReader + MemoryWriter> {
+    mem: &'a M,
+    mode: VerifyMode,
+    failures: RefCell<Vec<VerifyReport>>,
+}
+
+impl<'a, M: MemoryReader + MemoryWriter> VerifyMemory<'a, M> {
+    pub fn new(mem: &'a M, mode: VerifyMode) -> Self {
+        VerifyMemory { mem, mode, failures: RefCell::new(Vec::new()) }
+    }
+
+    pub fn mode(&self) -> VerifyMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: VerifyMode) {
+        self.mode = mode;
+    }
+
+    /// 取走校验失败的记录
+    pub fn take_failures(&self) -> Vec<VerifyReport> {
+        std::mem::take(&mut *self.failures.borrow_mut())
+    }
+
+    /// 写入并按当前模式校验，不一致时也返回 Ok，由调用者检查报告
+    pub fn write_verified(&self, address: usize, buf: &[u8]) -> Result<VerifyReport, MemoryError> {
+        let (retries, stable, interval) = match self.mode {
+            VerifyMode::Off => {
+                self.mem.write_exact(address, buf)?;
+                return Ok(VerifyReport { address, len: buf.len(), attempts: 1, mismatches: Vec::new() });
+            },
+            VerifyMode::Once => (0, 1, Duration::ZERO),
+            VerifyMode::UntilStable { retries, stable, interval } => (retries, stable.max(1), interval),
+        };
+
+        let mut found = vec![0; buf.len()];
+        let mut attempts = 0;
+        loop {
+            self.mem.write_exact(address, buf)?;
+            attempts += 1;
+            let mut diff = Vec::new();
+            for check in 0..stable {
+                if check > 0 {
+                    std::thread::sleep(interval);
+                }
+                self.mem.read_exact(address, &mut found)?;
+                diff = mismatches(address, buf, &found);
+                if !diff.is_empty() {
+                    break;
+                }
+            }
+            if diff.is_empty() || attempts > retries {
+                return Ok(VerifyReport { address, len: buf.len(), attempts, mismatches: diff });
+            }
+        }
+    }
+}
+
+impl<M: MemoryReader + MemoryWriter> MemoryReader for VerifyMemory<'_, M> {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        self.mem.read(address)
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        self.mem.readbuf(address, buf)
+    }
+}
+
+impl<M: MemoryReader + MemoryWriter> MemoryWriter for VerifyMemory<'_, M> {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        self.writebuf(address, bytemuck::bytes_of(value)).map(|_| ())
+    }
+
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        let report = self.write_verified(address, buf)?;
+        if report.is_ok() {
+            return Ok(buf.len());
+        }
+        let first = &report.mismatches[0];
+        let err = MemoryError::VerifyError(format!(
+            "{address:#x}: {} mismatches after {} attempts, first at {:#x} expected {:02x?} found {:02x?}",
+            report.mismatches.len(), report.attempts, first.address, first.expected, first.found,
+        ));
+        self.failures.borrow_mut().push(report);
+        Err(err)
+    }
+}
@@ -0,0 +1,332 @@
+use std::str::FromStr;
+use crate::memory::{MemoryReader, MemoryWriter};
+use crate::process::Process;
+
+#[derive(Debug)]
+pub enum PatchError {
+    InvalidSignature(String),
+    DuplicatePatch(String),
+    UnknownPatch(String),
+    /// 找不到模块或特征码
+    NotFound(String),
+    /// 特征码匹配到多处
+    Ambiguous(String),
+    /// 目标处的字节与预期不符
+    UnexpectedBytes { name: String, address: usize, found: Vec<u8> },
+    /// 与已添加的补丁覆盖的字节重叠
+    Overlap { name: String, other: String },
+    InvalidCode(String),
+    JumpOutOfRange(String),
+    MemoryError(String),
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// AOB 特征码，如 `48 8B 05 ?? ?? ?? ?? C3`，`?` 或 `??` 匹配任意字节
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature(pub Vec<Option<u8>>);
+
+impl FromStr for Signature {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.split_whitespace()
+            .map(|t| match t {
+                "?" | "??" => Ok(None),
+                _ => u8::from_str_radix(t, 16).map(Some)
+                    .map_err(|_| PatchError::InvalidSignature(format!("Bad byte {t:?} in {s:?}"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if bytes.is_empty() {
+            return Err(PatchError::InvalidSignature("Empty signature".to_string()));
+        }
+        Ok(Signature(bytes))
+    }
+}
+
+impl From<&[u8]> for Signature {
+    fn from(bytes: &[u8]) -> Self {
+        Signature(bytes.iter().copied().map(Some).collect())
+    }
+}
+
+impl Signature {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// data 的开头是否匹配
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.len() && self.0.iter().zip(data).all(|(s, b)| s.is_none_or(|s| s == *b))
+    }
+
+    /// data 中所有匹配的偏移
+    pub fn find<'a>(&'a self, data: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        (0..(data.len() + 1).saturating_sub(self.len())).filter(|i| self.matches(&data[*i..]))
+    }
+}
+
+/// 补丁的位置，用模块偏移或特征码定位以适应 ASLR
+#[derive(Debug, Clone)]
+pub enum PatchLocation {
+    Address(usize),
+    /// 模块 base 加偏移，模块名的匹配规则同 Module::matches
+    ModuleOffset { module: String, offset: usize },
+    /// 在模块的可执行段中查找唯一的匹配，再加上 offset
+    Signature { module: String, signature: Signature, offset: isize },
+}
+
+/// 要写入的代码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchCode {
+    Bytes(Vec<u8>),
+    /// 若干字节的 NOP，aarch64 上必须是 4 的倍数
+    Nop(usize),
+    /// 跳转到绝对地址，按补丁所在地址编码成相对跳转
+    Jump(usize),
+}
+
+#[cfg(target_arch = "aarch64")]
+const NOP: &[u8] = &[0x1f, 0x20, 0x03, 0xd5];
+#[cfg(not(target_arch = "aarch64"))]
+const NOP: &[u8] = &[0x90];
+
+impl PatchCode {
+    /// 在 address 处的机器码
+    pub fn assemble(&self, address: usize) -> Result<Vec<u8>, PatchError> {
+        match self {
+            PatchCode::Bytes(bytes) => Ok(bytes.clone()),
+            PatchCode::Nop(len) => {
+                if len % NOP.len() != 0 {
+                    return Err(PatchError::InvalidCode(format!("Nop length {len} not a multiple of {}", NOP.len())));
+                }
+                Ok(NOP.repeat(len / NOP.len()))
+            },
+            PatchCode::Jump(target) => jump(address, *target),
+        }
+    }
+}
+
+/// x86_64 的 jmp rel32
+#[cfg(not(target_arch = "aarch64"))]
+fn jump(from: usize, to: usize) -> Result<Vec<u8>, PatchError> {
+    let rel = (to as i64).wrapping_sub(from as i64 + 5);
+    let rel = i32::try_from(rel).map_err(|_| PatchError::JumpOutOfRange(format!("{from:#x} -> {to:#x}")))?;
+    let mut res = vec![0xe9];
+    res.extend(rel.to_le_bytes());
+    Ok(res)
+}
+
+/// aarch64 的 b imm26，范围 ±128MB
+#[cfg(target_arch = "aarch64")]
+fn jump(from: usize, to: usize) -> Result<Vec<u8>, PatchError> {
+    let rel = (to as i64).wrapping_sub(from as i64);
+    if rel % 4 != 0 || !(-(1 << 27)..(1 << 27)).contains(&rel) {
+        return Err(PatchError::JumpOutOfRange(format!("{from:#x} -> {to:#x}")));
+    }
+    let insn = 0x1400_0000u32 | ((rel >> 2) as u32 & 0x03ff_ffff);
+    Ok(insn.to_le_bytes().to_vec())
+}
+
+#[derive(Debug, Clone)]
+pub struct Patch {
+    pub name: String,
+    pub location: PatchLocation,
+    pub code: PatchCode,
+    /// 打补丁前必须存在的字节，None 时不检查
+    pub expected: Option<Signature>,
+}
+
+impl Patch {
+    pub fn new(name: &str, location: PatchLocation, code: PatchCode) -> Self {
+        Patch { name: name.to_string(), location, code, expected: None }
+    }
+
+    pub fn expect(mut self, expected: Signature) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+}
+
+/// 已定位的补丁
+#[derive(Debug, Clone)]
+pub struct AppliedPatch {
+    pub patch: Patch,
+    pub address: usize,
+    /// 被覆盖的原始字节
+    pub original: Vec<u8>,
+    pub code: Vec<u8>,
+    pub enabled: bool,
+}
+
+/// 管理一组命名补丁，记录原始字节以便关闭和全部还原
+///
+/// 代码段通常不可写，mem 一般用 ForceMemory。drop 时不会还原补丁，需要的话调用 revert_all。
+pub struct PatchManager<'a, M: MemoryReader + MemoryWriter> {
+    mem: &'a M,
+    process: Process,
+    patches: Vec<AppliedPatch>,
+}
+
+impl<'a, M: MemoryReader + MemoryWriter> PatchManager<'a, M> {
+    /// process 需要已经读取 maps
+    pub fn new(mem: &'a M, process: &Process) -> Self {
+        PatchManager { mem, process: process.clone(), patches: Vec::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AppliedPatch> {
+        self.patches.iter().find(|p| p.patch.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AppliedPatch> {
+        self.patches.iter()
+    }
+
+    /// 补丁位置对应的地址
+    pub fn resolve(&self, location: &PatchLocation) -> Result<usize, PatchError> {
+        match location {
+            PatchLocation::Address(address) => Ok(*address),
+            PatchLocation::ModuleOffset { module, offset } => {
+                let module = self.process.module_by_name(module)
+                    .ok_or(PatchError::NotFound(format!("Module {module}")))?;
+                Ok(module.address_of(*offset))
+            },
+            PatchLocation::Signature { module, signature, offset } => {
+                let module = self.process.module_by_name(module)
+                    .ok_or(PatchError::NotFound(format!("Module {module}")))?;
+                let mut found = Vec::new();
+                for segment in module.code() {
+                    let (start, end) = segment.address;
+                    let mut data = vec![0; end - start];
+                    if self.mem.read_exact(start, &mut data).is_err() {
+                        continue;
+                    }
+                    found.extend(signature.find(&data).map(|i| start + i));
+                }
+                match found.as_slice() {
+                    [] => Err(PatchError::NotFound(format!("Signature in {}", module.name))),
+                    [address] => Ok(address.wrapping_add_signed(*offset)),
+                    _ => Err(PatchError::Ambiguous(format!("Signature matched {} times in {}", found.len(), module.name))),
+                }
+            },
+        }
+    }
+
+    /// 定位补丁并记录原始字节，检查预期字节，不立即写入
+    pub fn add(&mut self, patch: Patch) -> Result<usize, PatchError> {
+        if self.get(&patch.name).is_some() {
+            return Err(PatchError::DuplicatePatch(patch.name));
+        }
+        let address = self.resolve(&patch.location)?;
+        let code = patch.code.assemble(address)?;
+        let end = address.checked_add(code.len())
+            .ok_or(PatchError::InvalidCode(format!("Patch {} at {address:#x} overflows the address space", patch.name)))?;
+        if let Some(other) = self.patches.iter().find(|p| p.address < end && address < p.address + p.code.len()) {
+            return Err(PatchError::Overlap { name: patch.name, other: other.patch.name.clone() });
+        }
+        // 原始字节和预期字节来自同一次读取
+        let expected_len = patch.expected.as_ref().map_or(0, |e| e.len());
+        let mut found = vec![0; code.len().max(expected_len)];
+        self.mem.read_exact(address, &mut found).map_err(|e| PatchError::MemoryError(e.to_string()))?;
+        if let Some(expected) = patch.expected.as_ref() && !expected.matches(&found) {
+            found.truncate(expected_len);
+            return Err(PatchError::UnexpectedBytes { name: patch.name, address, found });
+        }
+        let original = found[..code.len()].to_vec();
+        self.patches.push(AppliedPatch { patch, address, original, code, enabled: false });
+        Ok(address)
+    }
+
+    /// 添加并启用
+    pub fn apply(&mut self, patch: Patch) -> Result<usize, PatchError> {
+        let name = patch.name.clone();
+        let address = self.add(patch)?;
+        if let Err(e) = self.enable(&name) {
+            self.patches.retain(|p| p.patch.name != name);
+            return Err(e);
+        }
+        Ok(address)
+    }
+
+    fn index(&self, name: &str) -> Result<usize, PatchError> {
+        self.patches.iter().position(|p| p.patch.name == name)
+            .ok_or(PatchError::UnknownPatch(name.to_string()))
+    }
+
+    /// 把 from 换成 to，当前内容不是 from 时报错，写入失败时尽量写回 from
+    fn swap(&self, i: usize, enable: bool) -> Result<(), PatchError> {
+        let p = &self.patches[i];
+        let (from, to) = if enable { (&p.original, &p.code) } else { (&p.code, &p.original) };
+        let mut found = vec![0; from.len()];
+        self.mem.read_exact(p.address, &mut found).map_err(|e| PatchError::MemoryError(e.to_string()))?;
+        if &found != from {
+            return Err(PatchError::UnexpectedBytes { name: p.patch.name.clone(), address: p.address, found });
+        }
+        if let Err(e) = self.mem.write_exact(p.address, to) {
+            let _ = self.mem.write_exact(p.address, from);
+            return Err(PatchError::MemoryError(e.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn enable(&mut self, name: &str) -> Result<(), PatchError> {
+        let i = self.index(name)?;
+        if !self.patches[i].enabled {
+            self.swap(i, true)?;
+            self.patches[i].enabled = true;
+        }
+        Ok(())
+    }
+
+    pub fn disable(&mut self, name: &str) -> Result<(), PatchError> {
+        let i = self.index(name)?;
+        if self.patches[i].enabled {
+            self.swap(i, false)?;
+            self.patches[i].enabled = false;
+        }
+        Ok(())
+    }
+
+    /// 切换状态，返回切换后是否启用
+    pub fn toggle(&mut self, name: &str) -> Result<bool, PatchError> {
+        let i = self.index(name)?;
+        if self.patches[i].enabled {
+            self.disable(name)?;
+        } else {
+            self.enable(name)?;
+        }
+        Ok(self.patches[i].enabled)
+    }
+
+    /// 关闭并删除
+    pub fn remove(&mut self, name: &str) -> Result<AppliedPatch, PatchError> {
+        self.disable(name)?;
+        let i = self.index(name)?;
+        Ok(self.patches.remove(i))
+    }
+
+    /// 按添加的逆序关闭所有补丁，出错时继续还原其它补丁，返回第一个错误
+    pub fn revert_all(&mut self) -> Result<(), PatchError> {
+        let mut res = Ok(());
+        for i in (0..self.patches.len()).rev() {
+            if !self.patches[i].enabled {
+                continue;
+            }
+            match self.swap(i, false) {
+                Ok(_) => self.patches[i].enabled = false,
+                Err(e) => if res.is_ok() {
+                    res = Err(e);
+                },
+            }
+        }
+        res
+    }
+}
@@ -1,22 +1,41 @@
 use core::fmt;
-use std::{fmt::Debug, io::{BufRead, BufReader}};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+pub mod classify;
+pub mod discovery;
+pub mod maps_diff;
+pub mod module;
+pub mod smaps;
+pub mod thread;
+
+pub use classify::RegionClassifier;
+pub use maps_diff::MapsDiff;
+pub use module::Module;
+pub use smaps::RegionStats;
 
 #[derive(Debug)]
 pub enum ProcessError {
     IoError(String),
 
+    ProcStatusError(String),
+    QueryError(String),
+    ThreadReadError(String),
+
+    SmapsOpenError(String),
+    SmapsParseError(String),
+
     MapsOpenError(String),
     MapsReadError(String),
 
-    MapParseError,
-    MapParseConvertError(String),
+    MapParseError { line: usize, reason: String },
 }
 
 pub mod permissions {
     pub const READABLE: u8 = 0b0001;
     pub const WRITABLTE: u8 = 0b0010;
     pub const EXECUTABLE: u8 = 0b0100;
-    pub const SHARED: u8 = 0b100;
+    pub const SHARED: u8 = 0b1000;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -51,6 +70,10 @@ pub enum MemoryType {
     Xa,
     ///PPSSPP
     Ps,
+    ///.NET GC heap
+    Nh,
+    ///Mono heap
+    Mh,
 }
 
 type Permission = u8;
@@ -60,10 +83,15 @@ pub struct MapRange {
     pub address: (usize, usize),
     perms: Permission,
     pub offset: usize,
-    pub dev: (u8, u8),
-    pub inode: u32,
+    pub dev: (u64, u64),
+    pub inode: u64,
+    /// 不含 ` (deleted)` 后缀
     pub pathname: String,
-    pub memory_type: MemoryType
+    /// 映射的文件已被删除
+    pub deleted: bool,
+    pub memory_type: MemoryType,
+    /// smaps 中的统计，调用 Process::smaps() 之后才有
+    pub stats: Option<RegionStats>,
 }
 
 impl fmt::Debug for MapRange {
@@ -72,10 +100,38 @@ impl fmt::Debug for MapRange {
     }
 }
 
-#[derive(Debug)]
+/// 按 /proc/pid/maps 的格式输出
+impl fmt::Display for MapRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |bit: u8, c: char| if self.perms & bit != 0 { c } else { '-' };
+        write!(f, "{:x}-{:x} {}{}{}{} {:08x} {:02x}:{:02x} {}",
+            self.address.0, self.address.1,
+            flag(permissions::READABLE, 'r'),
+            flag(permissions::WRITABLTE, 'w'),
+            flag(permissions::EXECUTABLE, 'x'),
+            if self.shared() { 's' } else { 'p' },
+            self.offset, self.dev.0, self.dev.1, self.inode)?;
+        if !self.pathname.is_empty() || self.deleted {
+            write!(f, "{:width$}{}", "", self.pathname, width = 26usize.saturating_sub(self.inode.to_string().len()).max(1))?;
+        }
+        if self.deleted {
+            write!(f, " (deleted)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Process {
     pub pid: u32,
-    pub maps: Vec<MapRange>
+    pub maps: Vec<MapRange>,
+    classifier: Arc<dyn RegionClassifier>,
+}
+
+impl Default for Process {
+    fn default() -> Self {
+        Process::new(0)
+    }
 }
 
 impl MemoryType {
@@ -178,47 +234,125 @@ impl MemoryType {
         {
             return MemoryType::A;
         }
-        return MemoryType::Other;
+        MemoryType::Other
+    }
+}
+
+/// 解析整个 maps 文件并用当前平台默认的规则分类，出错时返回出错的行号
+pub fn parse_maps(text: &str) -> Result<Vec<MapRange>, ProcessError> {
+    parse_maps_with(text, classify::default_classifier().as_ref())
+}
+
+/// 解析整个 maps 文件并用指定的规则分类
+pub fn parse_maps_with(text: &str, classifier: &dyn RegionClassifier) -> Result<Vec<MapRange>, ProcessError> {
+    let mut maps = Vec::<MapRange>::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        maps.push(MapRange::parse(line, i + 1)?);
     }
+    classifier.classify_all(&mut maps);
+    Ok(maps)
 }
 
 impl MapRange {
-    fn new(s: &str, last_is_cd: bool) -> Result<MapRange, ProcessError> {
-        let mut parts = s.splitn(6, ' ');
-        let addr = parts.next().unwrap().split_once('-').ok_or(ProcessError::MapParseError)?;
-        let addr_s = usize::from_str_radix(addr.0, 16).map_err(|e|ProcessError::MapParseConvertError(e.to_string()))?;
-        let addr_e = usize::from_str_radix(addr.1, 16).map_err(|e|ProcessError::MapParseConvertError(e.to_string()))?;
+    /// 解析 maps 中的一行，line 为从 1 开始的行号，只用于错误信息
+    ///
+    /// 格式：`start-end perms offset major:minor inode   pathname`，
+    /// pathname 可能为空、包含空格或以 ` (deleted)` 结尾。
+    /// 分类要看前后的区域，这里 memory_type 为 Other，由 RegionClassifier 填写
+    pub fn parse(s: &str, line: usize) -> Result<MapRange, ProcessError> {
+        let err = |reason: String| ProcessError::MapParseError { line, reason };
+        let s = s.strip_suffix('\n').unwrap_or(s);
+        let mut rest = s;
+        let mut field = |name: &str| -> Result<&str, ProcessError> {
+            let trimmed = rest.trim_start_matches(' ');
+            let end = trimmed.find(' ').unwrap_or(trimmed.len());
+            let (value, tail) = trimmed.split_at(end);
+            rest = tail;
+            if value.is_empty() {
+                Err(err(format!("missing {name}")))
+            } else {
+                Ok(value)
+            }
+        };
+
+        let addr_raw = field("address")?;
+        let perms_raw = field("perms")?;
+        let offset_raw = field("offset")?;
+        let dev_raw = field("dev")?;
+        let inode_raw = field("inode")?;
+
+        let (addr_s, addr_e) = addr_raw.split_once('-').ok_or(err(format!("bad address {addr_raw:?}")))?;
+        let addr_s = usize::from_str_radix(addr_s, 16).map_err(|e| err(format!("bad start address {addr_s:?}: {e}")))?;
+        let addr_e = usize::from_str_radix(addr_e, 16).map_err(|e| err(format!("bad end address {addr_e:?}: {e}")))?;
+        if addr_e < addr_s {
+            return Err(err(format!("end address before start {addr_raw:?}")));
+        }
 
-        let perms_raw = parts.next().unwrap_or("----");
+        let perms_bytes = perms_raw.as_bytes();
+        if perms_bytes.len() != 4 {
+            return Err(err(format!("bad perms {perms_raw:?}")));
+        }
         let mut perms = 0u8;
-        let mut perms_iter = perms_raw.chars();
-        if perms_iter.next() == Some('r') { perms |= permissions::READABLE; }
-        if perms_iter.next() == Some('w') { perms |= permissions::WRITABLTE; }
-        if perms_iter.next() == Some('x') { perms |= permissions::EXECUTABLE; }
-        if perms_iter.next() == Some('s') { perms |= permissions::SHARED; }
+        for (i, (set, flag)) in [(b'r', permissions::READABLE), (b'w', permissions::WRITABLTE), (b'x', permissions::EXECUTABLE)].into_iter().enumerate() {
+            match perms_bytes[i] {
+                c if c == set => perms |= flag,
+                b'-' => {},
+                _ => return Err(err(format!("bad perms {perms_raw:?}"))),
+            }
+        }
+        match perms_bytes[3] {
+            b's' => perms |= permissions::SHARED,
+            b'p' => {},
+            _ => return Err(err(format!("bad perms {perms_raw:?}"))),
+        }
 
-        let offset = usize::from_str_radix(parts.next().unwrap_or("0"), 16).unwrap();
+        let offset = usize::from_str_radix(offset_raw, 16).map_err(|e| err(format!("bad offset {offset_raw:?}: {e}")))?;
 
-        let dev = parts.next().unwrap_or("0:0").split_once(":").unwrap();
-        let dev_0 = u8::from_str_radix(dev.0, 16).unwrap();
-        let dev_1 = u8::from_str_radix(dev.1, 10).unwrap();
+        // major 和 minor 都是十六进制
+        let (major, minor) = dev_raw.split_once(':').ok_or(err(format!("bad dev {dev_raw:?}")))?;
+        let major = u64::from_str_radix(major, 16).map_err(|e| err(format!("bad dev major {major:?}: {e}")))?;
+        let minor = u64::from_str_radix(minor, 16).map_err(|e| err(format!("bad dev minor {minor:?}: {e}")))?;
 
-        let inode = parts.next().unwrap_or("0").parse::<u32>().map_err(|e|ProcessError::MapParseConvertError(e.to_string()))?;
+        let inode = inode_raw.parse::<u64>().map_err(|e| err(format!("bad inode {inode_raw:?}: {e}")))?;
 
-        let path_raw = parts.next().unwrap_or("").trim_start().trim_end();
+        // 内核用空格补齐到固定列，pathname 本身的空格要保留
+        let path_raw = rest.trim_start_matches(' ');
+        let (path_raw, deleted) = match path_raw.strip_suffix(" (deleted)") {
+            Some(p) => (p, true),
+            None => (path_raw, false),
+        };
 
-        let memory_type = MemoryType::new(Some(path_raw), perms, offset as i64, last_is_cd);
         Ok( MapRange{
             address: (addr_s, addr_e),
-            perms: perms,
-            offset: offset,
-            dev: (dev_0, dev_1),
-            inode: inode,
+            perms,
+            offset,
+            dev: (major, minor),
+            inode,
             pathname: path_raw.to_string(),
-            memory_type: memory_type
+            deleted,
+            memory_type: MemoryType::Other,
+            stats: None,
         } )
     }
 
+    /// 从 core 文件等非 maps 来源构造，之后需要 Process::classify() 分类
+    pub(crate) fn from_parts(address: (usize, usize), perms: Permission, offset: usize, pathname: String) -> MapRange {
+        MapRange {
+            address,
+            perms,
+            offset,
+            dev: (0, 0),
+            inode: 0,
+            pathname,
+            deleted: false,
+            memory_type: MemoryType::Other,
+            stats: None,
+        }
+    }
+
     #[inline]
     pub fn readable(&self) -> bool {
         self.perms & permissions::READABLE != 0
@@ -242,29 +376,48 @@ impl MapRange {
 
 impl Process {
     pub fn new(pid: u32) -> Self {
-        Process { pid, maps: Vec::new() }
+        Process::with_classifier(pid, classify::default_classifier())
     }
 
-    pub fn maps(&mut self) -> Result<(), ProcessError> {
-        if self.maps.is_empty() {
-            let maps_file = std::fs::File::open(format!("/proc/{}/maps", self.pid)).map_err(|e|ProcessError::MapsOpenError(e.to_string()))?;
+    pub fn with_classifier(pid: u32, classifier: Arc<dyn RegionClassifier>) -> Self {
+        Process { pid, maps: Vec::new(), classifier }
+    }
 
-            let reader = BufReader::new(maps_file);
+    /// 更换分类规则并重新分类已有的 maps
+    pub fn set_classifier(&mut self, classifier: Arc<dyn RegionClassifier>) {
+        self.classifier = classifier;
+        self.classify();
+    }
 
-            let mut last_is_cd = false;
-            for line in reader.lines() {
-                match line {
-                    Err(e) => return Err(ProcessError::IoError(e.to_string())),
-                    Ok(s) => {
-                        self.maps.push(MapRange::new(&s, last_is_cd)?);
-                        last_is_cd = self.maps.last().unwrap().memory_type == MemoryType::Cd;
-                    }
-                }
-            }
-            
-            Ok(())
-        } else {
-            Ok(())
+    /// 用当前规则重新给 maps 分类
+    pub fn classify(&mut self) {
+        self.classifier.classify_all(&mut self.maps);
+    }
+
+    /// 按名字查找进程，有多个时取 pid 最小的
+    pub fn by_name(name: &str) -> Result<Option<Self>, ProcessError> {
+        let found = discovery::find(&discovery::ProcessQuery::Name(name.to_string()))?;
+        Ok(found.first().map(|p| p.process()))
+    }
+
+    /// 读取当前的 maps，不修改 self.maps
+    pub fn read_maps(&self) -> Result<Vec<MapRange>, ProcessError> {
+        let text = std::fs::read_to_string(format!("/proc/{}/maps", self.pid)).map_err(|e|ProcessError::MapsOpenError(e.to_string()))?;
+        parse_maps_with(&text, self.classifier.as_ref())
+    }
+
+    pub fn maps(&mut self) -> Result<(), ProcessError> {
+        if self.maps.is_empty() {
+            self.maps = self.read_maps()?;
         }
+        Ok(())
+    }
+
+    /// 重新读取 maps，返回与上一次相比的变化
+    pub fn refresh_maps(&mut self) -> Result<MapsDiff, ProcessError> {
+        let maps = self.read_maps()?;
+        let diff = MapsDiff::new(&self.maps, &maps);
+        self.maps = maps;
+        Ok(diff)
     }
 }
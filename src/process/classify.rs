@@ -0,0 +1,291 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use super::{MapRange, MemoryType};
+
+/// 区域分类规则，不同平台/运行时使用不同的实现
+pub trait RegionClassifier: Debug + Send + Sync {
+    /// 给 maps[index] 分类，maps[..index] 已经分类过
+    fn classify(&self, maps: &[MapRange], index: usize) -> MemoryType;
+
+    /// 按顺序给所有区域分类
+    fn classify_all(&self, maps: &mut [MapRange]) {
+        for i in 0..maps.len() {
+            maps[i].memory_type = self.classify(maps, i);
+        }
+    }
+}
+
+/// 当前平台默认的分类规则
+pub fn default_classifier() -> Arc<dyn RegionClassifier> {
+    #[cfg(target_os = "android")]
+    return Arc::new(AndroidClassifier);
+    #[cfg(not(target_os = "android"))]
+    return Arc::new(DesktopLinuxClassifier::new());
+}
+
+/// Android 上的分类，即 MemoryType::new
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AndroidClassifier;
+
+impl RegionClassifier for AndroidClassifier {
+    fn classify(&self, maps: &[MapRange], index: usize) -> MemoryType {
+        let map = &maps[index];
+        let last_is_cd = index > 0 && maps[index - 1].memory_type == MemoryType::Cd;
+        let pathname = (!map.pathname.is_empty()).then_some(map.pathname.as_str());
+        MemoryType::new(pathname, map.perms, map.offset as i64, last_is_cd)
+    }
+}
+
+/// 桌面 Linux 上的分类，包括 glibc/jemalloc/mimalloc、Wine/Proton、JVM、.NET、Mono 和 Unity
+///
+/// 匿名区域的归属取决于加载了哪些运行时。classify_all 只扫描一次 maps；
+/// 逐个调用 classify 时先用 for_maps 扫描，否则每次调用都要扫描全部 maps。
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DesktopLinuxClassifier {
+    runtimes: Option<Runtimes>,
+}
+
+/// 进程中加载的运行时，决定匿名区域的归属
+#[derive(Debug, Default, Clone, Copy)]
+struct Runtimes {
+    jvm: bool,
+    coreclr: bool,
+    mono: bool,
+    /// 加载了 jemalloc/mimalloc 时它们的 chunk 对齐
+    allocator_align: Option<usize>,
+}
+
+/// libmono-native.so 属于 .NET Core，不算 Mono
+const MONO_LIBRARIES: &[&str] = &["libmonosgen-2.0", "libmono-2.0", "libmonobdwgc-2.0"];
+
+/// glibc 非主 arena 的 heap 大小，也是其对齐
+const GLIBC_HEAP_MAX: usize = 64 << 20;
+
+/// HotSpot 的 Java 堆至少按 2M 对齐
+const JAVA_HEAP_ALIGN: usize = 2 << 20;
+/// CoreCLR 的 GC region 按 4M 对齐
+const CORECLR_REGION_ALIGN: usize = 4 << 20;
+/// Mono SGen 的 nursery 和 major 堆按 1M 对齐
+const MONO_HEAP_ALIGN: usize = 1 << 20;
+
+/// GC 堆先保留一大段地址空间再逐步提交：按 align 对齐，已提交部分后面紧跟匿名的 ---p 保留部分
+///
+/// 线程栈、malloc 的大块 mmap 不符合这个形式，运行时加载了也不会被当成托管堆。
+fn managed_heap(maps: &[MapRange], index: usize, align: usize) -> bool {
+    let map = &maps[index];
+    map.address.0.is_multiple_of(align) && maps.get(index + 1).is_some_and(|next| {
+        next.address.0 == map.address.1
+            && next.pathname.is_empty()
+            && !next.readable()
+            && !next.writable()
+    })
+}
+
+impl Runtimes {
+    fn scan(maps: &[MapRange]) -> Self {
+        let mut res = Runtimes::default();
+        for map in maps.iter() {
+            let file = map.pathname.rsplit('/').next().unwrap_or("");
+            if file == "libjvm.so" {
+                res.jvm = true;
+            } else if file == "libcoreclr.so" {
+                res.coreclr = true;
+            } else if MONO_LIBRARIES.contains(&file.split(".so").next().unwrap_or("")) {
+                res.mono = true;
+            } else if file.starts_with("libjemalloc") {
+                res.allocator_align = Some(2 << 20);
+            } else if file.starts_with("libmimalloc") {
+                res.allocator_align = Some(4 << 20);
+            }
+        }
+        res
+    }
+}
+
+/// jemalloc/mimalloc 的 chunk：按 align 对齐、大小为 align 的整数倍，
+/// 且下面没有紧贴的 ---p 保护页（那是线程栈）
+fn allocator_chunk(maps: &[MapRange], index: usize, align: usize) -> bool {
+    let map = &maps[index];
+    let guarded = index > 0 && {
+        let prev = &maps[index - 1];
+        prev.address.1 == map.address.0 && prev.pathname.is_empty() && !prev.readable() && !prev.writable()
+    };
+    map.address.0.is_multiple_of(align) && (map.address.1 - map.address.0).is_multiple_of(align) && !guarded
+}
+
+fn is_system_path(name: &str) -> bool {
+    name.starts_with("/usr/")
+        || name.starts_with("/lib")
+        || name.starts_with("/etc/")
+        || name.starts_with("/var/")
+        || name.starts_with("/nix/store/")
+        || name.starts_with("/snap/")
+}
+
+fn is_gpu(name: &str) -> bool {
+    name.starts_with("/dev/dri/")
+        || name.starts_with("/dev/nvidia")
+        || name.starts_with("/dev/kfd")
+        || name.starts_with("/dev/mali")
+        || name.starts_with("anon_inode:i915.gem")
+        || name.starts_with("anon_inode:dmabuf")
+        || name.starts_with("anon_inode:[drm")
+}
+
+/// Wine/Proton 加载的 PE 映像
+fn is_pe_image(lower: &str) -> bool {
+    lower.ends_with(".exe") || lower.ends_with(".dll") || lower.ends_with(".sys") || lower.ends_with(".drv")
+}
+
+fn is_wine_system(lower: &str) -> bool {
+    lower.contains("/windows/system32/")
+        || lower.contains("/windows/syswow64/")
+        || lower.contains("/lib/wine/")
+        || lower.contains("/lib64/wine/")
+        || lower.contains("/files/lib/wine/")
+}
+
+/// Unity 和 IL2CPP 的游戏映像
+fn is_unity_image(file: &str) -> bool {
+    file == "libil2cpp.so"
+        || file == "gameassembly.so"
+        || file == "unityplayer.so"
+        || file == "libunity.so"
+        || file == "libmain.so"
+}
+
+impl DesktopLinuxClassifier {
+    pub fn new() -> Self {
+        DesktopLinuxClassifier::default()
+    }
+
+    /// 预先扫描 maps 中的运行时，之后 classify 只能用于这份 maps
+    pub fn for_maps(maps: &[MapRange]) -> Self {
+        DesktopLinuxClassifier { runtimes: Some(Runtimes::scan(maps)) }
+    }
+
+    fn runtimes(&self, maps: &[MapRange]) -> Runtimes {
+        self.runtimes.unwrap_or_else(|| Runtimes::scan(maps))
+    }
+
+    fn classify_with(&self, maps: &[MapRange], index: usize, runtimes: &Runtimes) -> MemoryType {
+        let map = &maps[index];
+        let name = map.pathname.as_str();
+        let lower = name.to_ascii_lowercase();
+        let file = lower.rsplit('/').next().unwrap_or("");
+
+        if is_gpu(name) {
+            return MemoryType::V;
+        }
+        if name == "[vvar]" || name == "[vvar_vclock]" || name == "[vsyscall]" {
+            return MemoryType::Bad;
+        }
+        if name == "[vdso]" {
+            return MemoryType::Xs;
+        }
+        if name == "[heap]" {
+            return MemoryType::Ch;
+        }
+        if name.starts_with("[stack") || name.starts_with("[anon:stack") {
+            return MemoryType::S;
+        }
+        // .NET 的 JIT 代码双重映射
+        if name.starts_with("/memfd:doublemapper") {
+            return if map.executable() { MemoryType::Xa } else { MemoryType::Nh };
+        }
+        if is_pe_image(&lower) {
+            return match (map.executable(), is_wine_system(&lower)) {
+                (true, true) => MemoryType::Xs,
+                (true, false) => MemoryType::Xa,
+                (false, true) => MemoryType::Other,
+                (false, false) => MemoryType::Cd,
+            };
+        }
+        if is_unity_image(file) || file == "global-metadata.dat" {
+            return if map.executable() { MemoryType::Xa } else { MemoryType::Cd };
+        }
+        if name.starts_with('/') {
+            let system = is_system_path(name);
+            if map.executable() {
+                return if system { MemoryType::Xs } else { MemoryType::Xa };
+            }
+            if map.writable() && !system {
+                return MemoryType::Cd;
+            }
+            return MemoryType::Other;
+        }
+
+        // 以下为匿名区域，包括 prctl(PR_SET_VMA_ANON_NAME) 命名的
+        if map.executable() {
+            return MemoryType::Xa;
+        }
+        if let Some(anon) = name.strip_prefix("[anon:") {
+            let anon = anon.to_ascii_lowercase();
+            if anon.contains("malloc") || anon.contains("jemalloc") || anon.contains("arena") {
+                return MemoryType::Ca;
+            } else if anon.contains("java heap") {
+                return MemoryType::Jh;
+            } else if anon.contains("gc heap") || anon.contains("dotnet") {
+                return MemoryType::Nh;
+            } else if anon.contains("mono") {
+                return MemoryType::Mh;
+            }
+        } else if !name.is_empty() {
+            return MemoryType::Other;
+        }
+
+        if !map.readable() || !map.writable() {
+            return MemoryType::Other;
+        }
+
+        // 紧跟在模块可写数据段后面的匿名区域是 .bss
+        if index > 0 {
+            let prev = &maps[index - 1];
+            if prev.address.1 == map.address.0 && prev.pathname.starts_with('/') && prev.writable() {
+                return MemoryType::Cb;
+            }
+        }
+
+        // glibc 的非主 arena：按 64M 对齐，可写部分后面跟着 ---p 的保留部分
+        if map.address.0.is_multiple_of(GLIBC_HEAP_MAX) {
+            let size = map.address.1 - map.address.0;
+            let reserved = maps.get(index + 1).is_some_and(|next| {
+                next.address.0 == map.address.1
+                    && next.pathname.is_empty()
+                    && !next.readable()
+                    && next.address.1 - map.address.0 == GLIBC_HEAP_MAX
+            });
+            if reserved || size == GLIBC_HEAP_MAX {
+                return MemoryType::Ca;
+            }
+        }
+
+        if let Some(align) = runtimes.allocator_align && allocator_chunk(maps, index, align) {
+            return MemoryType::Ca;
+        }
+        if runtimes.jvm && managed_heap(maps, index, JAVA_HEAP_ALIGN) {
+            return MemoryType::Jh;
+        }
+        if runtimes.coreclr && managed_heap(maps, index, CORECLR_REGION_ALIGN) {
+            return MemoryType::Nh;
+        }
+        if runtimes.mono && managed_heap(maps, index, MONO_HEAP_ALIGN) {
+            return MemoryType::Mh;
+        }
+
+        MemoryType::A
+    }
+}
+
+impl RegionClassifier for DesktopLinuxClassifier {
+    fn classify(&self, maps: &[MapRange], index: usize) -> MemoryType {
+        self.classify_with(maps, index, &self.runtimes(maps))
+    }
+
+    fn classify_all(&self, maps: &mut [MapRange]) {
+        let runtimes = self.runtimes(maps);
+        for i in 0..maps.len() {
+            maps[i].memory_type = self.classify_with(maps, i, &runtimes);
+        }
+    }
+}
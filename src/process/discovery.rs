@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+use regex::Regex;
+use super::{Process, ProcessError};
+
+/// /proc 中一个进程的基本信息
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub uid: u32,
+    /// /proc/pid/comm，内核截断为 15 字节
+    pub name: String,
+    pub cmdline: Vec<String>,
+    /// /proc/pid/exe 的目标，没有权限或内核线程时为 None
+    pub exe: Option<PathBuf>,
+}
+
+/// 查找进程的条件
+#[derive(Debug, Clone)]
+pub enum ProcessQuery {
+    ///名字完全相同，比较 comm、argv[0] 的文件名和 Android 包名
+    Name(String),
+    ///comm、cmdline 或 exe 中包含子串
+    Contains(String),
+    ///正则匹配 comm 或以空格连接的 cmdline
+    Regex(Regex),
+    ///exe 路径完全相同
+    Exe(PathBuf),
+    ///Android 包名，忽略 `:service` 这样的子进程后缀
+    Package(String),
+}
+
+impl ProcessInfo {
+    pub fn from_pid(pid: u32) -> Result<Self, ProcessError> {
+        let status = std::fs::read_to_string(format!("/proc/{}/status", pid))
+            .map_err(|e| ProcessError::ProcStatusError(e.to_string()))?;
+
+        let mut name = String::new();
+        let mut ppid = 0;
+        let mut uid = 0;
+        for line in status.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key {
+                "Name" => name = value.to_string(),
+                "PPid" => ppid = value.parse().map_err(|e: std::num::ParseIntError| ProcessError::ProcStatusError(e.to_string()))?,
+                // Real, Effective, Saved, FS
+                "Uid" => uid = value.split_whitespace().next().unwrap_or("0").parse()
+                    .map_err(|e: std::num::ParseIntError| ProcessError::ProcStatusError(e.to_string()))?,
+                _ => {}
+            }
+        }
+
+        // status 里的 Name 会转义，优先用 comm
+        if let Ok(comm) = std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+            name = comm.trim_end_matches('\n').to_string();
+        }
+
+        let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid))
+            .map(|raw| raw.split(|b| *b == 0)
+                .filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect())
+            .unwrap_or_default();
+
+        let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+
+        Ok(ProcessInfo { pid, ppid, uid, name, cmdline, exe })
+    }
+
+    /// Android 应用的包名。zygote fork 出的进程把包名写在 argv[0]，如 `com.foo.bar:remote`
+    ///
+    /// exe 可读时必须是 app_process，读不到（其他应用的进程）时只看 argv[0] 的形式。
+    pub fn package(&self) -> Option<&str> {
+        let argv0 = self.cmdline.first()?;
+        if argv0.contains('/') {
+            return None;
+        }
+        if let Some(exe) = self.exe.as_ref()
+            && !exe.file_name().is_some_and(|n| n.to_string_lossy().starts_with("app_process")) {
+            return None;
+        }
+        let package = argv0.split(':').next().unwrap_or(argv0);
+        is_package_name(package).then_some(package)
+    }
+
+    pub fn process(&self) -> Process {
+        Process::new(self.pid)
+    }
+}
+
+/// 至少两段，每段为 `[A-Za-z][A-Za-z0-9_]*`
+fn is_package_name(s: &str) -> bool {
+    let mut parts = 0;
+    for part in s.split('.') {
+        let mut chars = part.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic()) || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return false;
+        }
+        parts += 1;
+    }
+    parts >= 2
+}
+
+impl ProcessQuery {
+    pub fn regex(pattern: &str) -> Result<Self, ProcessError> {
+        Regex::new(pattern)
+            .map(ProcessQuery::Regex)
+            .map_err(|e| ProcessError::QueryError(e.to_string()))
+    }
+
+    pub fn matches(&self, info: &ProcessInfo) -> bool {
+        match self {
+            ProcessQuery::Name(name) => {
+                info.name == *name
+                    || info.cmdline.first().is_some_and(|a| a.rsplit('/').next() == Some(name))
+                    || info.package() == Some(name)
+            },
+            ProcessQuery::Contains(s) => {
+                info.name.contains(s)
+                    || info.cmdline.iter().any(|a| a.contains(s))
+                    || info.exe.as_ref().is_some_and(|e| e.to_string_lossy().contains(s))
+            },
+            ProcessQuery::Regex(re) => re.is_match(&info.name) || re.is_match(&info.cmdline.join(" ")),
+            ProcessQuery::Exe(path) => info.exe.as_ref() == Some(path),
+            ProcessQuery::Package(package) => info.package() == Some(package),
+        }
+    }
+}
+
+/// 枚举 /proc 下所有进程，遍历过程中退出的进程会被跳过
+pub fn processes() -> Result<Vec<ProcessInfo>, ProcessError> {
+    let dir = std::fs::read_dir("/proc").map_err(|e| ProcessError::IoError(e.to_string()))?;
+    let mut res = Vec::new();
+    for entry in dir {
+        let entry = entry.map_err(|e| ProcessError::IoError(e.to_string()))?;
+        let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+            continue;
+        };
+        if let Ok(info) = ProcessInfo::from_pid(pid) {
+            res.push(info);
+        }
+    }
+    res.sort_by_key(|p| p.pid);
+    Ok(res)
+}
+
+/// 按条件查找进程
+pub fn find(query: &ProcessQuery) -> Result<Vec<ProcessInfo>, ProcessError> {
+    Ok(processes()?.into_iter().filter(|p| query.matches(p)).collect())
+}
@@ -0,0 +1,105 @@
+use super::MapRange;
+
+/// 两次读取 maps 之间的变化
+#[derive(Debug, Clone, Default)]
+pub struct MapsDiff {
+    pub added: Vec<MapRange>,
+    pub removed: Vec<MapRange>,
+    /// (旧, 新)，如 [heap] 向上或栈向下扩展
+    pub grown: Vec<(MapRange, MapRange)>,
+    /// (旧, 新)，如 munmap 掉一部分或 mprotect 拆分，拆分出的每一块各有一项
+    pub shrunk: Vec<(MapRange, MapRange)>,
+    /// (旧, 新)，可能同时出现在 grown/shrunk 中
+    pub perms_changed: Vec<(MapRange, MapRange)>,
+    /// 新 maps 的地址范围，按起始地址排序
+    mapped: Vec<(usize, usize)>,
+}
+
+#[inline]
+fn same_object(a: &MapRange, b: &MapRange) -> bool {
+    a.pathname == b.pathname && a.inode == b.inode && a.dev == b.dev
+}
+
+#[inline]
+fn overlaps(a: &MapRange, b: &MapRange) -> bool {
+    a.address.0 < b.address.1 && b.address.0 < a.address.1
+}
+
+impl MapsDiff {
+    /// old 和 new 都按起始地址排序，同时遍历一遍
+    pub fn new(old: &[MapRange], new: &[MapRange]) -> Self {
+        let mut diff = MapsDiff::default();
+        let mut used = vec![false; old.len()];
+        let mut first = 0;
+
+        for n in new.iter() {
+            // 完全在 n 之前的旧区域不会再和后面的新区域重叠
+            while first < old.len() && old[first].address.1 <= n.address.0 {
+                first += 1;
+            }
+            // 每个新区域只和一个旧区域配对，优先起始地址相同的；
+            // 一个旧区域可以和 mprotect 拆分出的每一块配对，各自比较权限
+            let candidates = old[first..].iter().enumerate()
+                .take_while(|(_, o)| o.address.0 < n.address.1)
+                .filter(|(_, o)| same_object(o, n) && overlaps(o, n));
+            let Some((i, o)) = candidates.min_by_key(|(_, o)| o.address.0 != n.address.0).map(|(i, o)| (first + i, o)) else {
+                diff.added.push(n.clone());
+                continue;
+            };
+
+            let old_size = o.address.1 - o.address.0;
+            let new_size = n.address.1 - n.address.0;
+            if o.address != n.address {
+                if new_size > old_size {
+                    diff.grown.push((o.clone(), n.clone()));
+                } else if new_size < old_size {
+                    diff.shrunk.push((o.clone(), n.clone()));
+                } else {
+                    // 大小相同但位置不同，当作移除后重新映射
+                    diff.added.push(n.clone());
+                    continue;
+                }
+            }
+            used[i] = true;
+            if o.perms != n.perms {
+                diff.perms_changed.push((o.clone(), n.clone()));
+            }
+        }
+
+        diff.removed.extend(old.iter().zip(used).filter(|(_, u)| !u).map(|(o, _)| o.clone()));
+        diff.mapped = new.iter().map(|m| m.address).collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.grown.is_empty()
+            && self.shrunk.is_empty()
+            && self.perms_changed.is_empty()
+    }
+
+    /// 地址在新 maps 中是否仍然有映射
+    pub fn is_mapped(&self, address: usize) -> bool {
+        let idx = self.mapped.partition_point(|r| r.0 <= address);
+        idx > 0 && address < self.mapped[idx - 1].1
+    }
+
+    /// 地址所在的区域是否被移除或缩小到不再包含它
+    pub fn invalidated(&self, address: usize) -> bool {
+        let in_old = self.removed.iter().any(|m| m.address.0 <= address && address < m.address.1)
+            || self.shrunk.iter().any(|(o, _)| o.address.0 <= address && address < o.address.1);
+        in_old && !self.is_mapped(address)
+    }
+
+    /// 去掉已经失效的地址，用于扫描结果和监视列表
+    pub fn retain_valid(&self, addresses: &mut Vec<usize>) {
+        addresses.retain(|a| !self.invalidated(*a));
+    }
+
+    /// 权限变得不可读的区域中的地址，扫描时应跳过
+    pub fn unreadable(&self, address: usize) -> bool {
+        self.perms_changed.iter()
+            .any(|(_, n)| !n.readable() && n.address.0 <= address && address < n.address.1)
+    }
+}
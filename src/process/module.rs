@@ -0,0 +1,151 @@
+use super::{MapRange, Process};
+
+/// 同一个文件在 maps 中连续的若干段，如 libil2cpp.so、game.exe
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// 文件名，如 libc.so.6
+    pub name: String,
+    pub path: String,
+    pub base: usize,
+    /// 从 base 到最后一段（含 .bss）末尾的大小
+    pub size: usize,
+    /// 文件映射的各段，每段有自己的权限
+    pub segments: Vec<MapRange>,
+    /// 紧跟在最后一段后面的匿名可写区域
+    pub bss: Option<MapRange>,
+}
+
+impl Module {
+    pub fn end(&self) -> usize {
+        self.base + self.size
+    }
+
+    pub fn contains(&self, address: usize) -> bool {
+        self.base <= address && address < self.end()
+    }
+
+    /// 地址相对于 base 的偏移
+    pub fn offset_of(&self, address: usize) -> Option<usize> {
+        self.contains(address).then(|| address - self.base)
+    }
+
+    /// 模块偏移对应的运行时地址
+    pub fn address_of(&self, offset: usize) -> usize {
+        self.base + offset
+    }
+
+    /// 地址所在的段，.bss 也算
+    pub fn segment_at(&self, address: usize) -> Option<&MapRange> {
+        self.segments.iter()
+            .chain(self.bss.iter())
+            .find(|m| m.address.0 <= address && address < m.address.1)
+    }
+
+    /// 可执行的段
+    pub fn code(&self) -> impl Iterator<Item = &MapRange> {
+        self.segments.iter().filter(|m| m.executable())
+    }
+
+    /// name 为文件名、完整路径，或文件名到 `.so` 为止的部分（libc 和 libc.so 都匹配 libc.so.6，不匹配 libcrypto.so）
+    pub fn matches(&self, name: &str) -> bool {
+        if self.name == name || self.path == name {
+            return true;
+        }
+        self.name.match_indices(".so")
+            .filter(|(i, _)| self.name[i + 3..].is_empty() || self.name[i + 3..].starts_with('.'))
+            .any(|(i, _)| name == &self.name[..i] || name == &self.name[..i + 3])
+    }
+}
+
+#[inline]
+fn same_file(a: &MapRange, b: &MapRange) -> bool {
+    a.pathname == b.pathname && a.inode == b.inode && a.dev == b.dev
+}
+
+/// 段之间没有权限的匿名区域，64K 对齐的库和 bionic 的加载器会在段之间留下
+#[inline]
+fn is_gap(map: &MapRange) -> bool {
+    map.pathname.is_empty() && !map.readable() && !map.writable() && !map.executable()
+}
+
+#[inline]
+fn is_bss(module_end: usize, map: &MapRange) -> bool {
+    map.address.0 == module_end
+        && map.writable()
+        && (map.pathname.is_empty() || map.pathname == "[anon:.bss]")
+}
+
+/// 把 maps 中属于同一文件的连续区域合并成模块
+///
+/// 同一文件再次以 offset 0 出现时视为重新加载，另起一个模块。
+/// 段之间的 ---p 匿名区域会跳过，只要后面是同一文件的更大偏移
+pub fn modules_from_maps(maps: &[MapRange]) -> Vec<Module> {
+    let mut res = Vec::<Module>::new();
+    let mut i = 0;
+    while i < maps.len() {
+        let first = &maps[i];
+        if !first.pathname.starts_with('/') {
+            i += 1;
+            continue;
+        }
+
+        let mut segments = vec![first.clone()];
+        i += 1;
+        loop {
+            let mut next = i;
+            while next < maps.len() && is_gap(&maps[next]) {
+                next += 1;
+            }
+            let last = segments.last().unwrap();
+            let Some(map) = maps.get(next).filter(|m| same_file(first, m) && m.offset != 0) else {
+                break;
+            };
+            if next > i && map.offset <= last.offset {
+                break;
+            }
+            segments.push(map.clone());
+            i = next + 1;
+        }
+
+        let last_end = segments.last().unwrap().address.1;
+        let bss = maps.get(i).filter(|m| is_bss(last_end, m)).cloned();
+        if bss.is_some() {
+            i += 1;
+        }
+
+        let base = first.address.0;
+        let end = bss.as_ref().map_or(last_end, |b| b.address.1);
+        res.push(Module {
+            name: first.pathname.rsplit('/').next().unwrap_or(&first.pathname).to_string(),
+            path: first.pathname.clone(),
+            base,
+            size: end - base,
+            segments,
+            bss,
+        });
+    }
+    res
+}
+
+impl Process {
+    /// 从已读取的 maps 构造模块列表，需要先调用 maps()
+    pub fn modules(&self) -> Vec<Module> {
+        modules_from_maps(&self.maps)
+    }
+
+    /// 按名字查找第一个匹配的模块
+    pub fn module_by_name(&self, name: &str) -> Option<Module> {
+        let modules = self.modules();
+        // 完全匹配优先于前缀匹配
+        let exact = modules.iter().position(|m| m.name == name || m.path == name);
+        match exact {
+            Some(i) => modules.into_iter().nth(i),
+            None => modules.into_iter().find(|m| m.matches(name)),
+        }
+    }
+
+    /// 地址所在的模块
+    pub fn module_at(&self, address: usize) -> Option<Module> {
+        self.modules().into_iter().find(|m| m.contains(address))
+    }
+}
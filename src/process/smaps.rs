@@ -0,0 +1,104 @@
+use super::{Process, ProcessError};
+
+/// /proc/pid/smaps 中一个区域的统计，大小均为字节
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionStats {
+    pub size: usize,
+    pub rss: usize,
+    pub pss: usize,
+    pub shared_clean: usize,
+    pub shared_dirty: usize,
+    pub private_clean: usize,
+    pub private_dirty: usize,
+    pub referenced: usize,
+    pub anonymous: usize,
+    pub swap: usize,
+    pub swap_pss: usize,
+    /// 两个字母的标志，如 rd wr mr mw me ac sd
+    pub vm_flags: Vec<String>,
+}
+
+impl RegionStats {
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.vm_flags.iter().any(|f| f == flag)
+    }
+
+    /// 有被写过的私有页，未被碰过的文件映射为 0
+    pub fn is_dirty(&self) -> bool {
+        self.private_dirty > 0
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ProcessError> {
+        if key == "VmFlags" {
+            self.vm_flags = value.split_whitespace().map(|s| s.to_string()).collect();
+            return Ok(());
+        }
+
+        let field = match key {
+            "Size" => &mut self.size,
+            "Rss" => &mut self.rss,
+            "Pss" => &mut self.pss,
+            "Shared_Clean" => &mut self.shared_clean,
+            "Shared_Dirty" => &mut self.shared_dirty,
+            "Private_Clean" => &mut self.private_clean,
+            "Private_Dirty" => &mut self.private_dirty,
+            "Referenced" => &mut self.referenced,
+            "Anonymous" => &mut self.anonymous,
+            "Swap" => &mut self.swap,
+            "SwapPss" => &mut self.swap_pss,
+            _ => return Ok(()),
+        };
+        let kb = value.trim_end_matches("kB").trim();
+        *field = kb.parse::<usize>().map_err(|e| ProcessError::SmapsParseError(format!("{key}: {e}")))? * 1024;
+        Ok(())
+    }
+}
+
+/// 解析 smaps 文本，返回 (起始地址, 结束地址, 统计)
+pub fn parse_smaps(text: &str) -> Result<Vec<(usize, usize, RegionStats)>, ProcessError> {
+    let mut res = Vec::<(usize, usize, RegionStats)>::new();
+    for line in text.lines() {
+        let Some(first) = line.split_whitespace().next() else {
+            continue;
+        };
+        if let Some(key) = first.strip_suffix(':') {
+            let (_, value) = line.split_once(':').unwrap();
+            let Some(last) = res.last_mut() else {
+                return Err(ProcessError::SmapsParseError(format!("Field before header: {line}")));
+            };
+            last.2.set(key, value.trim())?;
+        } else {
+            let (start, end) = first.split_once('-')
+                .ok_or(ProcessError::SmapsParseError(format!("Bad header: {line}")))?;
+            let start = usize::from_str_radix(start, 16).map_err(|e| ProcessError::SmapsParseError(e.to_string()))?;
+            let end = usize::from_str_radix(end, 16).map_err(|e| ProcessError::SmapsParseError(e.to_string()))?;
+            res.push((start, end, RegionStats::default()));
+        }
+    }
+    Ok(res)
+}
+
+impl Process {
+    /// 读取 /proc/pid/smaps，把统计填到 maps 中对应的区域
+    pub fn smaps(&mut self) -> Result<(), ProcessError> {
+        self.maps()?;
+        let text = std::fs::read_to_string(format!("/proc/{}/smaps", self.pid))
+            .map_err(|e| ProcessError::SmapsOpenError(e.to_string()))?;
+        for (start, end, stats) in parse_smaps(&text)? {
+            if let Some(map) = self.maps.iter_mut().find(|m| m.address == (start, end)) {
+                map.stats = Some(stats);
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取 /proc/pid/smaps_rollup，整个进程的汇总
+    pub fn smaps_rollup(&self) -> Result<RegionStats, ProcessError> {
+        let text = std::fs::read_to_string(format!("/proc/{}/smaps_rollup", self.pid))
+            .map_err(|e| ProcessError::SmapsOpenError(e.to_string()))?;
+        parse_smaps(&text)?
+            .pop()
+            .map(|(_, _, stats)| stats)
+            .ok_or(ProcessError::SmapsParseError("Empty smaps_rollup".to_string()))
+    }
+}
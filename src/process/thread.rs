@@ -0,0 +1,129 @@
+use super::{MapRange, Process, ProcessError};
+
+/// /proc/pid/task/tid/stat 中的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    ///R
+    Running,
+    ///S
+    Sleeping,
+    ///D
+    DiskSleep,
+    ///T
+    Stopped,
+    ///t
+    TracingStop,
+    ///Z
+    Zombie,
+    ///X
+    Dead,
+    ///I
+    Idle,
+    Other(char),
+}
+
+impl ThreadState {
+    pub fn from_char(c: char) -> Self {
+        match c {
+            'R' => ThreadState::Running,
+            'S' => ThreadState::Sleeping,
+            'D' => ThreadState::DiskSleep,
+            'T' => ThreadState::Stopped,
+            't' => ThreadState::TracingStop,
+            'Z' => ThreadState::Zombie,
+            'X' | 'x' => ThreadState::Dead,
+            'I' => ThreadState::Idle,
+            c => ThreadState::Other(c),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub tid: u32,
+    pub name: String,
+    pub state: ThreadState,
+    /// 线程栈所在的区域，找不到时为 None
+    pub stack: Option<MapRange>,
+}
+
+impl Thread {
+    pub fn is_main(&self, pid: u32) -> bool {
+        self.tid == pid
+    }
+}
+
+fn read_state(pid: u32, tid: u32) -> Result<ThreadState, ProcessError> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid))
+        .map_err(|e| ProcessError::ThreadReadError(e.to_string()))?;
+    // comm 中可能有空格和括号，状态在最后一个 ')' 之后
+    let state = stat.rsplit_once(')')
+        .and_then(|(_, rest)| rest.trim_start().chars().next())
+        .ok_or(ProcessError::ThreadReadError(format!("Bad stat for {tid}")))?;
+    Ok(ThreadState::from_char(state))
+}
+
+/// /proc/pid/task/tid/syscall 的倒数第二项是用户态栈指针，需要 ptrace 权限
+fn read_sp(pid: u32, tid: u32) -> Option<usize> {
+    let syscall = std::fs::read_to_string(format!("/proc/{}/task/{}/syscall", pid, tid)).ok()?;
+    let mut parts = syscall.split_whitespace().rev();
+    parts.next()?;
+    usize::from_str_radix(parts.next()?.trim_start_matches("0x"), 16).ok()
+}
+
+impl Process {
+    /// 列出 /proc/pid/task 下的线程 id
+    pub fn tids(&self) -> Result<Vec<u32>, ProcessError> {
+        let dir = std::fs::read_dir(format!("/proc/{}/task", self.pid))
+            .map_err(|e| ProcessError::ThreadReadError(e.to_string()))?;
+        let mut res = Vec::new();
+        for entry in dir {
+            let entry = entry.map_err(|e| ProcessError::ThreadReadError(e.to_string()))?;
+            if let Some(tid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) {
+                res.push(tid);
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+
+    /// 线程的栈区域
+    ///
+    /// 按 maps 中的 `[stack]`、`[stack:tid]`、`[anon:stack_and_tls:tid]` 匹配，
+    /// 都没有时（如 glibc 的线程栈）用栈指针所在的区域
+    pub fn thread_stack(&self, tid: u32) -> Option<&MapRange> {
+        let named = if tid == self.pid {
+            self.maps.iter().find(|m| m.pathname == "[stack]")
+        } else {
+            let stack = format!("[stack:{}]", tid);
+            let bionic = format!("[anon:stack_and_tls:{}]", tid);
+            self.maps.iter().find(|m| m.pathname == stack || m.pathname == bionic)
+        };
+
+        named.or_else(|| {
+            let sp = read_sp(self.pid, tid)?;
+            self.maps.iter().find(|m| m.address.0 <= sp && sp < m.address.1)
+        })
+    }
+
+    /// 列出所有线程，需要先调用 maps() 才能找到栈
+    pub fn threads(&self) -> Result<Vec<Thread>, ProcessError> {
+        let mut res = Vec::new();
+        for tid in self.tids()? {
+            // 枚举之后退出的线程直接跳过
+            let Ok(state) = read_state(self.pid, tid) else {
+                continue;
+            };
+            let name = std::fs::read_to_string(format!("/proc/{}/task/{}/comm", self.pid, tid))
+                .map(|s| s.trim_end_matches('\n').to_string())
+                .unwrap_or_default();
+            res.push(Thread {
+                tid,
+                name,
+                state,
+                stack: self.thread_stack(tid).cloned(),
+            });
+        }
+        Ok(res)
+    }
+}
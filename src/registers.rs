@@ -0,0 +1,251 @@
+use bytemuck::{Pod, Zeroable};
+use crate::elf::{NT_ARM_SYSTEM_CALL, NT_PRFPREG, NT_PRSTATUS};
+use crate::process::MapRange;
+
+/// 可以用 PTRACE_GETREGSET 读写的寄存器组，NT_TYPE 为对应的 regset
+pub trait RegisterSet: Pod {
+    const NT_TYPE: u32;
+}
+
+/// 通用寄存器
+pub trait GeneralRegisters: RegisterSet {
+    fn pc(&self) -> u64;
+    fn sp(&self) -> u64;
+    fn set_pc(&mut self, pc: u64);
+    fn set_sp(&mut self, sp: u64);
+    /// 按名字列出所有寄存器
+    fn values(&self) -> Vec<(&'static str, u64)>;
+    /// 设置系统调用号和最多 6 个参数
+    fn set_syscall(&mut self, number: u64, args: &[u64]);
+    /// 系统调用的返回值，失败时为负的 errno
+    fn syscall_result(&self) -> i64;
+}
+
+/// x86_64 的 user_regs_struct
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct X86_64Regs {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub eflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+impl RegisterSet for X86_64Regs {
+    const NT_TYPE: u32 = NT_PRSTATUS;
+}
+
+impl GeneralRegisters for X86_64Regs {
+    fn pc(&self) -> u64 {
+        self.rip
+    }
+
+    fn sp(&self) -> u64 {
+        self.rsp
+    }
+
+    fn set_pc(&mut self, pc: u64) {
+        self.rip = pc;
+    }
+
+    fn set_sp(&mut self, sp: u64) {
+        self.rsp = sp;
+    }
+
+    fn values(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("rax", self.rax), ("rbx", self.rbx), ("rcx", self.rcx), ("rdx", self.rdx),
+            ("rsi", self.rsi), ("rdi", self.rdi), ("rbp", self.rbp), ("rsp", self.rsp),
+            ("r8", self.r8), ("r9", self.r9), ("r10", self.r10), ("r11", self.r11),
+            ("r12", self.r12), ("r13", self.r13), ("r14", self.r14), ("r15", self.r15),
+            ("rip", self.rip), ("eflags", self.eflags), ("orig_rax", self.orig_rax),
+            ("fs_base", self.fs_base), ("gs_base", self.gs_base),
+            ("cs", self.cs), ("ss", self.ss), ("ds", self.ds), ("es", self.es), ("fs", self.fs), ("gs", self.gs),
+        ]
+    }
+
+    fn set_syscall(&mut self, number: u64, args: &[u64]) {
+        self.rax = number;
+        // 不是停在系统调用里，恢复时内核不会去重启它
+        self.orig_rax = u64::MAX;
+        let regs = [&mut self.rdi, &mut self.rsi, &mut self.rdx, &mut self.r10, &mut self.r8, &mut self.r9];
+        for (reg, arg) in regs.into_iter().zip(args) {
+            *reg = *arg;
+        }
+    }
+
+    fn syscall_result(&self) -> i64 {
+        self.rax as i64
+    }
+}
+
+/// x86_64 的 user_fpregs_struct，即 FXSAVE 区域
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct X86_64FpRegs {
+    pub cwd: u16,
+    pub swd: u16,
+    pub ftw: u16,
+    pub fop: u16,
+    pub rip: u64,
+    pub rdp: u64,
+    pub mxcsr: u32,
+    pub mxcr_mask: u32,
+    /// 8 个 x87 寄存器，每个占 16 字节
+    pub st_space: [u32; 32],
+    /// 16 个 xmm 寄存器，每个占 16 字节
+    pub xmm_space: [u32; 64],
+    pub padding: [u32; 24],
+}
+
+impl Default for X86_64FpRegs {
+    fn default() -> Self {
+        Self::zeroed()
+    }
+}
+
+impl RegisterSet for X86_64FpRegs {
+    const NT_TYPE: u32 = NT_PRFPREG;
+}
+
+impl X86_64FpRegs {
+    pub fn xmm(&self, i: usize) -> u128 {
+        bytemuck::pod_read_unaligned(bytemuck::cast_slice(&self.xmm_space[i * 4..i * 4 + 4]))
+    }
+
+    pub fn set_xmm(&mut self, i: usize, value: u128) {
+        self.xmm_space[i * 4..i * 4 + 4].copy_from_slice(bytemuck::cast_slice(&[value]));
+    }
+}
+
+/// aarch64 的 user_pt_regs
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct Aarch64Regs {
+    /// x0 - x30，x29 为 fp，x30 为 lr
+    pub regs: [u64; 31],
+    pub sp: u64,
+    pub pc: u64,
+    pub pstate: u64,
+}
+
+impl RegisterSet for Aarch64Regs {
+    const NT_TYPE: u32 = NT_PRSTATUS;
+}
+
+impl GeneralRegisters for Aarch64Regs {
+    fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    fn sp(&self) -> u64 {
+        self.sp
+    }
+
+    fn set_pc(&mut self, pc: u64) {
+        self.pc = pc;
+    }
+
+    fn set_sp(&mut self, sp: u64) {
+        self.sp = sp;
+    }
+
+    fn values(&self) -> Vec<(&'static str, u64)> {
+        const NAMES: [&str; 31] = [
+            "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10",
+            "x11", "x12", "x13", "x14", "x15", "x16", "x17", "x18", "x19", "x20",
+            "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28", "fp", "lr",
+        ];
+        let mut res: Vec<_> = NAMES.iter().copied().zip(self.regs.iter().copied()).collect();
+        res.extend([("sp", self.sp), ("pc", self.pc), ("pstate", self.pstate)]);
+        res
+    }
+
+    /// 系统调用重启由 Aarch64SyscallNo 控制
+    fn set_syscall(&mut self, number: u64, args: &[u64]) {
+        self.regs[8] = number;
+        for (i, arg) in args.iter().take(6).enumerate() {
+            self.regs[i] = *arg;
+        }
+    }
+
+    fn syscall_result(&self) -> i64 {
+        self.regs[0] as i64
+    }
+}
+
+/// aarch64 的 NT_ARM_SYSTEM_CALL，当前的系统调用号，-1 表示恢复时不重启系统调用
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct Aarch64SyscallNo {
+    pub nr: i32,
+}
+
+impl RegisterSet for Aarch64SyscallNo {
+    const NT_TYPE: u32 = NT_ARM_SYSTEM_CALL;
+}
+
+/// aarch64 的 user_fpsimd_state
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct Aarch64FpRegs {
+    pub vregs: [u128; 32],
+    pub fpsr: u32,
+    pub fpcr: u32,
+    pub reserved: [u32; 2],
+}
+
+impl RegisterSet for Aarch64FpRegs {
+    const NT_TYPE: u32 = NT_PRFPREG;
+}
+
+/// 当前架构的通用寄存器
+#[cfg(target_arch = "aarch64")]
+pub type Regs = Aarch64Regs;
+#[cfg(not(target_arch = "aarch64"))]
+pub type Regs = X86_64Regs;
+
+/// 当前架构的浮点寄存器
+#[cfg(target_arch = "aarch64")]
+pub type FpRegs = Aarch64FpRegs;
+#[cfg(not(target_arch = "aarch64"))]
+pub type FpRegs = X86_64FpRegs;
+
+/// 寄存器中指向已映射内存的值，可作为指针扫描的起点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterRoot {
+    pub tid: u32,
+    pub register: &'static str,
+    pub value: usize,
+}
+
+/// 找出 regs 中落在可读区域内的值
+pub fn roots<G: GeneralRegisters>(tid: u32, regs: &G, maps: &[MapRange]) -> Vec<RegisterRoot> {
+    regs.values().into_iter()
+        .map(|(register, value)| RegisterRoot { tid, register, value: value as usize })
+        .filter(|r| maps.iter().any(|m| m.readable() && m.address.0 <= r.value && r.value < m.address.1))
+        .collect()
+}
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use crate::memory::{from_bytes, MemoryReader, MemoryWriter};
+
+#[derive(Debug)]
+pub enum StructError {
+    IoError(String),
+    ParseError { line: usize, reason: String },
+    UnknownType(String),
+    UnknownField(String),
+    TypeMismatch(String),
+    ReadError(String),
+    WriteError(String),
+    /// 结构体直接或间接内嵌自身，大小无法确定
+    RecursiveType(String),
+    SizeOverflow(String),
+}
+
+impl fmt::Display for StructError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+const POINTER_SIZE: usize = std::mem::size_of::<usize>();
+/// 一次 decode 默认最多读取的字节数
+pub const DEFAULT_DECODE_LIMIT: usize = 0x100_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    U8, U16, U32, U64,
+    I8, I16, I32, I64,
+    F32, F64,
+    Bool,
+}
+
+impl Primitive {
+    pub fn size(self) -> usize {
+        match self {
+            Primitive::U8 | Primitive::I8 | Primitive::Bool => 1,
+            Primitive::U16 | Primitive::I16 => 2,
+            Primitive::U32 | Primitive::I32 | Primitive::F32 => 4,
+            Primitive::U64 | Primitive::I64 | Primitive::F64 => 8,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "u8" => Primitive::U8,
+            "u16" => Primitive::U16,
+            "u32" => Primitive::U32,
+            "u64" => Primitive::U64,
+            "i8" => Primitive::I8,
+            "i16" => Primitive::I16,
+            "i32" => Primitive::I32,
+            "i64" => Primitive::I64,
+            "f32" => Primitive::F32,
+            "f64" => Primitive::F64,
+            "bool" => Primitive::Bool,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn decode(self, b: &[u8]) -> Value {
+        match self {
+            Primitive::U8 => Value::U8(b[0]),
+            Primitive::U16 => Value::U16(from_bytes::<u16>(b).unwrap()),
+            Primitive::U32 => Value::U32(from_bytes::<u32>(b).unwrap()),
+            Primitive::U64 => Value::U64(from_bytes::<u64>(b).unwrap()),
+            Primitive::I8 => Value::I8(b[0] as i8),
+            Primitive::I16 => Value::I16(from_bytes::<i16>(b).unwrap()),
+            Primitive::I32 => Value::I32(from_bytes::<i32>(b).unwrap()),
+            Primitive::I64 => Value::I64(from_bytes::<i64>(b).unwrap()),
+            Primitive::F32 => Value::F32(from_bytes::<f32>(b).unwrap()),
+            Primitive::F64 => Value::F64(from_bytes::<f64>(b).unwrap()),
+            // 远程的 bool 可能是任意字节，按非 0 处理
+            Primitive::Bool => Value::Bool(b[0] != 0),
+        }
+    }
+
+    /// 把文本解析成该类型的值，整数支持 0x 前缀
+    pub fn parse_value(self, s: &str) -> Result<Value, StructError> {
+        let s = s.trim();
+        let err = |e: String| StructError::TypeMismatch(format!("{s:?} as {self:?}: {e}"));
+        let int = |s: &str| -> Result<i128, StructError> {
+            let (neg, digits) = match s.strip_prefix('-') {
+                Some(d) => (true, d),
+                None => (false, s),
+            };
+            let v = match digits.strip_prefix("0x") {
+                Some(hex) => i128::from_str_radix(hex, 16),
+                None => digits.parse::<i128>(),
+            }.map_err(|e| err(e.to_string()))?;
+            Ok(if neg { -v } else { v })
+        };
+        macro_rules! int_value {
+            ($variant:ident, $ty:ty) => {
+                Value::$variant(<$ty>::try_from(int(s)?).map_err(|e| err(e.to_string()))?)
+            };
+        }
+        Ok(match self {
+            Primitive::U8 => int_value!(U8, u8),
+            Primitive::U16 => int_value!(U16, u16),
+            Primitive::U32 => int_value!(U32, u32),
+            Primitive::U64 => int_value!(U64, u64),
+            Primitive::I8 => int_value!(I8, i8),
+            Primitive::I16 => int_value!(I16, i16),
+            Primitive::I32 => int_value!(I32, i32),
+            Primitive::I64 => int_value!(I64, i64),
+            Primitive::F32 => Value::F32(s.parse().map_err(|e: std::num::ParseFloatError| err(e.to_string()))?),
+            Primitive::F64 => Value::F64(s.parse().map_err(|e: std::num::ParseFloatError| err(e.to_string()))?),
+            Primitive::Bool => Value::Bool(match s {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Err(err("expected true/false".to_string())),
+            }),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Primitive(Primitive),
+    Array(Box<FieldType>, usize),
+    /// 内嵌的结构体，按名字引用
+    Struct(String),
+    /// 指针，None 为 void*
+    Pointer(Option<Box<FieldType>>),
+}
+
+impl FieldType {
+    /// u32、[f32; 3]、Vec3、*Player、*void
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Some(inner) = s.strip_prefix('*') {
+            let inner = inner.trim();
+            return if inner == "void" {
+                Ok(FieldType::Pointer(None))
+            } else {
+                Ok(FieldType::Pointer(Some(Box::new(FieldType::parse(inner)?))))
+            };
+        }
+        if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let (elem, count) = inner.rsplit_once(';').ok_or(format!("bad array type {s:?}"))?;
+            let count = parse_number(count.trim()).ok_or(format!("bad array length {count:?}"))?;
+            return Ok(FieldType::Array(Box::new(FieldType::parse(elem)?), count));
+        }
+        if let Some(p) = Primitive::from_name(s) {
+            return Ok(FieldType::Primitive(p));
+        }
+        if !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Ok(FieldType::Struct(s.to_string()));
+        }
+        Err(format!("bad type {s:?}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    pub name: String,
+    pub offset: usize,
+    pub ty: FieldType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub name: String,
+    /// 未指定时为最后一个字段的末尾
+    pub size: Option<usize>,
+    pub fields: Vec<FieldDef>,
+}
+
+/// 解码得到的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    U8(u8), U16(u16), U32(u32), U64(u64),
+    I8(i8), I16(i16), I32(i32), I64(i64),
+    F32(f32), F64(f64),
+    Bool(bool),
+    /// target 为跟随指针读到的值，空指针、超过深度或读取失败时为 None
+    Pointer { address: usize, target: Option<Box<Value>> },
+    Array(Vec<Value>),
+    Struct { name: String, address: usize, fields: Vec<(String, Value)> },
+}
+
+impl Value {
+    /// 按路径取子值，如 `pos.x`、`items[2]`
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut cur = self;
+        for seg in parse_path(path).ok()? {
+            cur = match (cur, seg) {
+                (Value::Struct { fields, .. }, PathSeg::Field(name)) => &fields.iter().find(|(n, _)| n == name)?.1,
+                (Value::Array(items), PathSeg::Index(i)) => items.get(i)?,
+                (Value::Pointer { target: Some(t), .. }, seg) => match (t.as_ref(), seg) {
+                    (Value::Struct { fields, .. }, PathSeg::Field(name)) => &fields.iter().find(|(n, _)| n == name)?.1,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+        }
+        Some(cur)
+    }
+
+    fn encode(&self, prim: Primitive) -> Result<Vec<u8>, StructError> {
+        let mismatch = || StructError::TypeMismatch(format!("{self:?} as {prim:?}"));
+        Ok(match (prim, self) {
+            (Primitive::U8, Value::U8(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::U16, Value::U16(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::U32, Value::U32(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::U64, Value::U64(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::I8, Value::I8(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::I16, Value::I16(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::I32, Value::I32(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::I64, Value::I64(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::F32, Value::F32(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::F64, Value::F64(v)) => v.to_ne_bytes().to_vec(),
+            (Primitive::Bool, Value::Bool(v)) => vec![*v as u8],
+            _ => return Err(mismatch()),
+        })
+    }
+
+    fn fmt_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Value::U8(v) => write!(f, "{v}"),
+            Value::U16(v) => write!(f, "{v}"),
+            Value::U32(v) => write!(f, "{v}"),
+            Value::U64(v) => write!(f, "{v}"),
+            Value::I8(v) => write!(f, "{v}"),
+            Value::I16(v) => write!(f, "{v}"),
+            Value::I32(v) => write!(f, "{v}"),
+            Value::I64(v) => write!(f, "{v}"),
+            Value::F32(v) => write!(f, "{v}"),
+            Value::F64(v) => write!(f, "{v}"),
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Pointer { address, target } => {
+                write!(f, "{address:#x}")?;
+                if let Some(t) = target {
+                    write!(f, " -> ")?;
+                    t.fmt_indent(f, indent)?;
+                }
+                Ok(())
+            },
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_indent(f, indent)?;
+                }
+                write!(f, "]")
+            },
+            Value::Struct { name, address, fields } => {
+                writeln!(f, "{name} @ {address:#x} {{")?;
+                for (field, value) in fields {
+                    write!(f, "{:width$}{field}: ", "", width = indent + 4)?;
+                    value.fmt_indent(f, indent + 4)?;
+                    writeln!(f)?;
+                }
+                write!(f, "{:width$}}}", "", width = indent)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indent(f, 0)
+    }
+}
+
+fn parse_number(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PathSeg<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+/// `pos.x`、`items[2].id`
+fn parse_path(path: &str) -> Result<Vec<PathSeg<'_>>, StructError> {
+    let mut res = Vec::new();
+    for part in path.split('.') {
+        let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !name.is_empty() {
+            res.push(PathSeg::Field(name));
+        }
+        while let Some(inner) = rest.strip_prefix('[') {
+            let (idx, tail) = inner.split_once(']').ok_or(StructError::UnknownField(path.to_string()))?;
+            res.push(PathSeg::Index(parse_number(idx).ok_or(StructError::UnknownField(path.to_string()))?));
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(StructError::UnknownField(path.to_string()));
+        }
+    }
+    Ok(res)
+}
+
+/// 结构体定义的集合
+///
+/// 文本格式，`#` 开头为注释，偏移省略时紧跟上一个字段，`size` 可省略：
+///
+/// ```text
+/// struct Vec3 {
+///     x: f32
+///     y: f32
+///     z: f32
+/// }
+///
+/// struct Player size 0x100 {
+///     0x10 hp: i32
+///     0x18 pos: Vec3
+///     0x30 items: [u32; 8]
+///     0x50 target: *Player
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StructRegistry {
+    structs: HashMap<String, StructDef>,
+    /// decode 一次最多读取的字节数，定义中声明的大小不可信
+    decode_limit: usize,
+}
+
+impl Default for StructRegistry {
+    fn default() -> Self {
+        StructRegistry { structs: HashMap::new(), decode_limit: DEFAULT_DECODE_LIMIT }
+    }
+}
+
+impl StructRegistry {
+    pub fn new() -> Self {
+        StructRegistry::default()
+    }
+
+    pub fn decode_limit(mut self, limit: usize) -> Self {
+        self.decode_limit = limit;
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, StructError> {
+        let text = std::fs::read_to_string(path).map_err(|e| StructError::IoError(e.to_string()))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, StructError> {
+        let mut registry = StructRegistry::new();
+        let mut current: Option<StructDef> = None;
+        let mut next_offset = Some(0);
+
+        for (i, raw) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |reason: String| StructError::ParseError { line: line_no, reason };
+            let line = raw.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match current.as_mut() {
+                None => {
+                    let header = line.strip_prefix("struct ")
+                        .and_then(|h| h.strip_suffix('{'))
+                        .ok_or(err(format!("expected `struct Name {{`, got {line:?}")))?
+                        .trim();
+                    let mut words = header.split_whitespace();
+                    let name = words.next().ok_or(err("missing struct name".to_string()))?;
+                    let size = match (words.next(), words.next()) {
+                        (None, _) => None,
+                        (Some("size"), Some(size)) => Some(parse_number(size).ok_or(err(format!("bad size {size:?}")))?),
+                        _ => return Err(err(format!("bad struct header {header:?}"))),
+                    };
+                    current = Some(StructDef { name: name.to_string(), size, fields: Vec::new() });
+                    next_offset = Some(0);
+                },
+                Some(def) if line == "}" => {
+                    let def = std::mem::replace(def, StructDef { name: String::new(), size: None, fields: Vec::new() });
+                    current = None;
+                    registry.add(def);
+                },
+                Some(def) => {
+                    let (left, ty) = line.split_once(':').ok_or(err(format!("expected `[offset] name: type`, got {line:?}")))?;
+                    let ty = FieldType::parse(ty).map_err(err)?;
+                    let mut words = left.split_whitespace();
+                    let (offset, name) = match (words.next(), words.next()) {
+                        (Some(name), None) => (next_offset.ok_or(err(format!("offset of {name:?} unknown after a struct defined later, give it explicitly")))?, name),
+                        (Some(offset), Some(name)) => (parse_number(offset).ok_or(err(format!("bad offset {offset:?}")))?, name),
+                        _ => return Err(err(format!("bad field {left:?}"))),
+                    };
+                    // 内嵌的结构体可能定义在后面，大小未知时后面的字段必须写明偏移
+                    next_offset = registry.size_of(&ty).ok().and_then(|size| offset.checked_add(size));
+                    def.fields.push(FieldDef { name: name.to_string(), offset, ty });
+                },
+            }
+        }
+
+        if let Some(def) = current {
+            return Err(StructError::ParseError { line: text.lines().count(), reason: format!("struct {} not closed", def.name) });
+        }
+        // 所有引用的结构体都要有定义
+        for name in registry.structs.keys() {
+            registry.struct_size(name)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn add(&mut self, def: StructDef) {
+        self.structs.insert(def.name.clone(), def);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StructDef> {
+        self.structs.get(name)
+    }
+
+    fn def(&self, name: &str) -> Result<&StructDef, StructError> {
+        self.get(name).ok_or(StructError::UnknownType(name.to_string()))
+    }
+
+    pub fn struct_size(&self, name: &str) -> Result<usize, StructError> {
+        self.struct_size_in(name, &mut Vec::new())
+    }
+
+    pub fn size_of(&self, ty: &FieldType) -> Result<usize, StructError> {
+        self.size_of_in(ty, &mut Vec::new())
+    }
+
+    /// visiting 为正在计算大小的结构体，再次遇到说明内嵌了自身
+    fn struct_size_in(&self, name: &str, visiting: &mut Vec<String>) -> Result<usize, StructError> {
+        let def = self.def(name)?;
+        if let Some(size) = def.size {
+            return Ok(size);
+        }
+        if visiting.iter().any(|n| n == name) {
+            return Err(StructError::RecursiveType(name.to_string()));
+        }
+        visiting.push(name.to_string());
+        let mut end = 0;
+        for field in def.fields.iter() {
+            let size = self.size_of_in(&field.ty, visiting)?;
+            end = end.max(field.offset.checked_add(size).ok_or(StructError::SizeOverflow(format!("{name}.{}", field.name)))?);
+        }
+        visiting.pop();
+        Ok(end)
+    }
+
+    fn size_of_in(&self, ty: &FieldType, visiting: &mut Vec<String>) -> Result<usize, StructError> {
+        Ok(match ty {
+            FieldType::Primitive(p) => p.size(),
+            FieldType::Array(elem, count) => match self.size_of_in(elem, visiting)? {
+                // 大小为 0 的元素无法按下标定位，元素数也不受大小限制
+                0 => return Err(StructError::TypeMismatch(format!("{ty:?}: zero-sized element"))),
+                size => size.checked_mul(*count).ok_or(StructError::SizeOverflow(format!("{ty:?}")))?,
+            },
+            FieldType::Struct(name) => self.struct_size_in(name, visiting)?,
+            FieldType::Pointer(_) => POINTER_SIZE,
+        })
+    }
+
+    /// 从 address 读取结构体 name，指针最多跟随 depth 层
+    pub fn decode<R: MemoryReader>(&self, reader: &R, name: &str, address: usize, depth: usize) -> Result<Value, StructError> {
+        self.decode_type(reader, &FieldType::Struct(name.to_string()), address, depth)
+    }
+
+    /// 从 address 读取任意类型
+    pub fn decode_type<R: MemoryReader>(&self, reader: &R, ty: &FieldType, address: usize, depth: usize) -> Result<Value, StructError> {
+        let size = self.size_of(ty)?;
+        if size > self.decode_limit {
+            return Err(StructError::SizeOverflow(format!("{ty:?}: {size:#x} bytes, limit {:#x}", self.decode_limit)));
+        }
+        let mut buf = vec![0u8; size];
+        reader.read_exact(address, &mut buf).map_err(|e| StructError::ReadError(e.to_string()))?;
+        self.decode_buf(reader, ty, address, &buf, depth)
+    }
+
+    fn decode_buf<R: MemoryReader>(&self, reader: &R, ty: &FieldType, address: usize, buf: &[u8], depth: usize) -> Result<Value, StructError> {
+        Ok(match ty {
+            FieldType::Primitive(p) => p.decode(buf),
+            FieldType::Array(elem, count) => {
+                let elem_size = self.size_of(elem)?;
+                let mut items = Vec::with_capacity(*count);
+                for i in 0..*count {
+                    let off = i * elem_size;
+                    items.push(self.decode_buf(reader, elem, address.wrapping_add(off), &buf[off..off + elem_size], depth)?);
+                }
+                Value::Array(items)
+            },
+            FieldType::Struct(name) => {
+                let def = self.def(name)?;
+                let mut fields = Vec::with_capacity(def.fields.len());
+                for field in def.fields.iter() {
+                    let size = self.size_of(&field.ty)?;
+                    let bytes = buf.get(field.offset..field.offset + size)
+                        .ok_or(StructError::TypeMismatch(format!("{}.{} outside struct size", name, field.name)))?;
+                    fields.push((field.name.clone(), self.decode_buf(reader, &field.ty, address.wrapping_add(field.offset), bytes, depth)?));
+                }
+                Value::Struct { name: name.clone(), address, fields }
+            },
+            FieldType::Pointer(target_ty) => {
+                let target_addr = from_bytes::<usize>(buf).unwrap();
+                let target = match target_ty {
+                    Some(t) if target_addr != 0 && depth > 0 => self.decode_type(reader, t, target_addr, depth - 1).ok().map(Box::new),
+                    _ => None,
+                };
+                Value::Pointer { address: target_addr, target }
+            },
+        })
+    }
+
+    /// 结构体中字段路径的偏移和类型，如 `pos.x`、`items[2]`
+    pub fn field_offset(&self, name: &str, path: &str) -> Result<(usize, FieldType), StructError> {
+        let mut ty = FieldType::Struct(name.to_string());
+        let mut offset = 0;
+        for seg in parse_path(path)? {
+            ty = match (&ty, seg) {
+                (FieldType::Struct(s), PathSeg::Field(f)) => {
+                    let field = self.def(s)?.fields.iter().find(|x| x.name == f)
+                        .ok_or(StructError::UnknownField(format!("{s}.{f}")))?;
+                    offset += field.offset;
+                    field.ty.clone()
+                },
+                (FieldType::Array(elem, count), PathSeg::Index(i)) => {
+                    if i >= *count {
+                        return Err(StructError::UnknownField(format!("{path}: index {i} out of {count}")));
+                    }
+                    offset += i * self.size_of(elem)?;
+                    elem.as_ref().clone()
+                },
+                _ => return Err(StructError::UnknownField(path.to_string())),
+            };
+        }
+        Ok((offset, ty))
+    }
+
+    /// 写入结构体中的一个基本类型字段
+    pub fn write_field<W: MemoryWriter>(&self, writer: &W, name: &str, address: usize, path: &str, value: &Value) -> Result<(), StructError> {
+        let (offset, ty) = self.field_offset(name, path)?;
+        let bytes = match ty {
+            FieldType::Primitive(p) => value.encode(p)?,
+            FieldType::Pointer(_) => match value {
+                Value::Pointer { address, .. } => address.to_ne_bytes().to_vec(),
+                _ => return Err(StructError::TypeMismatch(format!("{value:?} as pointer"))),
+            },
+            _ => return Err(StructError::TypeMismatch(format!("{path} is not a primitive field"))),
+        };
+        let len = writer.writebuf(address + offset, &bytes).map_err(|e| StructError::WriteError(e.to_string()))?;
+        if len != bytes.len() {
+            return Err(StructError::WriteError(format!("Short write, result: {len}")));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,376 @@
+use std::fs::File;
+use std::os::unix::fs::MetadataExt;
+use crate::elf::{self, ElfHeader, ProgramHeader, SectionHeader, Sym};
+use crate::memory::MemoryReader;
+use crate::process::{Module, Process};
+
+#[derive(Debug)]
+pub enum SymbolError {
+    IoError(String),
+    ElfError(String),
+    ReadError(String),
+    NoSymbols(String),
+}
+
+impl std::fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Func,
+    Object,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// 运行时地址
+    pub address: usize,
+    pub size: usize,
+    pub kind: SymbolKind,
+}
+
+/// 一个模块的符号表，地址已经加上加载偏移
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    pub module: String,
+    pub base: usize,
+    pub end: usize,
+    /// 按地址排序
+    symbols: Vec<Symbol>,
+    /// symbols[..=i] 中最大的结束地址，向前查找覆盖地址的符号时用来提前结束
+    max_end: Vec<usize>,
+}
+
+fn elf_err(e: elf::ElfError) -> SymbolError {
+    SymbolError::ElfError(e.to_string())
+}
+
+fn symbol_kind(sym: &Sym) -> Option<SymbolKind> {
+    match sym.st_type() {
+        elf::STT_FUNC | elf::STT_GNU_IFUNC => Some(SymbolKind::Func),
+        elf::STT_OBJECT => Some(SymbolKind::Object),
+        _ => None,
+    }
+}
+
+/// 解析 symtab，strtab 为对应的字符串表
+fn parse_symbols(symtab: &[u8], strtab: &[u8], bias: usize, out: &mut Vec<Symbol>) -> Result<(), SymbolError> {
+    for raw in symtab.chunks_exact(elf::SYM_SIZE) {
+        let sym = Sym::parse(raw).map_err(elf_err)?;
+        let Some(kind) = symbol_kind(&sym) else {
+            continue;
+        };
+        if sym.st_shndx == elf::SHN_UNDEF || sym.st_value == 0 {
+            continue;
+        }
+        let Some(name) = elf::str_at(strtab, sym.st_name as usize).filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        out.push(Symbol {
+            name: name.to_string(),
+            address: bias.wrapping_add(sym.st_value as usize),
+            size: sym.st_size as usize,
+            kind,
+        });
+    }
+    Ok(())
+}
+
+/// 最低的 PT_LOAD 虚拟地址（页对齐），加载偏移 = base - 它
+fn min_load_vaddr(phdrs: &[ProgramHeader]) -> usize {
+    phdrs.iter()
+        .filter(|p| p.p_type == elf::PT_LOAD)
+        .map(|p| p.p_vaddr as usize & !0xfff)
+        .min()
+        .unwrap_or(0)
+}
+
+fn read_exact<R: MemoryReader>(reader: &R, address: usize, buf: &mut [u8]) -> Result<(), SymbolError> {
+    let mut done = 0;
+    while done < buf.len() {
+        let len = reader.readbuf(address + done, &mut buf[done..])
+            .map_err(|e| SymbolError::ReadError(e.to_string()))?;
+        if len == 0 {
+            return Err(SymbolError::ReadError(format!("Short read at {:#x}", address + done)));
+        }
+        done += len;
+    }
+    Ok(())
+}
+
+fn pread_exact(file: &File, buf: &mut [u8], offset: u64) -> Result<(), SymbolError> {
+    let mut done = 0;
+    while done < buf.len() {
+        let len = nix::sys::uio::pread(file, &mut buf[done..], (offset + done as u64) as i64)
+            .map_err(|e| SymbolError::IoError(e.to_string()))?;
+        if len == 0 {
+            return Err(SymbolError::ElfError(format!("Unexpected EOF at {:#x}", offset + done as u64)));
+        }
+        done += len;
+    }
+    Ok(())
+}
+
+/// 读取文件中 offset 开始的 size 字节，超出文件长度时不分配
+fn pread_range(file: &File, offset: u64, size: u64, file_len: u64) -> Result<Vec<u8>, SymbolError> {
+    match offset.checked_add(size) {
+        Some(end) if end <= file_len => {
+            let mut buf = vec![0u8; size as usize];
+            pread_exact(file, &mut buf, offset)?;
+            Ok(buf)
+        },
+        _ => Err(SymbolError::ElfError(format!("Range {offset:#x}+{size:#x} beyond file length {file_len:#x}"))),
+    }
+}
+
+/// 从目标读到的大小不可信，分配前检查不超过模块长度
+fn bounded(size: Option<usize>, module: &Module) -> Result<usize, SymbolError> {
+    size.filter(|s| *s <= module.size)
+        .ok_or(SymbolError::ElfError(format!("{}: size beyond module length {:#x}", module.path, module.size)))
+}
+
+impl SymbolTable {
+    /// 用已有的符号建表，符号地址为运行时地址
+    pub fn new(module: &Module, mut symbols: Vec<Symbol>) -> Result<Self, SymbolError> {
+        if symbols.is_empty() {
+            return Err(SymbolError::NoSymbols(module.path.clone()));
+        }
+        symbols.sort_by_key(|s| s.address);
+        symbols.dedup_by(|a, b| a.address == b.address && a.name == b.name);
+        let max_end = symbols.iter()
+            .scan(0, |end, s| {
+                *end = (*end).max(s.address.saturating_add(s.size));
+                Some(*end)
+            })
+            .collect();
+        Ok(SymbolTable {
+            module: module.name.clone(),
+            base: module.base,
+            end: module.end(),
+            symbols,
+            max_end,
+        })
+    }
+
+    /// 优先解析磁盘上的文件，文件已删除或被替换时从目标内存读取
+    pub fn load<R: MemoryReader>(module: &Module, reader: &R) -> Result<Self, SymbolError> {
+        let first = &module.segments[0];
+        let on_disk = !first.deleted && std::fs::metadata(&module.path)
+            .is_ok_and(|m| first.inode == 0 || m.ino() == first.inode);
+        if on_disk && let Ok(table) = SymbolTable::from_file(module) {
+            return Ok(table);
+        }
+        SymbolTable::from_memory(module, reader)
+    }
+
+    /// 从磁盘上的 ELF 读取 .symtab 和 .dynsym，只读取 ELF 头、程序头、节头和符号表所在的范围
+    pub fn from_file(module: &Module) -> Result<Self, SymbolError> {
+        let file = File::open(&module.path).map_err(|e| SymbolError::IoError(e.to_string()))?;
+        let file_len = file.metadata().map_err(|e| SymbolError::IoError(e.to_string()))?.len();
+        let mut ehdr_raw = [0u8; elf::EHDR_SIZE];
+        pread_exact(&file, &mut ehdr_raw, 0)?;
+        let ehdr = ElfHeader::parse(&ehdr_raw).map_err(elf_err)?;
+        if (ehdr.e_phentsize as usize) < elf::PHDR_SIZE || (ehdr.e_shnum != 0 && (ehdr.e_shentsize as usize) < elf::SHDR_SIZE) {
+            return Err(SymbolError::ElfError(format!("Bad entry size: {} {}", ehdr.e_phentsize, ehdr.e_shentsize)));
+        }
+
+        let phdrs_raw = pread_range(&file, ehdr.e_phoff, ehdr.e_phnum as u64 * ehdr.e_phentsize as u64, file_len)?;
+        let phdrs = phdrs_raw.chunks_exact(ehdr.e_phentsize as usize)
+            .map(ProgramHeader::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(elf_err)?;
+        let bias = module.base.wrapping_sub(min_load_vaddr(&phdrs));
+
+        let shdrs_raw = pread_range(&file, ehdr.e_shoff, ehdr.e_shnum as u64 * ehdr.e_shentsize as u64, file_len)?;
+        let shdrs = shdrs_raw.chunks_exact((ehdr.e_shentsize as usize).max(elf::SHDR_SIZE))
+            .map(SectionHeader::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(elf_err)?;
+
+        let mut symbols = Vec::new();
+        for sh in shdrs.iter().filter(|s| s.sh_type == elf::SHT_SYMTAB || s.sh_type == elf::SHT_DYNSYM) {
+            let strtab = shdrs.get(sh.sh_link as usize)
+                .ok_or(SymbolError::ElfError(format!("Bad sh_link {}", sh.sh_link)))?;
+            let symtab_raw = pread_range(&file, sh.sh_offset, sh.sh_size, file_len)?;
+            let strtab_raw = pread_range(&file, strtab.sh_offset, strtab.sh_size, file_len)?;
+            parse_symbols(&symtab_raw, &strtab_raw, bias, &mut symbols)?;
+        }
+
+        SymbolTable::new(module, symbols)
+    }
+
+    /// 从目标内存中的 ELF 头和 PT_DYNAMIC 读取 .dynsym
+    pub fn from_memory<R: MemoryReader>(module: &Module, reader: &R) -> Result<Self, SymbolError> {
+        let mut ehdr_raw = [0u8; elf::EHDR_SIZE];
+        read_exact(reader, module.base, &mut ehdr_raw)?;
+        let ehdr = ElfHeader::parse(&ehdr_raw).map_err(elf_err)?;
+
+        if (ehdr.e_phentsize as usize) < elf::PHDR_SIZE {
+            return Err(SymbolError::ElfError(format!("Bad e_phentsize: {}", ehdr.e_phentsize)));
+        }
+
+        let mut phdrs_raw = vec![0u8; bounded(Some(ehdr.e_phnum as usize * ehdr.e_phentsize as usize), module)?];
+        read_exact(reader, module.base.wrapping_add(ehdr.e_phoff as usize), &mut phdrs_raw)?;
+        let phdrs = phdrs_raw.chunks_exact(ehdr.e_phentsize as usize)
+            .map(ProgramHeader::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(elf_err)?;
+        let bias = module.base.wrapping_sub(min_load_vaddr(&phdrs));
+
+        let dynamic = phdrs.iter().find(|p| p.p_type == elf::PT_DYNAMIC)
+            .ok_or(SymbolError::NoSymbols(format!("{}: no PT_DYNAMIC", module.path)))?;
+        let mut dyn_raw = vec![0u8; bounded(usize::try_from(dynamic.p_memsz).ok(), module)?];
+        read_exact(reader, bias.wrapping_add(dynamic.p_vaddr as usize), &mut dyn_raw)?;
+
+        let (mut symtab, mut strtab, mut strsz, mut hash, mut gnu_hash) = (0, 0, 0, 0, 0);
+        for entry in dyn_raw.chunks_exact(elf::DYN_SIZE) {
+            let tag = elf::u64_at(entry, 0).map_err(elf_err)?;
+            let val = elf::u64_at(entry, 8).map_err(elf_err)? as usize;
+            match tag {
+                elf::DT_NULL => break,
+                elf::DT_SYMTAB => symtab = val,
+                elf::DT_STRTAB => strtab = val,
+                elf::DT_STRSZ => strsz = val,
+                elf::DT_HASH => hash = val,
+                elf::DT_GNU_HASH => gnu_hash = val,
+                _ => {}
+            }
+        }
+        if symtab == 0 || strtab == 0 {
+            return Err(SymbolError::NoSymbols(format!("{}: no DT_SYMTAB", module.path)));
+        }
+        // glibc 会把 .dynamic 中的地址重定位，Android 等不会
+        let fix = |v: usize| if v < module.base { bias.wrapping_add(v) } else { v };
+        let (symtab, strtab) = (fix(symtab), fix(strtab));
+
+        let count = if hash != 0 {
+            // DT_HASH: nbucket, nchain，nchain 即符号数
+            let mut raw = [0u8; 8];
+            read_exact(reader, fix(hash), &mut raw)?;
+            elf::u32_at(&raw, 4).map_err(elf_err)? as usize
+        } else if gnu_hash != 0 {
+            gnu_hash_count(reader, fix(gnu_hash), module)?
+        } else if strtab > symtab {
+            (strtab - symtab) / elf::SYM_SIZE
+        } else {
+            return Err(SymbolError::NoSymbols(format!("{}: unknown symbol count", module.path)));
+        };
+
+        let mut symtab_raw = vec![0u8; bounded(count.checked_mul(elf::SYM_SIZE), module)?];
+        read_exact(reader, symtab, &mut symtab_raw)?;
+        let mut strtab_raw = vec![0u8; bounded(Some(strsz), module)?];
+        read_exact(reader, strtab, &mut strtab_raw)?;
+
+        let mut symbols = Vec::new();
+        parse_symbols(&symtab_raw, &strtab_raw, bias, &mut symbols)?;
+        SymbolTable::new(module, symbols)
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// 符号名对应的运行时地址
+    pub fn resolve(&self, name: &str) -> Option<usize> {
+        self.symbols.iter().find(|s| s.name == name).map(|s| s.address)
+    }
+
+    /// 覆盖地址的最近的符号及偏移，例如函数内部大小为 0 的局部标号会跳过，取外层的函数；
+    /// 没有符号覆盖时返回 None
+    pub fn symbolize(&self, address: usize) -> Option<(&Symbol, usize)> {
+        let idx = self.symbols.partition_point(|s| s.address <= address);
+        (0..idx).rev()
+            .take_while(|&i| self.max_end[i] > address)
+            .map(|i| &self.symbols[i])
+            .find(|s| address - s.address < s.size)
+            .map(|s| (s, address - s.address))
+    }
+}
+
+/// DT_GNU_HASH 不记录符号数，取所有链中最大的下标
+fn gnu_hash_count<R: MemoryReader>(reader: &R, address: usize, module: &Module) -> Result<usize, SymbolError> {
+    let mut header = [0u8; 16];
+    read_exact(reader, address, &mut header)?;
+    let nbuckets = elf::u32_at(&header, 0).map_err(elf_err)? as usize;
+    let symoffset = elf::u32_at(&header, 4).map_err(elf_err)? as usize;
+    let bloom_size = elf::u32_at(&header, 8).map_err(elf_err)? as usize;
+
+    let bloom_len = bounded(bloom_size.checked_mul(8), module)?;
+    let buckets_len = bounded(nbuckets.checked_mul(4), module)?;
+    let buckets_addr = address.wrapping_add(16 + bloom_len);
+    let mut buckets = vec![0u8; buckets_len];
+    read_exact(reader, buckets_addr, &mut buckets)?;
+    let max_bucket = buckets.chunks_exact(4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+        .max()
+        .unwrap_or(0);
+    if max_bucket < symoffset {
+        return Ok(symoffset);
+    }
+
+    // 链的最后一项最低位为 1，链不会比模块还长
+    let chains_addr = buckets_addr.wrapping_add(buckets_len);
+    for idx in max_bucket..max_bucket + module.size / 4 {
+        let mut raw = [0u8; 4];
+        read_exact(reader, chains_addr.wrapping_add((idx - symoffset) * 4), &mut raw)?;
+        if u32::from_le_bytes(raw) & 1 != 0 {
+            return Ok(idx + 1);
+        }
+    }
+    Err(SymbolError::ElfError(format!("{}: unterminated GNU hash chain", module.path)))
+}
+
+/// 进程中所有模块的符号，地址显示为 `libc.so.6!malloc+0x12`
+#[derive(Debug, Clone, Default)]
+pub struct Symbolizer {
+    tables: Vec<SymbolTable>,
+    /// 没有符号的模块 (name, base, end)
+    modules: Vec<(String, usize, usize)>,
+}
+
+impl Symbolizer {
+    /// 加载进程中所有模块的符号，需要先调用 Process::maps()，无法解析的模块只按模块偏移显示
+    pub fn new<R: MemoryReader>(process: &Process, reader: &R) -> Self {
+        let mut res = Symbolizer::default();
+        for module in process.modules() {
+            match SymbolTable::load(&module, reader) {
+                Ok(table) => res.tables.push(table),
+                Err(_) => res.modules.push((module.name.clone(), module.base, module.end())),
+            }
+        }
+        res
+    }
+
+    pub fn tables(&self) -> &[SymbolTable] {
+        &self.tables
+    }
+
+    /// 解析 `module!symbol` 或只有 `symbol`（在所有模块中查找第一个）
+    pub fn resolve(&self, name: &str) -> Option<usize> {
+        match name.split_once('!') {
+            Some((module, symbol)) => self.tables.iter()
+                .filter(|t| t.module == module || t.module.starts_with(module))
+                .find_map(|t| t.resolve(symbol)),
+            None => self.tables.iter().find_map(|t| t.resolve(name)),
+        }
+    }
+
+    /// `libc.so.6!malloc+0x12`，没有符号时为 `libc.so.6+0x1234`，不在模块中时为 `0x...`
+    pub fn symbolize(&self, address: usize) -> String {
+        if let Some(table) = self.tables.iter().find(|t| t.base <= address && address < t.end) {
+            return match table.symbolize(address) {
+                Some((sym, 0)) => format!("{}!{}", table.module, sym.name),
+                Some((sym, offset)) => format!("{}!{}+{:#x}", table.module, sym.name, offset),
+                None => format!("{}+{:#x}", table.module, address - table.base),
+            };
+        }
+        if let Some((name, base, _)) = self.modules.iter().find(|(_, b, e)| *b <= address && address < *e) {
+            return format!("{}+{:#x}", name, address - base);
+        }
+        format!("{:#x}", address)
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use crate::memory::{MemoryReader, MemoryWriter, Pod};
+use crate::memory::ptrace_memory::{PtraceMemory, TraceState};
+use crate::process::Process;
+use crate::process::thread::ThreadState;
+
+/// 等待 SIGSTOP 生效的时间
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum TransactionError {
+    PauseError(String),
+    /// 保存原始值失败，什么都没有写
+    SaveError(String),
+    /// 写入失败，已回滚
+    WriteError(String),
+    /// 读回的值和写入的不同，已回滚
+    VerifyError { address: usize, expected: Vec<u8>, found: Vec<u8> },
+    /// 回滚也失败了，failed 中的地址没能恢复
+    RollbackError { cause: String, failed: Vec<usize> },
+}
+
+impl std::fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// 事务期间暂停目标的方式
+#[derive(Clone, Copy)]
+pub enum Pause<'a> {
+    None,
+    /// 发送 SIGSTOP 并等所有线程停下，结束后发送 SIGCONT；原本就停着的不会被恢复
+    Signal(u32),
+    /// 用 stop_world 停住所有线程，结束后只恢复这次停住的；调用者已经 stop_world 时保持停止
+    Ptrace(&'a PtraceMemory),
+}
+
+/// 一组要么全部成功、要么全部回滚的写入
+///
+/// 提交时先保存所有原始值，依次写入后读回校验，任何一步失败都写回原始值。
+/// 重叠的写入以后面的为准。
+pub struct Transaction<'a, M: MemoryReader + MemoryWriter> {
+    mem: &'a M,
+    pause: Pause<'a>,
+    writes: Vec<(usize, Vec<u8>)>,
+}
+
+impl<'a, M: MemoryReader + MemoryWriter> Transaction<'a, M> {
+    pub fn new(mem: &'a M) -> Self {
+        Transaction { mem, pause: Pause::None, writes: Vec::new() }
+    }
+
+    pub fn pause(mut self, pause: Pause<'a>) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    pub fn write<T: Pod>(&mut self, address: usize, value: &T) -> &mut Self {
+        self.writebuf(address, bytemuck::bytes_of(value))
+    }
+
+    pub fn writebuf(&mut self, address: usize, buf: &[u8]) -> &mut Self {
+        self.writes.push((address, buf.to_vec()));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    pub fn commit(&self) -> Result<(), TransactionError> {
+        let stopped = self.pause_target()?;
+        let res = self.apply();
+        let resumed = self.resume_target(&stopped);
+        res.and(resumed)
+    }
+
+    /// 返回这次停住、结束时需要恢复的进程或线程
+    fn pause_target(&self) -> Result<Vec<u32>, TransactionError> {
+        match self.pause {
+            Pause::None => Ok(Vec::new()),
+            Pause::Signal(pid) => {
+                let process = Process::new(pid);
+                let stopped = || -> Result<bool, TransactionError> {
+                    let threads = process.threads().map_err(|e| TransactionError::PauseError(format!("{:?}", e)))?;
+                    Ok(threads.iter().all(|t| matches!(t.state, ThreadState::Stopped | ThreadState::TracingStop)))
+                };
+                if stopped()? {
+                    return Ok(Vec::new());
+                }
+                kill(Pid::from_raw(pid as i32), Signal::SIGSTOP).map_err(|e| TransactionError::PauseError(e.to_string()))?;
+                let deadline = Instant::now() + STOP_TIMEOUT;
+                while !stopped()? {
+                    if Instant::now() > deadline {
+                        let _ = kill(Pid::from_raw(pid as i32), Signal::SIGCONT);
+                        return Err(TransactionError::PauseError(format!("Process {pid} did not stop")));
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Ok(vec![pid])
+            },
+            Pause::Ptrace(ptrace) => {
+                let was_stopped = ptrace.thread_states().into_iter()
+                    .filter(|(_, s)| *s != TraceState::Running)
+                    .map(|(tid, _)| tid)
+                    .collect::<Vec<_>>();
+                ptrace.stop_world().map_err(|e| TransactionError::PauseError(e.to_string()))?;
+                Ok(ptrace.attached_threads().into_iter().filter(|tid| !was_stopped.contains(tid)).collect())
+            },
+        }
+    }
+
+    fn resume_target(&self, stopped: &[u32]) -> Result<(), TransactionError> {
+        if stopped.is_empty() {
+            return Ok(());
+        }
+        match self.pause {
+            Pause::None => Ok(()),
+            Pause::Signal(pid) => kill(Pid::from_raw(pid as i32), Signal::SIGCONT)
+                .map_err(|e| TransactionError::PauseError(e.to_string())),
+            Pause::Ptrace(ptrace) => stopped.iter()
+                .try_for_each(|tid| ptrace.cont(*tid))
+                .map_err(|e| TransactionError::PauseError(e.to_string())),
+        }
+    }
+
+    fn apply(&self) -> Result<(), TransactionError> {
+        let mut originals = Vec::with_capacity(self.writes.len());
+        for (address, data) in self.writes.iter() {
+            let mut buf = vec![0; data.len()];
+            self.mem.read_exact(*address, &mut buf)
+                .map_err(|e| TransactionError::SaveError(format!("{address:#x}: {e}")))?;
+            originals.push(buf);
+        }
+
+        for (i, (address, data)) in self.writes.iter().enumerate() {
+            if let Err(e) = self.mem.write_exact(*address, data) {
+                // 失败的这一次可能写入了一部分，尽量恢复
+                let _ = self.mem.write_exact(*address, &originals[i]);
+                return Err(self.rollback(&originals[..i], TransactionError::WriteError(format!("{address:#x}: {e}"))));
+            }
+        }
+
+        for (i, (address, data)) in self.writes.iter().enumerate() {
+            let mut found = vec![0; data.len()];
+            if let Err(e) = self.mem.read_exact(*address, &mut found) {
+                return Err(self.rollback(&originals, TransactionError::WriteError(format!("Verify {address:#x}: {e}"))));
+            }
+            // 被后面的写入覆盖的字节不检查
+            let later = &self.writes[i + 1..];
+            let mismatch = (0..data.len()).any(|j| {
+                let a = address + j;
+                found[j] != data[j] && !later.iter().any(|(b, d)| *b <= a && a < b + d.len())
+            });
+            if mismatch {
+                let cause = TransactionError::VerifyError { address: *address, expected: data.clone(), found };
+                return Err(self.rollback(&originals, cause));
+            }
+        }
+        Ok(())
+    }
+
+    /// 倒序写回原始值，全部成功时返回 cause
+    fn rollback(&self, originals: &[Vec<u8>], cause: TransactionError) -> TransactionError {
+        let mut failed = Vec::new();
+        for ((address, _), original) in self.writes.iter().zip(originals).rev() {
+            if self.mem.write_exact(*address, original).is_err() {
+                failed.push(*address);
+            }
+        }
+        if failed.is_empty() {
+            cause
+        } else {
+            TransactionError::RollbackError { cause: cause.to_string(), failed }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::{Duration, Instant};
+use crate::memory::MemoryError;
+use crate::memory::ptrace_memory::{PtraceMemory, Trap, TRAP_HWBKPT};
+use crate::registers::{GeneralRegisters, Regs};
+
+/// x86_64 的 DR0 - DR3 共 4 个
+pub const SLOTS: usize = 4;
+
+const DR_CONTROL: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// 执行到地址时触发，长度必须为 1
+    Execute,
+    Write,
+    /// x86 没有单独的读断点，读和写都会触发
+    ReadWrite,
+}
+
+impl WatchKind {
+    /// DR7 中的 R/W 位
+    fn rw_bits(self) -> u64 {
+        match self {
+            WatchKind::Execute => 0b00,
+            WatchKind::Write => 0b01,
+            WatchKind::ReadWrite => 0b11,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub address: usize,
+    /// 1、2、4 或 8，地址必须按长度对齐
+    pub len: usize,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    /// DR7 中的 LEN 位
+    fn len_bits(&self) -> u64 {
+        match self.len {
+            1 => 0b00,
+            2 => 0b01,
+            8 => 0b10,
+            _ => 0b11,
+        }
+    }
+}
+
+/// 一次命中
+#[derive(Debug, Clone)]
+pub struct WatchHit {
+    pub tid: u32,
+    pub slot: usize,
+    pub watchpoint: Watchpoint,
+    /// 停下时的指令地址，即恢复后从这里继续执行
+    pub pc: usize,
+    /// 触发断点的指令地址。执行断点为 pc；读写断点在指令执行之后才触发，
+    /// pc 指向下一条指令，x86 的变长指令无法从 pc 可靠地倒推，此时为 None
+    pub fault_pc: Option<usize>,
+    pub regs: Regs,
+}
+
+/// 基于调试寄存器的硬件断点，设置到所有已附加的线程
+///
+/// 之后创建的线程在 wait 时附加并设置。drop 时清除所有断点。
+pub struct Watchpoints<'a> {
+    mem: &'a PtraceMemory,
+    slots: [Option<Watchpoint>; SLOTS],
+}
+
+impl<'a> Watchpoints<'a> {
+    pub fn new(mem: &'a PtraceMemory) -> Self {
+        Watchpoints { mem, slots: [None; SLOTS] }
+    }
+
+    pub fn slots(&self) -> &[Option<Watchpoint>; SLOTS] {
+        &self.slots
+    }
+
+    /// 设置断点，返回使用的槽位
+    pub fn add(&mut self, address: usize, len: usize, kind: WatchKind) -> Result<usize, MemoryError> {
+        if ![1, 2, 4, 8].contains(&len) || !address.is_multiple_of(len) {
+            return Err(MemoryError::WatchpointError(format!("Bad watchpoint {address:#x} len {len}")));
+        }
+        if kind == WatchKind::Execute && len != 1 {
+            return Err(MemoryError::WatchpointError("Execute watchpoint must have len 1".to_string()));
+        }
+        let slot = self.slots.iter().position(|s| s.is_none())
+            .ok_or(MemoryError::WatchpointError("No free debug register".to_string()))?;
+
+        self.slots[slot] = Some(Watchpoint { address, len, kind });
+        if let Err(e) = self.apply() {
+            self.slots[slot] = None;
+            let _ = self.apply();
+            return Err(e);
+        }
+        Ok(slot)
+    }
+
+    pub fn remove(&mut self, slot: usize) -> Result<(), MemoryError> {
+        if let Some(s) = self.slots.get_mut(slot) {
+            *s = None;
+        }
+        self.apply()
+    }
+
+    pub fn clear(&mut self) -> Result<(), MemoryError> {
+        self.slots = [None; SLOTS];
+        self.apply()
+    }
+
+    /// DR7 的值
+    fn control(&self) -> u64 {
+        let mut dr7 = 0;
+        for (i, w) in self.slots.iter().enumerate() {
+            let Some(w) = w else {
+                continue;
+            };
+            // 局部启用位和 R/W、LEN
+            dr7 |= 1 << (2 * i);
+            dr7 |= (w.kind.rw_bits() | w.len_bits() << 2) << (16 + 4 * i);
+        }
+        dr7
+    }
+
+    /// 把槽位写到所有线程，新出现的线程也会附加
+    pub fn apply(&self) -> Result<(), MemoryError> {
+        self.mem.attach_threads()?;
+        self.apply_to(&self.mem.attached_threads())
+    }
+
+    fn apply_to(&self, tids: &[u32]) -> Result<(), MemoryError> {
+        let dr7 = self.control();
+        for &tid in tids {
+            self.mem.with_thread_stopped(tid, || {
+                // 先关掉再改地址，内核在写 DR7 时检查地址
+                self.mem.set_debug_reg(tid, DR_CONTROL, 0)?;
+                for (i, w) in self.slots.iter().enumerate() {
+                    if let Some(w) = w {
+                        self.mem.set_debug_reg(tid, i, w.address as u64)?;
+                    }
+                }
+                self.mem.set_debug_reg(tid, DR_CONTROL, dr7)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 线程的 DR7
+    pub fn debug_control(&self, tid: u32) -> Result<u64, MemoryError> {
+        self.mem.debug_reg(tid, DR_CONTROL)
+    }
+
+    /// 等待命中直到超时或收集到至少 max 个，每次命中后恢复目标运行
+    pub fn wait(&self, timeout: Duration, max: usize) -> Result<Vec<WatchHit>, MemoryError> {
+        let deadline = Instant::now() + timeout;
+        let mut hits = Vec::new();
+        while hits.len() < max && Instant::now() < deadline {
+            // 调试寄存器不会被 clone 继承，新线程要补上
+            let new = self.mem.attach_threads()?;
+            if !new.is_empty() && self.slots.iter().any(|s| s.is_some()) {
+                self.apply_to(&new)?;
+            }
+            let stops = self.mem.poll_stops()?;
+            // 其它操作停住线程时也可能截获命中，一起取走
+            let traps = self.mem.take_traps(|t| t.code == TRAP_HWBKPT);
+            if stops.is_empty() && traps.is_empty() {
+                std::thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            hits.extend(traps.iter().filter_map(|t| self.hit(t)));
+            for tid in stops.iter().map(|s| s.0).chain(traps.iter().map(|t| t.tid)) {
+                self.mem.cont(tid)?;
+            }
+        }
+        Ok(hits)
+    }
+
+    /// 根据 DR6 判断是哪个槽位
+    fn hit(&self, trap: &Trap) -> Option<WatchHit> {
+        let slot = (0..SLOTS).find(|i| trap.debug_status & (1 << i) != 0)?;
+        let watchpoint = self.slots[slot]?;
+        let pc = trap.regs.pc() as usize;
+        let fault_pc = (watchpoint.kind == WatchKind::Execute).then_some(pc);
+        Some(WatchHit { tid: trap.tid, slot, watchpoint, pc, fault_pc, regs: trap.regs })
+    }
+}
+
+impl Drop for Watchpoints<'_> {
+    fn drop(&mut self) {
+        if self.slots.iter().any(|s| s.is_some()) {
+            let _ = self.clear();
+        }
+    }
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{Known, Target, COUNTER, MAGIC, SPEED};
+use mempoll::memory::{process_vm_memory::ProcessVmMemory, proc_memory::ProcMemory, ptrace_memory::PtraceMemory, MemoryReader, MemoryWriter};
+use mempoll::process::MapRange;
+use mempoll::searcher::{MemorySearcher, SearchType};
+
+fn check_reads<M: MemoryReader>(mem: &M, target: &Target) {
+    assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+    let known = mem.read::<Known>(target.known).unwrap();
+    assert_eq!(known.counter, COUNTER);
+    assert_eq!(known.speed, SPEED);
+    assert_eq!(&known.bytes, b"mempoll-harness\0");
+
+    // 跨页、非对齐
+    let mut buf = [0u8; 37];
+    let start = target.pages + 4096 - 13;
+    assert_eq!(mem.readbuf(start, &mut buf).unwrap(), buf.len());
+    for (i, b) in buf.iter().enumerate() {
+        assert_eq!(*b, (4096 - 13 + i) as u8);
+    }
+}
+
+fn check_writes<M: MemoryReader + MemoryWriter>(mem: &M, target: &Target) {
+    mem.write(target.known + 8, &0xAABBCCDDu32).unwrap();
+    assert_eq!(mem.read::<u32>(target.known + 8).unwrap(), 0xAABBCCDD);
+    assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+
+    let data = [0x55u8; 11];
+    let start = target.pages + 4096 - 5;
+    assert_eq!(mem.writebuf(start, &data).unwrap(), data.len());
+    let mut buf = [0u8; 13];
+    mem.readbuf(start - 1, &mut buf).unwrap();
+    assert_eq!(buf[0], (4096 - 6) as u8);
+    assert_eq!(&buf[1..12], &data);
+    assert_eq!(buf[12], (4096 + 6) as u8);
+}
+
+fn contains(addr: usize) -> impl Fn(&MapRange) -> bool {
+    move |m: &MapRange| m.address.0 <= addr && addr < m.address.1
+}
+
+#[test]
+fn proc_memory() {
+    let target = Target::spawn();
+    let mut mem = ProcMemory::new(target.pid);
+    mem.open().unwrap();
+    check_reads(&mem, &target);
+    check_writes(&mem, &target);
+
+    mem.process.maps().unwrap();
+    let res = mem.search::<SearchType<u64>, 4096>(SearchType::Eq(MAGIC), Some(contains(target.known))).unwrap();
+    assert!(res.contains(&target.known));
+}
+
+#[test]
+fn process_vm_memory() {
+    let target = Target::spawn();
+    let mut mem = ProcessVmMemory::new(target.pid);
+    check_reads(&mem, &target);
+    check_writes(&mem, &target);
+
+    mem.process.maps().unwrap();
+    let res = mem.search::<SearchType<u32>, 4096>(SearchType::Eq(0xAABBCCDD), Some(contains(target.known))).unwrap();
+    assert!(res.contains(&(target.known + 8)));
+}
+
+#[test]
+fn ptrace_memory() {
+    let target = Target::spawn();
+    let mut mem = PtraceMemory::new(target.pid);
+    mem.attach().unwrap();
+    check_reads(&mem, &target);
+    check_writes(&mem, &target);
+
+    mem.process.maps().unwrap();
+    let res = mem.search::<SearchType<u64>, 4096>(SearchType::Eq(MAGIC), Some(contains(target.known))).unwrap();
+    assert!(res.contains(&target.known));
+    mem.dettach().unwrap();
+}
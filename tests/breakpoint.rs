@@ -0,0 +1,95 @@
+mod common;
+
+use std::time::Duration;
+use common::{bump, Target};
+use mempoll::breakpoint::{Breakpoints, BREAK_INSN};
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::memory::MemoryReader;
+use mempoll::registers::GeneralRegisters;
+
+fn code(mem: &PtraceMemory, address: usize) -> Vec<u8> {
+    let mut buf = vec![0; BREAK_INSN.len()];
+    mem.read_exact(address, &mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn hits_and_steps_over() {
+    let target = Target::spawn_busy();
+    let mem = PtraceMemory::new(target.pid);
+    let bump_addr = bump as *const () as usize;
+    let counter = target.known + 8;
+    let original = code(&mem, bump_addr);
+
+    let mut bps = Breakpoints::new(&mem);
+    bps.add(bump_addr).unwrap();
+    assert_eq!(code(&mem, bump_addr), BREAK_INSN);
+    let before = mem.read::<u32>(counter).unwrap();
+
+    let hits = bps.wait(Duration::from_secs(5), 3).unwrap();
+    assert!(hits.len() >= 3);
+    for hit in hits.iter() {
+        assert_eq!(hit.tid, target.pid);
+        assert_eq!(hit.address, bump_addr);
+        assert_eq!(hit.regs.pc() as usize, bump_addr);
+        // 第一个参数就是 counter 的地址
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(hit.regs.rdi as usize, counter);
+    }
+    assert_eq!(bps.get(bump_addr).unwrap().hits.len(), hits.len());
+    assert!(mem.thread_states().iter().all(|(_, s)| *s == TraceState::Running));
+
+    // 越过断点后 bump 照常执行
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(mem.read::<u32>(counter).unwrap() >= before + 2);
+
+    let removed = bps.remove(bump_addr).unwrap();
+    assert_eq!(removed.original, original);
+    assert_eq!(code(&mem, bump_addr), original);
+    assert!(bps.wait(Duration::from_millis(50), 1).unwrap().is_empty());
+}
+
+#[test]
+fn disable_and_enable() {
+    let target = Target::spawn_busy();
+    let mem = PtraceMemory::new(target.pid);
+    let bump_addr = bump as *const () as usize;
+    let original = code(&mem, bump_addr);
+
+    {
+        let mut bps = Breakpoints::new(&mem);
+        bps.add(bump_addr).unwrap();
+        bps.disable(bump_addr).unwrap();
+        assert_eq!(code(&mem, bump_addr), original);
+        assert!(bps.wait(Duration::from_millis(50), 1).unwrap().is_empty());
+
+        bps.enable(bump_addr).unwrap();
+        assert_eq!(bps.wait(Duration::from_secs(5), 1).unwrap().len(), 1);
+        assert!(bps.disable(bump_addr + 1).is_err());
+    }
+
+    // drop 后恢复原始字节，目标继续运行
+    assert_eq!(code(&mem, bump_addr), original);
+    let counter = mem.read::<u32>(target.known + 8).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(mem.read::<u32>(target.known + 8).unwrap() > counter);
+}
+
+#[test]
+fn single_step_trace() {
+    let target = Target::spawn_busy();
+    let mem = PtraceMemory::new(target.pid);
+    let bump_addr = bump as *const () as usize;
+
+    let mut bps = Breakpoints::new(&mem);
+    bps.add(bump_addr).unwrap();
+    bps.wait(Duration::from_secs(5), 1).unwrap();
+
+    // 跟踪一轮循环，经过 bump 时不会停在断点上
+    let steps = bps.trace(target.pid, 2000).unwrap();
+    assert_eq!(steps.len(), 2000);
+    let at_bump = steps.iter().position(|r| r.pc() as usize == bump_addr).expect("bump not reached");
+    assert_ne!(steps[at_bump + 1].pc() as usize, bump_addr);
+    assert_eq!(bps.get(bump_addr).unwrap().hits.len(), 1);
+    assert_eq!(mem.state(target.pid), Some(TraceState::Running));
+}
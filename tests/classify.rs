@@ -0,0 +1,147 @@
+use mempoll::process::classify::{AndroidClassifier, DesktopLinuxClassifier, RegionClassifier};
+use mempoll::process::{parse_maps_with, MemoryType};
+
+const DESKTOP: &str = "\
+55d1c0800000-55d1c0900000 r-xp 00000000 08:01 100     /home/user/game/bin/game
+55d1c0900000-55d1c0910000 rw-p 00100000 08:01 100     /home/user/game/bin/game
+55d1c0910000-55d1c0920000 rw-p 00000000 00:00 0
+55d1c0a00000-55d1c0a21000 rw-p 00000000 00:00 0       [heap]
+7f0000000000-7f0000021000 rw-p 00000000 00:00 0
+7f0000021000-7f0004000000 ---p 00000000 00:00 0
+7f0010000000-7f0010100000 r-xp 00000000 08:01 200     /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0020000000-7f0020100000 r-xp 00000000 08:01 300     /home/user/.steam/steamapps/common/Game/Game.exe
+7f0020100000-7f0020110000 rw-p 00100000 08:01 300     /home/user/.steam/steamapps/common/Game/Game.exe
+7f0030000000-7f0030100000 r-xp 00000000 08:01 400     /home/user/.steam/steamapps/common/Proton 9.0/files/lib/wine/x86_64-windows/KERNEL32.dll
+7f0040000000-7f0040100000 r-xp 00000000 08:01 500     /home/user/game/GameAssembly.so
+7f0040100000-7f0040110000 r--p 00000000 08:01 501     /home/user/game/Data/il2cpp_data/Metadata/global-metadata.dat
+7f0050000000-7f0050100000 rw-s 00000000 00:05 600     /dev/dri/renderD128
+7f0060000000-7f0060100000 rw-p 00000000 00:00 0       [anon:glibc malloc]
+7f0070000000-7f0070100000 r-xp 00000000 00:01 700     /memfd:doublemapper (deleted)
+7ffd5e3c1000-7ffd5e3e2000 rw-p 00000000 00:00 0       [stack]
+7ffd5e3f0000-7ffd5e3f2000 r--p 00000000 00:00 0       [vvar]
+7ffd5e3f2000-7ffd5e3f4000 r-xp 00000000 00:00 0       [vdso]
+";
+
+#[test]
+fn desktop_profile() {
+    let maps = parse_maps_with(DESKTOP, &DesktopLinuxClassifier::new()).unwrap();
+    let types = maps.iter().map(|m| m.memory_type.clone()).collect::<Vec<_>>();
+    assert_eq!(types, vec![
+        MemoryType::Xa,
+        MemoryType::Cd,
+        MemoryType::Cb,
+        MemoryType::Ch,
+        MemoryType::Ca,
+        MemoryType::Other,
+        MemoryType::Xs,
+        MemoryType::Xa,
+        MemoryType::Cd,
+        MemoryType::Xs,
+        MemoryType::Xa,
+        MemoryType::Cd,
+        MemoryType::V,
+        MemoryType::Ca,
+        MemoryType::Xa,
+        MemoryType::S,
+        MemoryType::Bad,
+        MemoryType::Xs,
+    ]);
+}
+
+#[test]
+fn managed_runtimes() {
+    // 已提交部分后面紧跟保留部分时才是托管堆，其它匿名区域仍是 A
+    let jvm = "\
+7f0010000000-7f0010100000 r-xp 00000000 08:01 200     /usr/lib/jvm/java-17/lib/server/libjvm.so
+7f0080000000-7f0088000000 rw-p 00000000 00:00 0
+7f0088000000-7f0090000000 ---p 00000000 00:00 0
+7f00a0001000-7f00a0800000 rw-p 00000000 00:00 0
+";
+    let maps = parse_maps_with(jvm, &DesktopLinuxClassifier::new()).unwrap();
+    assert_eq!(maps[1].memory_type, MemoryType::Jh);
+    assert_eq!(maps[3].memory_type, MemoryType::A);
+
+    let dotnet = "\
+7f0010000000-7f0010100000 r-xp 00000000 08:01 200     /usr/share/dotnet/shared/Microsoft.NETCore.App/8.0.0/libcoreclr.so
+7f0080400000-7f0080800000 rw-p 00000000 00:00 0
+7f0080800000-7f0090000000 ---p 00000000 00:00 0
+7f00a0000000-7f00a0400000 rw-p 00000000 00:00 0
+";
+    let maps = parse_maps_with(dotnet, &DesktopLinuxClassifier::new()).unwrap();
+    assert_eq!(maps[1].memory_type, MemoryType::Nh);
+    assert_eq!(maps[3].memory_type, MemoryType::A);
+
+    // 线程栈：保护页在下面，上面紧跟另一个线程的保护页
+    let mono = "\
+7f0010000000-7f0010100000 r-xp 00000000 08:01 200     /home/user/game/MonoBleedingEdge/x86_64/libmonobdwgc-2.0.so
+7f0080100000-7f0080200000 rw-p 00000000 00:00 0
+7f0080200000-7f0081000000 ---p 00000000 00:00 0
+7f0090000000-7f0090001000 ---p 00000000 00:00 0
+7f0090001000-7f0090801000 rw-p 00000000 00:00 0
+7f0090801000-7f0090802000 ---p 00000000 00:00 0
+";
+    let maps = parse_maps_with(mono, &DesktopLinuxClassifier::new()).unwrap();
+    assert_eq!(maps[1].memory_type, MemoryType::Mh);
+    assert_eq!(maps[4].memory_type, MemoryType::A);
+}
+
+#[test]
+fn allocator_and_mono_evidence() {
+    // jemalloc 的 chunk 按 2M 对齐且大小是 2M 的整数倍；线程栈和普通 mmap 不算
+    let jemalloc = "\
+7f0010000000-7f0010100000 r-xp 00000000 08:01 200     /usr/lib/x86_64-linux-gnu/libjemalloc.so.2
+7f0080000000-7f0080400000 rw-p 00000000 00:00 0
+7f0090000000-7f0090001000 ---p 00000000 00:00 0
+7f0090001000-7f0090201000 rw-p 00000000 00:00 0
+7f00a0000000-7f00a0801000 rw-p 00000000 00:00 0
+7f00b0000000-7f00b0200000 rw-p 00000000 00:00 0
+";
+    let maps = parse_maps_with(jemalloc, &DesktopLinuxClassifier::new()).unwrap();
+    let types = maps.iter().map(|m| m.memory_type.clone()).collect::<Vec<_>>();
+    assert_eq!(types[1..], [MemoryType::Ca, MemoryType::Other, MemoryType::A, MemoryType::A, MemoryType::Ca]);
+
+    // 没有加载分配器时对齐的区域也是 A
+    let maps = parse_maps_with(&jemalloc.replace("libjemalloc.so.2", "libz.so.1"), &DesktopLinuxClassifier::new()).unwrap();
+    assert_eq!(maps[1].memory_type, MemoryType::A);
+
+    // 预先扫描的结果和 classify_all 相同
+    let maps = parse_maps_with(jemalloc, &DesktopLinuxClassifier::new()).unwrap();
+    let prepared = DesktopLinuxClassifier::for_maps(&maps);
+    assert!((0..maps.len()).all(|i| prepared.classify(&maps, i) == maps[i].memory_type));
+
+    // .NET Core 的 libmono-native.so 不是 Mono，1M 对齐的 GC 形式不算 Mono 堆
+    let dotnet = "\
+7f0010000000-7f0010100000 r-xp 00000000 08:01 200     /usr/share/dotnet/shared/Microsoft.NETCore.App/8.0.0/libcoreclr.so
+7f0010100000-7f0010200000 r-xp 00000000 08:01 201     /usr/share/dotnet/shared/Microsoft.NETCore.App/8.0.0/libmono-native.so
+7f0080100000-7f0080200000 rw-p 00000000 00:00 0
+7f0080200000-7f0081000000 ---p 00000000 00:00 0
+";
+    let maps = parse_maps_with(dotnet, &DesktopLinuxClassifier::new()).unwrap();
+    assert_eq!(maps[2].memory_type, MemoryType::A);
+    let maps = parse_maps_with(&dotnet.replace("libmono-native.so", "libmonosgen-2.0.so.1"), &DesktopLinuxClassifier::new()).unwrap();
+    assert_eq!(maps[2].memory_type, MemoryType::Mh);
+}
+
+#[test]
+fn android_profile() {
+    let android = "\
+7000000000-7000100000 r-xp 00000000 fd:05 100     /data/app/~~x==/com.example.game-1/lib/arm64/libil2cpp.so
+7000100000-7000110000 rw-p 00100000 fd:05 100     /data/app/~~x==/com.example.game-1/lib/arm64/libil2cpp.so
+7000110000-7000120000 rw-p 00000000 00:00 0       [anon:.bss]
+7100000000-7100100000 rw-p 00000000 00:00 0       [anon:scudo:primary]
+7200000000-7200100000 rw-p 00000000 00:00 0       [anon:dalvik-main space (region space)]
+7300000000-7300100000 rw-p 00000000 00:00 0
+7400000000-7400100000 rw-s 00000000 00:05 600     /dev/kgsl-3d0
+";
+    let maps = parse_maps_with(android, &AndroidClassifier).unwrap();
+    let types = maps.iter().map(|m| m.memory_type.clone()).collect::<Vec<_>>();
+    assert_eq!(types, vec![
+        MemoryType::Xa,
+        MemoryType::Cd,
+        MemoryType::Cd,
+        MemoryType::Ca,
+        MemoryType::Jh,
+        MemoryType::A,
+        MemoryType::V,
+    ]);
+}
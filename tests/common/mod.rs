@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::waitpid;
+use nix::unistd::{fork, ForkResult, Pid};
+use mempoll::memory::{Pod, Zeroable};
+
+pub const MAGIC: u64 = 0xDEAD_BEEF_CAFE_F00D;
+pub const COUNTER: u32 = 0x1234_5678;
+pub const SPEED: f32 = 13.5;
+
+/// 子进程中已知地址上的已知值
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[bytemuck(crate = "mempoll::memory::bytemuck")]
+pub struct Known {
+    pub magic: u64,
+    pub counter: u32,
+    pub speed: f32,
+    pub bytes: [u8; 16],
+}
+
+/// fork 出的测试目标进程，drop 时杀死
+///
+/// fork 之后地址空间布局一致，所以父进程知道子进程里 `Known` 和 `page` 的地址
+pub struct Target {
+    pub pid: u32,
+    /// `Known` 所在地址
+    pub known: usize,
+    /// 两页大小、页对齐的缓冲区，内容为 i as u8
+    pub pages: usize,
+    pub pages_len: usize,
+}
+
+/// 忙碌的目标进程每毫秒调用一次，给 counter 加一
+#[inline(never)]
+pub extern "C" fn bump(counter: *mut u32) {
+    unsafe { *counter = (*counter).wrapping_add(1) };
+}
+
+/// 子进程中用 clone 创建的线程，一直睡眠
+extern "C" fn idle_thread(_: *mut nix::libc::c_void) -> nix::libc::c_int {
+    loop {
+        unsafe { nix::libc::pause() };
+    }
+}
+
+/// 子进程中用 clone 创建的线程，每毫秒调用一次 `bump(counter)`
+extern "C" fn busy_thread(counter: *mut nix::libc::c_void) -> nix::libc::c_int {
+    loop {
+        bump(counter as *mut u32);
+        unsafe { nix::libc::usleep(1000) };
+    }
+}
+
+const THREAD_STACK_SIZE: usize = 0x10000;
+
+/// 子进程的主线程做什么
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Work {
+    Sleep,
+    Bump,
+    /// 睡眠 LATE_THREAD_DELAY 后创建一个忙碌的线程
+    LateBumpThread,
+}
+
+pub const LATE_THREAD_DELAY: std::time::Duration = std::time::Duration::from_millis(300);
+
+impl Target {
+    /// 子进程一直睡眠
+    pub fn spawn() -> Self {
+        Self::spawn_with(Work::Sleep, 0)
+    }
+
+    /// 子进程每毫秒调用一次 `bump(&known.counter)`
+    pub fn spawn_busy() -> Self {
+        Self::spawn_with(Work::Bump, 0)
+    }
+
+    /// 子进程启动 LATE_THREAD_DELAY 后创建一个线程，每毫秒调用一次 `bump(&known.counter)`
+    pub fn spawn_late_busy_thread() -> Self {
+        Self::spawn_with(Work::LateBumpThread, 1)
+    }
+
+    /// 子进程除主线程外还有 threads 个睡眠的线程，返回时线程都已创建
+    pub fn spawn_threads(threads: usize) -> Self {
+        let target = Self::spawn_with(Work::Sleep, threads);
+        let process = mempoll::process::Process::new(target.pid);
+        for _ in 0..1000 {
+            if process.tids().unwrap().len() == threads + 1 {
+                return target;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("target threads were not created");
+    }
+
+    fn spawn_with(work: Work, threads: usize) -> Self {
+        let known = Box::leak(Box::new(Known {
+            magic: MAGIC,
+            counter: COUNTER,
+            speed: SPEED,
+            bytes: *b"mempoll-harness\0",
+        })) as *mut Known as usize;
+
+        let pages_len = 2 * 4096;
+        let layout = std::alloc::Layout::from_size_align(pages_len, 4096).unwrap();
+        let pages = unsafe { std::alloc::alloc(layout) };
+        for i in 0..pages_len {
+            unsafe { *pages.add(i) = i as u8 };
+        }
+
+        // fork 之后不能再分配内存，线程栈提前准备好
+        let stacks = (0..threads).map(|_| Box::leak(vec![0u8; THREAD_STACK_SIZE].into_boxed_slice())).collect::<Vec<_>>();
+
+        match unsafe { fork() }.expect("fork") {
+            ForkResult::Child => {
+                // 不设置 TLS，线程里只调用 pause
+                let flags = nix::libc::CLONE_VM | nix::libc::CLONE_FS | nix::libc::CLONE_FILES
+                    | nix::libc::CLONE_SIGHAND | nix::libc::CLONE_THREAD | nix::libc::CLONE_SYSVSEM;
+                for stack in stacks.iter() {
+                    let top = unsafe { stack.as_ptr().add(THREAD_STACK_SIZE) } as *mut nix::libc::c_void;
+                    if work == Work::LateBumpThread {
+                        unsafe { nix::libc::usleep(LATE_THREAD_DELAY.as_micros() as u32) };
+                        unsafe { nix::libc::clone(busy_thread, top, flags, (known + 8) as *mut nix::libc::c_void) };
+                    } else {
+                        unsafe { nix::libc::clone(idle_thread, top, flags, std::ptr::null_mut()) };
+                    }
+                }
+                loop {
+                    // 子进程只做 async-signal-safe 的事
+                    if work == Work::Bump {
+                        bump((known + 8) as *mut u32);
+                        unsafe { nix::libc::usleep(1000) };
+                    } else {
+                        unsafe { nix::libc::pause() };
+                    }
+                }
+            },
+            ForkResult::Parent { child } => Target {
+                pid: child.as_raw() as u32,
+                known,
+                pages: pages as usize,
+                pages_len,
+            },
+        }
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        let pid = Pid::from_raw(self.pid as i32);
+        let _ = kill(pid, Signal::SIGKILL);
+        let _ = waitpid(pid, None);
+    }
+}
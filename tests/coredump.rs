@@ -0,0 +1,155 @@
+mod common;
+
+use common::{Target, COUNTER, MAGIC};
+use mempoll::coredump::{CoreDumper, PR_PID_OFFSET, PR_REG_OFFSET, PR_REG_SIZE};
+use mempoll::elf::{self, ElfHeader, ProgramHeader};
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::memory::{core_memory::CoreMemory, mock_memory::MockMemory, process_vm_memory::ProcessVmMemory, MemoryError, MemoryReader};
+use mempoll::process::{parse_maps, MapRange, Process};
+use mempoll::searcher::{MemorySearcher, SearchType};
+
+#[test]
+fn dump_and_reload() {
+    let target = Target::spawn();
+    let mut mem = ProcessVmMemory::new(target.pid);
+    mem.process.maps().unwrap();
+
+    let path = std::env::temp_dir().join(format!("mempoll-core-{}", target.pid));
+    let mut file = std::fs::File::create(&path).unwrap();
+    let filter = |m: &MapRange| m.writable() && !m.pathname.starts_with("[v");
+    CoreDumper::new(&mem.process, &mem).write(&mut file, Some(filter)).unwrap();
+    drop(file);
+
+    let core = CoreMemory::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(core.read::<u64>(target.known).unwrap(), MAGIC);
+    assert_eq!(core.read::<u32>(target.known + 8).unwrap(), COUNTER);
+    let dumped = mem.process.maps.iter().filter(|m| filter(m)).count();
+    assert_eq!(core.process.maps.len(), dumped);
+
+    let res = core.search::<SearchType<u64>, 4096>(SearchType::Eq(MAGIC), None::<fn(&MapRange) -> bool>).unwrap();
+    assert!(res.contains(&target.known));
+}
+
+#[test]
+fn dump_thread_notes() {
+    let target = Target::spawn_threads(2);
+    let mut mem = PtraceMemory::new(target.pid);
+    mem.process.maps().unwrap();
+
+    // 调用者已经停住目标，寄存器不会变，dump 之后保持停止
+    mem.stop_world().unwrap();
+    let path = std::env::temp_dir().join(format!("mempoll-core-notes-{}", target.pid));
+    let mut file = std::fs::File::create(&path).unwrap();
+    let filter = |m: &MapRange| m.readable() && !m.pathname.starts_with("[v");
+    CoreDumper::from_ptrace(&mem).unwrap().write(&mut file, Some(filter)).unwrap();
+    drop(file);
+    assert!(mem.thread_states().iter().all(|(_, s)| *s == TraceState::Stopped { signal: None }));
+
+    let core = CoreMemory::open(&path).unwrap();
+    assert_eq!(core.process.pid, target.pid);
+    assert_eq!(core.read::<u64>(target.known).unwrap(), MAGIC);
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let ehdr = ElfHeader::parse(&data).unwrap();
+    let note = ProgramHeader::parse(&data[ehdr.e_phoff as usize..]).unwrap();
+    assert_eq!(note.p_type, elf::PT_NOTE);
+    let notes = &data[note.p_offset as usize..(note.p_offset + note.p_filesz) as usize];
+
+    let mut tids = Vec::new();
+    let mut files = Vec::new();
+    for note in elf::notes(notes) {
+        let note = note.unwrap();
+        match note.n_type {
+            elf::NT_PRSTATUS => {
+                let tid = u32::from_le_bytes(note.desc[PR_PID_OFFSET..PR_PID_OFFSET + 4].try_into().unwrap());
+                let regs = mem.regs(tid).unwrap();
+                assert_eq!(&note.desc[PR_REG_OFFSET..PR_REG_OFFSET + PR_REG_SIZE], bytemuck::bytes_of(&regs));
+                // pr_pgrp 是进程组，不是 pid
+                let pgrp = nix::unistd::getpgid(Some(nix::unistd::Pid::from_raw(target.pid as i32))).unwrap().as_raw() as u32;
+                assert_eq!(note.desc[40..44], pgrp.to_le_bytes());
+                tids.push(tid);
+            },
+            elf::NT_FILE => files = elf::parse_nt_file(note.desc).unwrap(),
+            _ => {},
+        }
+    }
+    assert_eq!(tids, mem.attached_threads());
+    assert_eq!(tids.len(), 3);
+    mem.resume().unwrap();
+
+    let expected = mem.process.maps.iter()
+        .filter(|m| filter(m) && m.pathname.starts_with('/'))
+        .map(|m| (m.address.0 as u64, m.address.1 as u64, m.offset as u64, m.pathname.clone()))
+        .collect::<Vec<_>>();
+    assert!(!expected.is_empty());
+    assert_eq!(files.into_iter().map(|f| (f.start, f.end, f.file_offset, f.path)).collect::<Vec<_>>(), expected);
+}
+
+/// 只有 ELF 头和程序头的 core 文件
+fn write_core(name: &str, phnum: u16, phdrs: &[ProgramHeader]) -> std::path::PathBuf {
+    let ehdr = ElfHeader {
+        e_type: elf::ET_CORE,
+        e_machine: elf::EM_X86_64,
+        e_phoff: elf::EHDR_SIZE as u64,
+        e_phentsize: elf::PHDR_SIZE as u16,
+        e_phnum: phnum,
+        ..Default::default()
+    };
+    let mut data = ehdr.to_bytes().to_vec();
+    for phdr in phdrs {
+        data.extend_from_slice(&phdr.to_bytes());
+    }
+    let path = std::env::temp_dir().join(format!("mempoll-bad-core-{}-{}", std::process::id(), name));
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+#[test]
+fn reject_malformed_core() {
+    let load = |offset, filesz, vaddr, memsz| ProgramHeader {
+        p_type: elf::PT_LOAD, p_flags: elf::PF_R, p_offset: offset, p_vaddr: vaddr, p_filesz: filesz, p_memsz: memsz, ..Default::default()
+    };
+    let note = ProgramHeader { p_type: elf::PT_NOTE, p_offset: 0, p_filesz: u64::MAX / 2, ..Default::default() };
+    let cases = [
+        ("phnum", write_core("phnum", 0xfff0, &[])),
+        ("note", write_core("note", 1, &[note])),
+        ("load-offset", write_core("load-offset", 1, &[load(u64::MAX - 8, 0x10, 0x1000, 0x10)])),
+        ("load-vaddr", write_core("load-vaddr", 1, &[load(0, 0, u64::MAX - 8, 0x100)])),
+    ];
+    for (name, path) in cases {
+        let res = CoreMemory::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(res, Err(MemoryError::CoreParseError(_))), "{name}");
+    }
+}
+
+#[test]
+fn many_maps_use_pn_xnum() {
+    // 0x10000 个不可读的区域加一个可读的，超过 e_phnum 的范围
+    let count = 0x10000;
+    let mut text = String::new();
+    for i in 0..count {
+        let start = 0x100000 + i * 0x2000;
+        text += &format!("{:x}-{:x} ---p 00000000 00:00 0\n", start, start + 0x1000);
+    }
+    text += "1000-2000 rw-p 00000000 00:00 0\n";
+    let mut process = Process::new(1);
+    process.maps = parse_maps(&text).unwrap();
+    let mut mem = MockMemory::new();
+    mem.add_rw(0x1000, vec![0xab; 0x1000]);
+
+    let path = std::env::temp_dir().join(format!("mempoll-core-xnum-{}", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    CoreDumper::new(&process, &mem).write(&mut file, None::<fn(&MapRange) -> bool>).unwrap();
+    drop(file);
+
+    let raw = std::fs::read(&path).unwrap();
+    assert_eq!(ElfHeader::parse(&raw).unwrap().e_phnum, elf::PN_XNUM);
+    let core = CoreMemory::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(core.process.maps.len(), count + 1);
+    assert_eq!(core.read::<u64>(0x1800).unwrap(), 0xabababababababab);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::Target;
+use mempoll::diff::MemoryDiff;
+use mempoll::memory::{mock_memory::MockMemory, process_vm_memory::ProcessVmMemory, snapshot_memory::SnapshotMemory, MemoryReader, MemoryWriter};
+use mempoll::process::{MapRange, Process};
+use mempoll::searcher::{MemorySearcher, SearchType};
+use mempoll::structs::{Primitive, Value};
+
+fn mock() -> MockMemory {
+    let mut mem = MockMemory::new();
+    mem.add_rw(0x1000, vec![0; 0x100])
+        .add_region(0x2000, vec![0; 0x10], 0, "")
+        .add_rw(0x3000, vec![0; 0x100]);
+    mem
+}
+
+#[test]
+fn snapshot_reads_and_searches() {
+    let mem = mock();
+    mem.write(0x1010, &0xDEADBEEFu32).unwrap();
+    mem.write(0x30f0, &0xDEADBEEFu32).unwrap();
+
+    let snap = SnapshotMemory::capture(&mem, &mem.process, None::<fn(&MapRange) -> bool>);
+    assert_eq!(snap.ranges().collect::<Vec<_>>(), vec![(0x1000, 0x1100), (0x3000, 0x3100)]);
+    assert_eq!(snap.size(), 0x200);
+
+    // 快照之后的修改不影响快照
+    mem.write(0x1010, &1u32).unwrap();
+    assert_eq!(snap.read::<u32>(0x1010).unwrap(), 0xDEADBEEF);
+    assert!(snap.read::<u32>(0x2000).is_err());
+    assert!(snap.read::<u32>(0x10fe).is_err());
+
+    let found = snap.search::<_, 64>(SearchType::Eq(0xDEADBEEFu32), None::<fn(&MapRange) -> bool>).unwrap();
+    assert_eq!(found, vec![0x1010, 0x30f0]);
+    let found = snap.search::<_, 64>(SearchType::Eq(0xDEADBEEFu32), Some(|m: &MapRange| m.address.0 == 0x3000)).unwrap();
+    assert_eq!(found, vec![0x30f0]);
+
+    let filtered = SnapshotMemory::capture(&mem, &mem.process, Some(|m: &MapRange| m.address.0 == 0x3000));
+    assert_eq!(filtered.size(), 0x100);
+    assert_eq!(filtered.process.maps.len(), 1);
+}
+
+#[test]
+fn snapshot_to_snapshot() {
+    let mem = mock();
+    let before = SnapshotMemory::capture(&mem, &mem.process, None::<fn(&MapRange) -> bool>);
+    mem.write(0x1010, &100u32).unwrap();
+    mem.write(0x1014, &200u32).unwrap();
+    mem.write(0x1021, &7u8).unwrap();
+    mem.write(0x3000, &1u32).unwrap();
+    let after = SnapshotMemory::capture(&mem, &mem.process, None::<fn(&MapRange) -> bool>);
+
+    let diff = MemoryDiff::new(Primitive::U32).diff_snapshots(&before, &after);
+    assert_eq!(diff.len(), 3);
+    assert_eq!((diff[0].start, diff[0].end), (0x1010, 0x1018));
+    assert_eq!(diff[0].changes[1].old, Value::U32(0));
+    assert_eq!(diff[0].changes[1].new, Value::U32(200));
+    assert_eq!((diff[1].start, diff[1].len()), (0x1020, 4));
+    assert_eq!(diff[1].changes[0].new, Value::U32(7 << 8));
+    assert_eq!(diff[2].start, 0x3000);
+
+    // 按字节比较
+    let diff = MemoryDiff::new(Primitive::U8).diff_snapshots(&before, &after);
+    assert_eq!(diff.iter().map(|r| r.changes.len()).sum::<usize>(), 4);
+    assert_eq!(diff[2].changes[0], mempoll::diff::Change { address: 0x1021, old: Value::U8(0), new: Value::U8(7) });
+
+    // 不对齐的步长
+    let diff = MemoryDiff::new(Primitive::U16).step(1).diff(&before, &after, [(0x1020, 0x1024)]);
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].changes.len(), 2);
+    assert_eq!((diff[0].start, diff[0].end), (0x1020, 0x1023));
+}
+
+#[test]
+fn region_to_region() {
+    let mem = mock();
+    mem.writebuf(0x1000, &[1, 2, 3, 4]).unwrap();
+    mem.writebuf(0x3000, &[1, 9, 3, 4]).unwrap();
+
+    let diff = MemoryDiff::new(Primitive::U8).diff_regions(&mem, 0x1000, &mem, 0x3000, 0x100);
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].changes[0], mempoll::diff::Change { address: 0x1001, old: Value::U8(2), new: Value::U8(9) });
+
+    // 读不到的一边跳过
+    let diff = MemoryDiff::new(Primitive::U8).diff_regions(&mem, 0x10f0, &mem, 0x2000, 0x20);
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn live_against_snapshot() {
+    let target = Target::spawn();
+    let mem = ProcessVmMemory::new(target.pid);
+    let mut process = Process::new(target.pid);
+    process.maps().unwrap();
+
+    let contains = |m: &MapRange| m.address.0 <= target.pages && target.pages < m.address.1 && m.readable();
+    let snap = SnapshotMemory::capture(&mem, &process, Some(contains));
+    assert!(snap.size() >= target.pages_len);
+    assert!(snap.process.maps.iter().all(|m| m.readable()));
+
+    mem.write(target.pages + 0x1234, &1.5f32).unwrap();
+    let diff = MemoryDiff::new(Primitive::F32).diff(&snap, &mem, [(target.pages, target.pages + target.pages_len)]);
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].start, target.pages + 0x1234);
+    assert_eq!(diff[0].changes[0].new, Value::F32(1.5));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::Target;
+use mempoll::process::discovery::{self, ProcessInfo, ProcessQuery};
+
+#[test]
+fn find_spawned_target() {
+    let target = Target::spawn();
+    let info = ProcessInfo::from_pid(target.pid).unwrap();
+    assert_eq!(info.ppid, std::process::id());
+    assert_eq!(info.uid, unsafe { nix::libc::getuid() });
+    assert_eq!(info.exe, std::env::current_exe().ok());
+
+    let found = discovery::find(&ProcessQuery::Name(info.name.clone())).unwrap();
+    assert!(found.iter().any(|p| p.pid == target.pid));
+
+    let found = discovery::find(&ProcessQuery::regex(&format!("^{}$", regex::escape(&info.name))).unwrap()).unwrap();
+    assert!(found.iter().any(|p| p.pid == target.pid));
+}
+
+#[test]
+fn android_package() {
+    let info = ProcessInfo {
+        pid: 1000,
+        ppid: 1,
+        uid: 10123,
+        name: "ple.game:remote".to_string(),
+        cmdline: vec!["com.example.game:remote".to_string()],
+        exe: None,
+    };
+    assert_eq!(info.package(), Some("com.example.game"));
+    assert!(ProcessQuery::Package("com.example.game".to_string()).matches(&info));
+    assert!(ProcessQuery::Name("com.example.game".to_string()).matches(&info));
+    assert!(ProcessQuery::Contains("example".to_string()).matches(&info));
+
+    let zygote = ProcessInfo { exe: Some("/system/bin/app_process64".into()), ..info.clone() };
+    assert_eq!(zygote.package(), Some("com.example.game"));
+
+    // 带点的普通程序名不是包名
+    for (argv0, exe) in [("python3.11", None), ("a..b", None), ("ld.so", Some("/usr/lib/ld-linux-x86-64.so.2")), ("com.example.game", Some("/usr/bin/game"))] {
+        let other = ProcessInfo { cmdline: vec![argv0.to_string()], exe: exe.map(Into::into), ..info.clone() };
+        assert_eq!(other.package(), None, "{argv0}");
+    }
+
+    let native = ProcessInfo { cmdline: vec!["/system/bin/surfaceflinger".to_string()], ..info };
+    assert_eq!(native.package(), None);
+    assert!(ProcessQuery::Name("surfaceflinger".to_string()).matches(&native));
+}
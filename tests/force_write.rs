@@ -0,0 +1,77 @@
+mod common;
+
+use common::{bump, Target};
+use mempoll::memory::force_memory::{ForceMemory, WriteStrategy};
+use mempoll::memory::process_vm_memory::ProcessVmMemory;
+use mempoll::memory::ptrace_memory::PtraceMemory;
+use mempoll::memory::{MemoryReader, MemoryWriter};
+use mempoll::process::Process;
+
+fn is_writable(pid: u32, address: usize) -> bool {
+    let maps = Process::new(pid).read_maps().unwrap();
+    maps.iter().find(|m| m.address.0 <= address && address < m.address.1).unwrap().writable()
+}
+
+/// 子进程不会执行到 bump，可以随便改
+fn code_address() -> usize {
+    bump as *const () as usize
+}
+
+#[test]
+fn direct_write_fails_on_code() {
+    let target = Target::spawn();
+    let mem = ProcessVmMemory::new(target.pid);
+    assert!(!is_writable(target.pid, code_address()));
+    assert!(mem.write(code_address(), &0u32).is_err());
+}
+
+#[test]
+fn default_strategies() {
+    let target = Target::spawn();
+    let mem = ForceMemory::new(target.pid);
+    assert_eq!(mem.last_strategy(), None);
+
+    // 可写页面直接写
+    assert_eq!(mem.force_writebuf(target.known + 8, &[1, 2, 3, 4]).unwrap(), WriteStrategy::Direct);
+    assert_eq!(mem.read::<u32>(target.known + 8).unwrap(), 0x04030201);
+
+    let address = code_address();
+    let original = mem.read::<u64>(address).unwrap();
+    assert_eq!(mem.force_writebuf(address, &0x9090909090909090u64.to_ne_bytes()).unwrap(), WriteStrategy::ProcMem);
+    assert_eq!(mem.read::<u64>(address).unwrap(), 0x9090909090909090);
+    mem.write(address, &original).unwrap();
+    assert_eq!(mem.last_strategy(), Some(WriteStrategy::ProcMem));
+    assert_eq!(mem.read::<u64>(address).unwrap(), original);
+    assert!(!is_writable(target.pid, address));
+}
+
+#[test]
+fn ptrace_strategies() {
+    let target = Target::spawn();
+    let ptrace = PtraceMemory::new(target.pid);
+    let address = code_address();
+
+    // 没有 ptrace 后端时这两种都不可用
+    let mem = ForceMemory::new(target.pid).strategies(&[WriteStrategy::PtracePoke, WriteStrategy::Mprotect]);
+    let err = mem.force_writebuf(address, &[0xcc]).unwrap_err();
+    assert!(format!("{}", err).contains("PtracePoke"));
+
+    let mem = mem.with_ptrace(&ptrace);
+    let original = mem.read::<u32>(address).unwrap();
+    assert_eq!(mem.force_writebuf(address, &0x11223344u32.to_ne_bytes()).unwrap(), WriteStrategy::PtracePoke);
+    assert_eq!(mem.read::<u32>(address).unwrap(), 0x11223344);
+
+    // 跨页写入，两页都临时改成可写
+    let mem = ForceMemory::new(target.pid).with_ptrace(&ptrace).strategies(&[WriteStrategy::Mprotect]);
+    let page_end = (address / 0x1000 + 1) * 0x1000;
+    let mut before = [0u8; 8];
+    mem.read_exact(page_end - 4, &mut before).unwrap();
+    assert_eq!(mem.force_writebuf(page_end - 4, &[0xaa; 8]).unwrap(), WriteStrategy::Mprotect);
+    assert_eq!(mem.read::<u64>(page_end - 4).unwrap(), u64::from_ne_bytes([0xaa; 8]));
+    assert!(!is_writable(target.pid, page_end - 4));
+    assert!(!is_writable(target.pid, page_end));
+
+    mem.force_writebuf(page_end - 4, &before).unwrap();
+    mem.write(address, &original).unwrap();
+    assert_eq!(mem.read::<u32>(address).unwrap(), original);
+}
@@ -0,0 +1,78 @@
+use mempoll::hexdump::{Column, HexDump, Sidebar};
+use mempoll::memory::{mock_memory::MockMemory, MemoryWriter};
+use mempoll::process::permissions;
+
+fn mock() -> MockMemory {
+    let mut mem = MockMemory::new();
+    let mut code = vec![0x90u8; 0x20];
+    code[..8].copy_from_slice(b"mempoll\0");
+    mem.add_region(0x1000, code, permissions::READABLE | permissions::EXECUTABLE, "/usr/lib/libgame.so")
+        .add_rw(0x1030, "hi".encode_utf16().flat_map(|c| c.to_ne_bytes()).chain([0; 0x1c]).collect());
+    mem
+}
+
+#[test]
+fn bytes_and_annotations() {
+    let mem = mock();
+    let mut dump = HexDump::new();
+    let out = dump.render(&mem, &mem.process.maps, 0x1000, 0x40);
+    let lines: Vec<&str> = out.lines().collect();
+
+    assert_eq!(lines.len(), 6, "{out}");
+    assert!(lines[0].starts_with("-- 1000-1020 r-xp"), "{out}");
+    assert!(lines[1].starts_with("0000000000001000  6d 65 6d 70 6f 6c 6c 00 90"), "{out}");
+    assert!(lines[1].contains("|mempoll.........|  libgame.so+0x0"), "{out}");
+    assert!(lines[2].ends_with("libgame.so+0x10"), "{out}");
+    // 两个区域之间的空洞
+    assert!(lines[3].starts_with("0000000000001020  ?? ??"), "{out}");
+    assert!(lines[4].starts_with("-- 1030-1050 rw-p"), "{out}");
+    assert!(lines[5].starts_with("0000000000001030  68 00 69 00"), "{out}");
+    assert!(!lines[5].contains("libgame"), "{out}");
+}
+
+#[test]
+fn columns_and_sidebars() {
+    let mem = mock();
+    mem.write(0x1040, &1.5f32).unwrap();
+    let maps = mem.process.maps.clone();
+
+    let mut dump = HexDump::new().column(Column::Dword).sidebar(Sidebar::None);
+    let row = dump.render(&mem, &maps, 0x1000, 0x10);
+    assert!(row.contains(&format!("{:08x}", u32::from_ne_bytes(*b"memp"))), "{row}");
+    assert!(!row.contains('|'));
+
+    let mut dump = HexDump::new().column(Column::Float).sidebar(Sidebar::Utf16);
+    let row = dump.render(&mem, &maps, 0x1040, 0x10);
+    assert!(row.contains("1.5000"), "{row}");
+
+    let mut dump = HexDump::new().sidebar(Sidebar::Utf16);
+    let row = dump.render(&mem, &maps, 0x1030, 0x10);
+    assert!(row.contains("|hi......|"), "{row}");
+
+    // 末尾不满一行也要对齐
+    let mut dump = HexDump::new().column(Column::Qword);
+    let out = dump.render(&mem, &maps, 0x1000, 0x1b);
+    let lines: Vec<&str> = out.lines().filter(|l| !l.starts_with("--")).collect();
+    assert_eq!(lines[0].find('|'), lines[1].find('|'), "{out}");
+}
+
+#[test]
+fn unreadable_and_changes() {
+    let mem = mock();
+    let maps = mem.process.maps.clone();
+    let mut dump = HexDump::new();
+
+    let out = dump.render(&mem, &maps, 0x1040, 0x20);
+    assert!(out.contains("?? ?? ??"), "{out}");
+    assert!(!out.contains('*'));
+
+    mem.write(0x1044, &0xffu8).unwrap();
+    let rows = dump.rows(&mem, &maps, 0x1040, 0x20);
+    assert_eq!(rows[0].changed.iter().filter(|c| **c).count(), 1);
+    assert!(rows[0].changed[4]);
+    assert!(dump.format_row(&rows[0]).contains("*ff"));
+
+    // 没有变化时不再标出
+    let rows = dump.rows(&mem, &maps, 0x1040, 0x20);
+    assert!(rows.iter().all(|r| r.changed.iter().all(|c| !c)));
+}
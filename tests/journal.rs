@@ -0,0 +1,128 @@
+mod common;
+
+use common::{Target, MAGIC};
+use mempoll::journal::{Journal, JournalError};
+use mempoll::memory::mock_memory::MockMemory;
+use mempoll::memory::process_vm_memory::ProcessVmMemory;
+use mempoll::memory::{MemoryReader, MemoryWriter};
+
+fn mock() -> MockMemory {
+    let mut mem = MockMemory::new();
+    mem.add_rw(0x1000, (0..0x100).map(|i| i as u8).collect());
+    mem.add_region(0x2000, vec![0; 0x10], mempoll::process::permissions::READABLE, "");
+    mem
+}
+
+#[test]
+fn undo_redo() {
+    let mem = mock();
+    let journal = Journal::new(&mem);
+    let original = mem.read::<u64>(0x1000).unwrap();
+
+    journal.write(0x1000, &1u32).unwrap();
+    // 重叠的写入按相反的顺序撤销
+    journal.write(0x1002, &0xffffu16).unwrap();
+    assert_eq!(journal.edits()[1].old, vec![0, 0]);
+    assert_eq!(journal.edits()[1].new, vec![0xff, 0xff]);
+
+    // 失败的写入不记录
+    assert!(journal.write(0x2000, &1u32).is_err());
+    assert_eq!(journal.position(), 2);
+
+    assert_eq!(journal.undo().unwrap().address, 0x1002);
+    assert_eq!(mem.read::<u32>(0x1000).unwrap(), 1);
+    journal.undo().unwrap();
+    assert_eq!(mem.read::<u64>(0x1000).unwrap(), original);
+    assert!(matches!(journal.undo(), Err(JournalError::NothingToUndo)));
+
+    journal.redo().unwrap();
+    journal.redo().unwrap();
+    assert_eq!(mem.read::<u32>(0x1000).unwrap(), 0xffff0001);
+    assert!(matches!(journal.redo(), Err(JournalError::NothingToRedo)));
+
+    // 撤销后再写入丢弃可以重做的记录
+    journal.undo().unwrap();
+    journal.write(0x1010, &7u8).unwrap();
+    assert!(!journal.can_redo());
+    assert_eq!(journal.edits().len(), 2);
+}
+
+#[test]
+fn checkpoints() {
+    let mem = mock();
+    let journal = Journal::new(&mem);
+    journal.write(0x1000, &1u8).unwrap();
+    journal.checkpoint("a").unwrap();
+    journal.write(0x1001, &2u8).unwrap();
+    journal.write(0x1002, &3u8).unwrap();
+    journal.checkpoint("b").unwrap();
+    assert!(matches!(journal.checkpoint("has space"), Err(JournalError::InvalidCheckpoint(_))));
+
+    assert_eq!(journal.revert_to("a").unwrap(), 2);
+    assert_eq!(mem.read::<[u8; 3]>(0x1000).unwrap(), [1, 1, 2]);
+    // 回到后面的检查点会重做
+    assert_eq!(journal.revert_to("b").unwrap(), 0);
+    assert_eq!(mem.read::<[u8; 3]>(0x1000).unwrap(), [1, 2, 3]);
+    assert!(matches!(journal.revert_to("c"), Err(JournalError::UnknownCheckpoint(_))));
+
+    // 被丢弃的记录上的检查点也一起丢弃
+    journal.revert_to("a").unwrap();
+    journal.write(0x1003, &4u8).unwrap();
+    assert_eq!(journal.checkpoints(), vec![("a".to_string(), 1)]);
+
+    assert_eq!(journal.revert_all().unwrap(), 2);
+    assert_eq!(mem.read::<[u8; 4]>(0x1000).unwrap(), [0, 1, 2, 3]);
+}
+
+#[test]
+fn save_and_load() {
+    let mem = mock();
+    let journal = Journal::new(&mem);
+    journal.write(0x1000, &0xaabbu16).unwrap();
+    journal.checkpoint("start").unwrap();
+    journal.write(0x1004, &[9u8; 5]).unwrap();
+    journal.write(0x1020, &1u32).unwrap();
+    journal.undo().unwrap();
+
+    let text = journal.to_text();
+    let loaded = Journal::parse(&mem, &text).unwrap();
+    assert_eq!(loaded.edits(), journal.edits());
+    assert_eq!(loaded.checkpoints(), journal.checkpoints());
+    assert_eq!(loaded.position(), 2);
+    assert_eq!(loaded.to_text(), text);
+
+    loaded.redo().unwrap();
+    assert_eq!(mem.read::<u32>(0x1020).unwrap(), 1);
+    loaded.revert_to("start").unwrap();
+    assert_eq!(mem.read::<[u8; 5]>(0x1004).unwrap(), [4, 5, 6, 7, 8]);
+
+    let err = Journal::parse(&mem, "edit 0x1000 0 00 0102\n").err().unwrap();
+    assert!(matches!(err, JournalError::ParseError { line: 1, .. }), "{err}");
+    let err = Journal::parse(&mem, "# comment\n\nposition 3\n").err().unwrap();
+    assert!(matches!(err, JournalError::ParseError { .. }), "{err}");
+}
+
+#[test]
+fn revert_after_restart() {
+    let target = Target::spawn();
+    let path = std::env::temp_dir().join(format!("mempoll-journal-{}.txt", target.pid));
+    let magic = target.known;
+    let bytes = target.known + 16;
+    let original = ProcessVmMemory::new(target.pid).read::<[u8; 16]>(bytes).unwrap();
+
+    {
+        let mem = ProcessVmMemory::new(target.pid);
+        let journal = Journal::new(&mem);
+        journal.write(magic, &0u64).unwrap();
+        journal.write(bytes, b"journal-test-abc").unwrap();
+        journal.save(&path).unwrap();
+        assert_eq!(mem.read::<u64>(magic).unwrap(), 0);
+    }
+
+    let mem = ProcessVmMemory::new(target.pid);
+    let journal = Journal::load(&mem, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(journal.revert_all().unwrap(), 2);
+    assert_eq!(mem.read::<u64>(magic).unwrap(), MAGIC);
+    assert_eq!(mem.read::<[u8; 16]>(bytes).unwrap(), original);
+}
@@ -0,0 +1,93 @@
+use mempoll::process::{parse_maps, MapsDiff, Process};
+use nix::libc;
+
+const PAGE: usize = 4096;
+
+#[test]
+fn refresh_tracks_mmap_mprotect_munmap() {
+    let mut process = Process::new(std::process::id());
+    process.maps().unwrap();
+
+    let addr = unsafe {
+        libc::mmap(std::ptr::null_mut(), 3 * PAGE, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)
+    } as usize;
+    assert_ne!(addr, libc::MAP_FAILED as usize);
+
+    let diff = process.refresh_maps().unwrap();
+    assert!(diff.is_mapped(addr + PAGE));
+    assert!(diff.added.iter().chain(diff.grown.iter().map(|(_, n)| n))
+        .any(|m| m.address.0 <= addr && addr + 3 * PAGE <= m.address.1));
+
+    unsafe { libc::mprotect((addr + PAGE) as *mut libc::c_void, PAGE, libc::PROT_READ) };
+    let diff = process.refresh_maps().unwrap();
+    let middle = process.maps.iter().find(|m| m.address.0 == addr + PAGE).unwrap();
+    assert!(middle.readable() && !middle.writable());
+    assert!(diff.perms_changed.iter().any(|(o, n)| o.writable() && n.address == middle.address && !n.writable()));
+    assert!(!diff.invalidated(addr + PAGE));
+    assert!(!diff.unreadable(addr + PAGE));
+
+    // 拆分出的后一块变得不可读
+    unsafe { libc::mprotect((addr + 2 * PAGE) as *mut libc::c_void, PAGE, libc::PROT_NONE) };
+    let diff = process.refresh_maps().unwrap();
+    assert!(diff.perms_changed.iter().any(|(_, n)| !n.readable() && n.address.0 <= addr + 2 * PAGE && addr + 3 * PAGE <= n.address.1));
+    assert!(diff.unreadable(addr + 2 * PAGE + 8));
+    assert!(!diff.unreadable(addr + PAGE));
+    assert!(!diff.added.iter().any(|m| m.address.0 <= addr + 2 * PAGE && addr + 2 * PAGE < m.address.1));
+
+    unsafe { libc::munmap(addr as *mut libc::c_void, 3 * PAGE) };
+    let diff = process.refresh_maps().unwrap();
+    assert!(diff.removed.iter().any(|m| m.address.0 == addr + PAGE));
+    assert!(diff.invalidated(addr + PAGE));
+
+    let mut results = vec![addr, addr + PAGE + 8, process.maps[0].address.0];
+    diff.retain_valid(&mut results);
+    assert_eq!(results, vec![process.maps[0].address.0]);
+}
+
+#[test]
+fn split_pieces_compare_perms() {
+    let old = parse_maps("1000-4000 rw-p 00000000 00:00 0\n").unwrap();
+    let new = parse_maps("1000-3000 rw-p 00000000 00:00 0\n3000-4000 ---p 00000000 00:00 0\n").unwrap();
+    let diff = MapsDiff::new(&old, &new);
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+    assert_eq!(diff.shrunk.len(), 2);
+    assert_eq!(diff.perms_changed.len(), 1);
+    assert_eq!(diff.perms_changed[0].1.address, (0x3000, 0x4000));
+    assert!(diff.unreadable(0x3008));
+    assert!(!diff.unreadable(0x2008));
+    assert!(!diff.invalidated(0x3008));
+
+    // 合并回来时新区域只和起始地址相同的那块配对，另一块算移除，但地址仍然有效
+    let diff = MapsDiff::new(&new, &old);
+    assert_eq!(diff.grown.len(), 1);
+    assert_eq!(diff.grown[0].0.address, (0x1000, 0x3000));
+    assert!(diff.perms_changed.is_empty());
+    assert_eq!(diff.removed.iter().map(|m| m.address).collect::<Vec<_>>(), vec![(0x3000, 0x4000)]);
+    assert!(!diff.invalidated(0x3008));
+    assert!(!diff.unreadable(0x3008));
+}
+
+#[test]
+fn adjacent_anonymous_regions() {
+    // 相邻的匿名区域各自只和重叠的新区域配对，不会重复记录
+    let old = parse_maps("\
+1000-2000 rw-p 00000000 00:00 0
+2000-3000 rw-p 00000000 00:00 0
+3000-4000 rw-p 00000000 00:00 0
+").unwrap();
+    let new = parse_maps("\
+1000-2800 rw-p 00000000 00:00 0
+3000-4000 r--p 00000000 00:00 0
+5000-6000 rw-p 00000000 00:00 0
+").unwrap();
+    let diff = MapsDiff::new(&old, &new);
+    assert_eq!(diff.grown.len(), 1);
+    assert_eq!(diff.grown[0].0.address, (0x1000, 0x2000));
+    assert!(diff.shrunk.is_empty());
+    assert_eq!(diff.perms_changed.len(), 1);
+    assert_eq!(diff.perms_changed[0].0.address, (0x3000, 0x4000));
+    assert_eq!(diff.removed.iter().map(|m| m.address).collect::<Vec<_>>(), vec![(0x2000, 0x3000)]);
+    assert_eq!(diff.added.iter().map(|m| m.address).collect::<Vec<_>>(), vec![(0x5000, 0x6000)]);
+    assert!(!diff.invalidated(0x2008));
+    assert!(diff.invalidated(0x2808));
+}
@@ -0,0 +1,162 @@
+use mempoll::process::{parse_maps, MapRange, ProcessError};
+
+/// xorshift64*，测试只需要可重复的伪随机数
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+const PATHS: &[&str] = &[
+    "",
+    "[heap]",
+    "[stack]",
+    "[anon:stack_and_tls:1234]",
+    "/usr/lib/x86_64-linux-gnu/libc.so.6",
+    "/home/user/My Games/game data/level 1.pak",
+    "/data/app/~~abc==/com.example.game-1/lib/arm64/libil2cpp.so",
+    "/memfd:doublemapper",
+    "  leading spaces",
+    "[anon:dalvik-main space (region space)]",
+];
+
+struct Expected {
+    line: String,
+    address: (usize, usize),
+    offset: u64,
+    inode: u64,
+    dev: (u64, u64),
+    path: String,
+    deleted: bool,
+}
+
+fn random_line(rng: &mut Rng) -> Expected {
+    let start = (rng.below(1 << 47) as usize) & !0xfff;
+    let end = start + ((rng.below(1 << 20) as usize + 1) << 12);
+    let perms = format!("{}{}{}{}",
+        rng.pick(&["r", "-"]), rng.pick(&["w", "-"]), rng.pick(&["x", "-"]), rng.pick(&["p", "s"]));
+    let offset = rng.next() >> rng.below(64);
+    let dev = (rng.next() >> rng.below(64), rng.next() >> rng.below(64));
+    let inode = rng.next() >> rng.below(64);
+    let path = rng.pick(PATHS).to_string();
+    let deleted = !path.is_empty() && rng.below(4) == 0;
+
+    let mut line = format!("{:x}-{:x} {} {:08x} {:02x}:{:02x} {}", start, end, perms, offset, dev.0, dev.1, inode);
+    if !path.is_empty() {
+        line.push_str(&" ".repeat(rng.below(30) as usize + 1));
+        line.push_str(&path);
+    }
+    if deleted {
+        line.push_str(" (deleted)");
+    }
+    Expected { line, address: (start, end), offset, inode, dev, path, deleted }
+}
+
+#[test]
+fn roundtrip_random_lines() {
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    for _ in 0..5000 {
+        let Expected { line, address, offset, inode, dev, path, deleted } = random_line(&mut rng);
+        let map = MapRange::parse(&line, 1).unwrap_or_else(|e| panic!("{line:?}: {e:?}"));
+        assert_eq!(map.address, address, "{line}");
+        assert_eq!(map.offset as u64, offset, "{line}");
+        assert_eq!(map.inode, inode, "{line}");
+        assert_eq!(map.dev, dev, "{line}");
+        assert_eq!(map.pathname, path.trim_start_matches(' '), "{line}");
+        assert_eq!(map.deleted, deleted, "{line}");
+
+        // Display 输出可以再解析回来
+        let again = MapRange::parse(&map.to_string(), 1).unwrap();
+        assert_eq!(again.address, map.address);
+        assert_eq!(again.pathname, map.pathname);
+        assert_eq!(again.shared(), map.shared());
+        assert_eq!(again.executable(), map.executable());
+    }
+}
+
+#[test]
+fn mutated_input_never_panics() {
+    let mut rng = Rng(0xDEADBEEF);
+    let noise = b"0123456789abcdefxyz-: \t()\xff\n";
+    for _ in 0..20000 {
+        let mut lines = (0..rng.below(4) + 1).map(|_| random_line(&mut rng).line.into_bytes()).collect::<Vec<_>>();
+        let bad = rng.below(lines.len() as u64) as usize;
+        let target = &mut lines[bad];
+        for _ in 0..rng.below(4) + 1 {
+            match rng.below(3) {
+                0 if !target.is_empty() => {
+                    let i = rng.below(target.len() as u64) as usize;
+                    target[i] = noise[rng.below(noise.len() as u64) as usize];
+                },
+                1 => {
+                    let len = rng.below(target.len() as u64 + 1) as usize;
+                    target.truncate(len);
+                },
+                _ => {
+                    let i = rng.below(target.len() as u64 + 1) as usize;
+                    target.insert(i, noise[rng.below(noise.len() as u64) as usize]);
+                },
+            }
+        }
+        let text = String::from_utf8_lossy(&lines.join(&b'\n')).into_owned();
+        match parse_maps(&text) {
+            Ok(maps) => assert!(maps.len() <= text.lines().count()),
+            Err(ProcessError::MapParseError { line, .. }) => assert!(line >= 1 && line <= text.lines().count()),
+            Err(e) => panic!("unexpected error {e:?}"),
+        }
+    }
+}
+
+#[test]
+fn malformed_lines_report_line_number() {
+    let good = "00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon";
+    let cases = [
+        "00400000 r-xp 00000000 08:02 173521",
+        "00400000-0045200g r-xp 00000000 08:02 173521",
+        "00452000-00400000 r-xp 00000000 08:02 173521",
+        "00400000-00452000 rwxq 00000000 08:02 173521",
+        "00400000-00452000 r-x 00000000 08:02 173521",
+        "00400000-00452000 r-xp zz 08:02 173521",
+        "00400000-00452000 r-xp 00000000 0802 173521",
+        "00400000-00452000 r-xp 00000000 08:02 -1",
+        "00400000-00452000 r-xp 00000000 08:02",
+        "",
+    ];
+    for case in cases {
+        let text = format!("{good}\n{good}\n{case}x\n");
+        match parse_maps(&text) {
+            Err(ProcessError::MapParseError { line, .. }) => assert_eq!(line, 3, "{case:?}"),
+            other => panic!("{case:?}: {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn kernel_formats() {
+    // minor 是十六进制，inode 超过 u32
+    let map = MapRange::parse("7f1c2a000000-7f1c2a021000 rw-s 00001000 fd:1a 18446744073709551615 /dev/shm/game state (deleted)", 1).unwrap();
+    assert_eq!(map.dev, (0xfd, 0x1a));
+    assert_eq!(map.inode, u64::MAX);
+    assert_eq!(map.pathname, "/dev/shm/game state");
+    assert!(map.deleted);
+    assert!(map.shared());
+    assert!(!map.executable());
+
+    let map = MapRange::parse("7ffd5e3c1000-7ffd5e3e2000 rw-p 00000000 00:00 0                          [stack]", 1).unwrap();
+    assert_eq!(map.pathname, "[stack]");
+    assert!(!map.deleted);
+    assert!(!map.shared());
+}
@@ -0,0 +1,79 @@
+use mempoll::memory::{from_bytes, mock_memory::MockMemory, MemoryReader, MemoryWriter, Pod, Zeroable};
+use mempoll::process::permissions;
+use mempoll::searcher::{MemorySearcher, SearchType};
+
+fn mock() -> MockMemory {
+    let mut mem = MockMemory::new();
+    let mut data = vec![0u8; 0x100];
+    data[0x10..0x14].copy_from_slice(&1234u32.to_ne_bytes());
+    mem.add_rw(0x1000, data)
+        .add_rw(0x1100, vec![0xAA; 0x100])
+        .add_region(0x3000, 1234u32.to_ne_bytes().repeat(4), permissions::READABLE, "/lib/libgame.so")
+        .add_region(0x4000, vec![0; 0x10], 0, "");
+    mem
+}
+
+#[test]
+fn read_write() {
+    let mem = mock();
+    assert_eq!(mem.read::<u32>(0x1010).unwrap(), 1234);
+
+    mem.write(0x1010, &5678u32).unwrap();
+    assert_eq!(mem.read::<u32>(0x1010).unwrap(), 5678);
+
+    // 相邻区域连续读写
+    mem.writebuf(0x10fe, &[1, 2, 3, 4]).unwrap();
+    let mut buf = [0u8; 6];
+    assert_eq!(mem.readbuf(0x10fd, &mut buf).unwrap(), 6);
+    assert_eq!(buf, [0, 1, 2, 3, 4, 0xAA]);
+
+    // 越过末尾只返回已读部分
+    let mut buf = [0u8; 8];
+    assert_eq!(mem.readbuf(0x11fc, &mut buf).unwrap(), 4);
+}
+
+#[test]
+fn permissions() {
+    let mem = mock();
+    assert!(mem.write(0x3000, &1u32).is_err());
+    assert!(mem.read::<u32>(0x4000).is_err());
+    assert!(mem.read::<u32>(0x2000).is_err());
+}
+
+#[test]
+fn search() {
+    let mem = mock();
+    let res = mem.search::<SearchType<u32>, 64>(SearchType::Eq(1234), None::<fn(&_) -> bool>).unwrap();
+    assert_eq!(res, vec![0x1010, 0x3000, 0x3004, 0x3008, 0x300c]);
+
+    let res = mem.search::<SearchType<u32>, 64>(SearchType::Eq(1234), Some(|m: &mempoll::process::MapRange| m.writable())).unwrap();
+    assert_eq!(res, vec![0x1010]);
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct Entity {
+    id: u32,
+    hp: f32,
+    pos: [f32; 2],
+}
+
+#[test]
+fn pod_values() {
+    let mem = mock();
+    let entity = Entity { id: 7, hp: 99.5, pos: [1.0, -2.0] };
+
+    // 不对齐的地址
+    mem.write(0x1021, &entity).unwrap();
+    assert_eq!(mem.read::<Entity>(0x1021).unwrap(), entity);
+
+    mem.write_array(0x1043, &[0x1111u16, 0x2222, 0x3333]).unwrap();
+    assert_eq!(mem.read_array::<u16>(0x1043, 3).unwrap(), vec![0x1111, 0x2222, 0x3333]);
+    // 跨越相邻区域
+    assert_eq!(mem.read_array::<u32>(0x10f8, 4).unwrap(), vec![0, 0, 0xAAAA_AAAA, 0xAAAA_AAAA]);
+    assert!(mem.read_array::<u32>(0x11f8, 4).is_err());
+
+    let bytes = [0xffu8, 0x78, 0x56, 0x34, 0x12];
+    assert_eq!(from_bytes::<u32>(&bytes[1..]), Some(0x1234_5678));
+    assert_eq!(from_bytes::<u64>(&bytes), None);
+}
@@ -0,0 +1,94 @@
+use mempoll::process::{module::modules_from_maps, parse_maps, Process};
+
+const MAPS: &str = "\
+55d1c0800000-55d1c0801000 r--p 00000000 08:01 100     /home/user/game/bin/game
+55d1c0801000-55d1c0900000 r-xp 00001000 08:01 100     /home/user/game/bin/game
+55d1c0900000-55d1c0910000 rw-p 00100000 08:01 100     /home/user/game/bin/game
+55d1c0910000-55d1c0920000 rw-p 00000000 00:00 0
+55d1c0a00000-55d1c0a21000 rw-p 00000000 00:00 0       [heap]
+7f0010000000-7f0010028000 r--p 00000000 08:01 200     /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0010028000-7f00101bd000 r-xp 00028000 08:01 200     /usr/lib/x86_64-linux-gnu/libc.so.6
+7f00101bd000-7f0010215000 r--p 001bd000 08:01 200     /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0010215000-7f0010219000 r--p 00214000 08:01 200     /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0010219000-7f001021b000 rw-p 00218000 08:01 200     /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0020000000-7f0020001000 r--p 00000000 08:01 300     /home/user/game/libplugin.so
+7f0020001000-7f0020002000 r--p 00000000 08:01 300     /home/user/game/libplugin.so
+";
+
+#[test]
+fn group_segments() {
+    let maps = parse_maps(MAPS).unwrap();
+    let modules = modules_from_maps(&maps);
+    assert_eq!(modules.len(), 4);
+
+    let game = &modules[0];
+    assert_eq!(game.name, "game");
+    assert_eq!(game.base, 0x55d1c0800000);
+    assert_eq!(game.segments.len(), 3);
+    assert_eq!(game.bss.as_ref().unwrap().address, (0x55d1c0910000, 0x55d1c0920000));
+    assert_eq!(game.end(), 0x55d1c0920000);
+    assert_eq!(game.code().count(), 1);
+    assert!(game.segment_at(0x55d1c0915000).unwrap().writable());
+
+    let libc = &modules[1];
+    assert_eq!(libc.name, "libc.so.6");
+    assert_eq!(libc.segments.len(), 5);
+    assert!(libc.bss.is_none());
+    assert_eq!(libc.size, 0x21b000);
+    assert_eq!(libc.offset_of(0x7f0010028010), Some(0x28010));
+    assert_eq!(libc.address_of(0x28010), 0x7f0010028010);
+
+    // offset 0 再次出现视为另一次加载
+    assert_eq!(modules[2].name, "libplugin.so");
+    assert_eq!(modules[3].base, 0x7f0020001000);
+}
+
+#[test]
+fn skip_gaps_between_segments() {
+    // 64K 对齐的库，段之间是 ---p 的匿名区域
+    let maps = parse_maps("\
+7f0030000000-7f0030010000 r--p 00000000 08:01 400     /system/lib64/libfoo.so
+7f0030010000-7f0030020000 ---p 00000000 00:00 0
+7f0030020000-7f0030030000 r-xp 00010000 08:01 400     /system/lib64/libfoo.so
+7f0030030000-7f0030040000 ---p 00000000 00:00 0
+7f0030040000-7f0030050000 rw-p 00020000 08:01 400     /system/lib64/libfoo.so
+7f0030050000-7f0030060000 rw-p 00000000 00:00 0
+7f0030060000-7f0030070000 ---p 00000000 00:00 0
+7f0030070000-7f0030080000 r--p 00000000 08:01 400     /system/lib64/libfoo.so
+7f0040000000-7f0040001000 r--p 00000000 08:01 500     /usr/lib/libcrypto.so.3
+7f0040100000-7f0040101000 r--p 00000000 08:01 200     /usr/lib/libc.so.6
+").unwrap();
+    let modules = modules_from_maps(&maps);
+    assert_eq!(modules.len(), 4);
+    let foo = &modules[0];
+    assert_eq!(foo.segments.len(), 3);
+    assert_eq!(foo.base, 0x7f0030000000);
+    assert_eq!(foo.end(), 0x7f0030060000);
+    assert!(foo.segment_at(0x7f0030025000).unwrap().executable());
+    assert!(foo.segment_at(0x7f0030015000).is_none());
+    assert_eq!(modules[1].base, 0x7f0030070000);
+
+    let crypto = &modules[2];
+    let libc = &modules[3];
+    assert!(!crypto.matches("libc") && !crypto.matches("libc.so"));
+    assert!(crypto.matches("libcrypto") && crypto.matches("libcrypto.so") && crypto.matches("libcrypto.so.3"));
+    assert!(libc.matches("libc") && libc.matches("libc.so") && libc.matches("/usr/lib/libc.so.6"));
+    assert!(!libc.matches("libc.so.") && !libc.matches("lib"));
+}
+
+#[test]
+fn lookup_in_own_process() {
+    let mut process = Process::new(std::process::id());
+    process.maps().unwrap();
+
+    let func = group_segments as *const () as usize;
+    let module = process.module_at(func).unwrap();
+    let exe = std::env::current_exe().unwrap();
+    assert_eq!(module.path, exe.to_string_lossy());
+    assert!(module.segment_at(func).unwrap().executable());
+
+    let by_name = process.module_by_name(&module.name).unwrap();
+    assert_eq!(by_name.base, module.base);
+    assert!(process.module_by_name("libc.so").is_some());
+    assert!(process.module_by_name("no-such-module.so").is_none());
+}
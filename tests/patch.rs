@@ -0,0 +1,148 @@
+mod common;
+
+use common::{bump, Target};
+use mempoll::memory::force_memory::ForceMemory;
+use mempoll::memory::mock_memory::MockMemory;
+use mempoll::memory::{MemoryReader, MemoryWriter};
+use mempoll::patch::{Patch, PatchCode, PatchError, PatchLocation, PatchManager, Signature};
+use mempoll::process::{permissions, Process};
+
+const BASE: usize = 0x10000;
+
+/// 一个可读写执行的假模块，0x20 和 0x40 处的代码只有立即数不同
+fn mock() -> MockMemory {
+    let mut code = vec![0xccu8; 0x100];
+    code[0x20..0x26].copy_from_slice(&[0x48, 0x8b, 0x05, 0x11, 0x22, 0xc3]);
+    code[0x40..0x46].copy_from_slice(&[0x48, 0x8b, 0x05, 0x33, 0x44, 0xc3]);
+    let mut mem = MockMemory::new();
+    let perms = permissions::READABLE | permissions::WRITABLTE | permissions::EXECUTABLE;
+    mem.add_region(BASE, code, perms, "/lib/libgame.so");
+    mem
+}
+
+fn bytes(mem: &MockMemory, address: usize, len: usize) -> Vec<u8> {
+    let mut buf = vec![0; len];
+    mem.read_exact(address, &mut buf).unwrap();
+    buf
+}
+
+fn sig(s: &str) -> Signature {
+    s.parse().unwrap()
+}
+
+#[test]
+fn parse_signature() {
+    assert_eq!(sig("48 8B ?? ? c3"), Signature(vec![Some(0x48), Some(0x8b), None, None, Some(0xc3)]));
+    assert!(matches!("48 zz".parse::<Signature>(), Err(PatchError::InvalidSignature(_))));
+    assert!("".parse::<Signature>().is_err());
+
+    let s = sig("8b ?? c3");
+    assert!(s.matches(&[0x8b, 0x00, 0xc3, 0xff]));
+    assert!(!s.matches(&[0x8b, 0x00]));
+    assert_eq!(s.find(&[0x8b, 1, 0xc3, 0x8b, 2, 0xc3]).collect::<Vec<_>>(), vec![0, 3]);
+}
+
+#[test]
+fn locate_patches() {
+    let mem = mock();
+    let pm = PatchManager::new(&mem, &mem.process);
+    let module = |offset| PatchLocation::ModuleOffset { module: "libgame.so".to_string(), offset };
+    assert_eq!(pm.resolve(&module(0x20)).unwrap(), BASE + 0x20);
+
+    let by_sig = |s: &str, offset| PatchLocation::Signature { module: "libgame".to_string(), signature: sig(s), offset };
+    assert_eq!(pm.resolve(&by_sig("48 8b 05 33", 3)).unwrap(), BASE + 0x43);
+    assert!(matches!(pm.resolve(&by_sig("48 8b 05 ?? ?? c3", 0)), Err(PatchError::Ambiguous(_))));
+    assert!(matches!(pm.resolve(&by_sig("48 8b 06", 0)), Err(PatchError::NotFound(_))));
+    let other = PatchLocation::ModuleOffset { module: "libother.so".to_string(), offset: 0 };
+    assert!(matches!(pm.resolve(&other), Err(PatchError::NotFound(_))));
+}
+
+#[test]
+fn toggle_and_revert() {
+    let mem = mock();
+    let mut pm = PatchManager::new(&mem, &mem.process);
+    let loc = |offset| PatchLocation::ModuleOffset { module: "libgame.so".to_string(), offset };
+
+    // 预期字节不符时拒绝
+    let bad = Patch::new("bad", loc(0x20), PatchCode::Nop(2)).expect(sig("48 8b 06"));
+    assert!(matches!(pm.add(bad), Err(PatchError::UnexpectedBytes { address, .. }) if address == BASE + 0x20));
+    assert!(pm.get("bad").is_none());
+
+    let nop = Patch::new("nop", loc(0x20), PatchCode::Nop(6)).expect(sig("48 8b 05 ?? ?? c3"));
+    assert_eq!(pm.add(nop).unwrap(), BASE + 0x20);
+    // add 只记录，不写入
+    assert_eq!(bytes(&mem, BASE + 0x20, 6), vec![0x48, 0x8b, 0x05, 0x11, 0x22, 0xc3]);
+    assert!(matches!(pm.add(Patch::new("nop", loc(0), PatchCode::Nop(1))), Err(PatchError::DuplicatePatch(_))));
+
+    assert!(pm.toggle("nop").unwrap());
+    assert_eq!(bytes(&mem, BASE + 0x20, 6), vec![0x90; 6]);
+    assert!(!pm.toggle("nop").unwrap());
+    assert_eq!(bytes(&mem, BASE + 0x20, 6), pm.get("nop").unwrap().original);
+
+    pm.apply(Patch::new("ret", loc(0x40), PatchCode::Bytes(vec![0xc3]))).unwrap();
+    pm.apply(Patch::new("jmp", loc(0x80), PatchCode::Jump(BASE + 0x40))).unwrap();
+    pm.enable("nop").unwrap();
+    assert_eq!(bytes(&mem, BASE + 0x40, 1), vec![0xc3]);
+    #[cfg(target_arch = "x86_64")]
+    assert_eq!(bytes(&mem, BASE + 0x80, 5), vec![0xe9, 0xbb, 0xff, 0xff, 0xff]);
+
+    // 被别人改过的补丁不会被覆盖
+    mem.write(BASE + 0x40, &0xccu8).unwrap();
+    assert!(matches!(pm.revert_all(), Err(PatchError::UnexpectedBytes { name, .. }) if name == "ret"));
+    assert_eq!(bytes(&mem, BASE + 0x20, 6), vec![0x48, 0x8b, 0x05, 0x11, 0x22, 0xc3]);
+    assert_eq!(bytes(&mem, BASE + 0x80, 5), vec![0xcc; 5]);
+    assert!(pm.iter().filter(|p| p.enabled).map(|p| p.patch.name.as_str()).eq(["ret"]));
+
+    mem.write(BASE + 0x40, &0xc3u8).unwrap();
+    pm.revert_all().unwrap();
+    assert_eq!(bytes(&mem, BASE + 0x40, 1), vec![0x48]);
+    assert!(matches!(pm.enable("missing"), Err(PatchError::UnknownPatch(_))));
+}
+
+#[test]
+fn overlap_and_short_write() {
+    let mut mem = mock();
+    // 紧跟着一段不可写的代码
+    mem.add_region(BASE + 0x100, vec![0xcc; 0x100], permissions::READABLE | permissions::EXECUTABLE, "/lib/libgame.so");
+    let mut pm = PatchManager::new(&mem, &mem.process);
+    let loc = |offset| PatchLocation::ModuleOffset { module: "libgame.so".to_string(), offset };
+
+    pm.add(Patch::new("nop", loc(0x20), PatchCode::Nop(6))).unwrap();
+    let err = pm.add(Patch::new("ret", loc(0x25), PatchCode::Bytes(vec![0xc3; 2]))).unwrap_err();
+    assert!(matches!(err, PatchError::Overlap { ref other, .. } if other == "nop"), "{err:?}");
+    pm.add(Patch::new("ret", loc(0x26), PatchCode::Bytes(vec![0xc3; 2]))).unwrap();
+    // 地址加上长度溢出
+    let wrap = Patch::new("wrap", PatchLocation::Address(usize::MAX - 2), PatchCode::Bytes(vec![0x90; 6]));
+    assert!(matches!(pm.add(wrap), Err(PatchError::InvalidCode(_))));
+
+    // 写到一半失败时写回原始字节
+    pm.add(Patch::new("cross", loc(0xfc), PatchCode::Nop(8))).unwrap();
+    assert!(matches!(pm.enable("cross"), Err(PatchError::MemoryError(_))));
+    assert!(!pm.get("cross").unwrap().enabled);
+    assert_eq!(bytes(&mem, BASE + 0xfc, 8), vec![0xcc; 8]);
+}
+
+#[test]
+fn patch_live_code() {
+    let target = Target::spawn();
+    let mem = ForceMemory::new(target.pid);
+    let mut process = Process::new(target.pid);
+    process.maps().unwrap();
+
+    let address = bump as *const () as usize;
+    let module = process.module_at(address).unwrap();
+    let mut original = vec![0; 4];
+    mem.read_exact(address, &mut original).unwrap();
+
+    let mut pm = PatchManager::new(&mem, &process);
+    let location = PatchLocation::ModuleOffset { module: module.name.clone(), offset: address - module.base };
+    let patch = Patch::new("bump", location, PatchCode::Bytes(vec![0xc3, 0x90, 0x90, 0x90]))
+        .expect(Signature::from(original.as_slice()));
+    assert_eq!(pm.apply(patch).unwrap(), address);
+    assert_eq!(mem.read::<u32>(address).unwrap(), u32::from_ne_bytes([0xc3, 0x90, 0x90, 0x90]));
+
+    pm.revert_all().unwrap();
+    let mut restored = vec![0; 4];
+    mem.read_exact(address, &mut restored).unwrap();
+    assert_eq!(restored, original);
+}
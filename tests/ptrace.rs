@@ -0,0 +1,116 @@
+mod common;
+
+use common::{Target, MAGIC};
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::memory::{MemoryReader, MemoryWriter};
+
+/// /proc/pid/stat 中的状态字符
+fn proc_state(pid: u32) -> char {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+    stat[stat.rfind(')').unwrap() + 2..].chars().next().unwrap()
+}
+
+fn tracer_pid(pid: u32) -> u32 {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).unwrap();
+    let line = status.lines().find(|l| l.starts_with("TracerPid:")).unwrap();
+    line.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+#[test]
+fn seize_leaves_target_running() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+    assert!(!mem.is_attached());
+
+    // 多次读取不会重复附加
+    for _ in 0..3 {
+        assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+    }
+    assert!(mem.is_attached());
+    assert_eq!(mem.thread_states(), vec![(target.pid, TraceState::Running)]);
+    assert_ne!(proc_state(target.pid), 't');
+    assert_eq!(tracer_pid(target.pid), nix::unistd::gettid().as_raw() as u32);
+}
+
+#[test]
+fn stop_world_and_resume() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+
+    mem.stop_world().unwrap();
+    assert_eq!(mem.state(target.pid), Some(TraceState::Stopped { signal: None }));
+    assert_eq!(proc_state(target.pid), 't');
+
+    // 批量操作期间保持停止
+    mem.write(target.known + 8, &1u32).unwrap();
+    assert_eq!(mem.read::<u32>(target.known + 8).unwrap(), 1);
+    assert_eq!(mem.state(target.pid), Some(TraceState::Stopped { signal: None }));
+
+    mem.resume().unwrap();
+    assert_eq!(mem.state(target.pid), Some(TraceState::Running));
+    assert_ne!(proc_state(target.pid), 't');
+
+    let counter = mem.stopped(|m| m.read::<u32>(target.known + 8)).unwrap();
+    assert_eq!(counter, 1);
+    assert_eq!(mem.state(target.pid), Some(TraceState::Running));
+}
+
+#[test]
+fn read_while_stopped_keeps_state() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+
+    mem.stop_world().unwrap();
+    mem.set_signal(target.pid, Some(nix::sys::signal::Signal::SIGCONT));
+    let stopped = TraceState::Stopped { signal: Some(nix::sys::signal::Signal::SIGCONT) };
+
+    // 已经停住时读写不再 interrupt，截获的信号保持不变
+    for _ in 0..3 {
+        assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+        mem.write(target.known + 8, &2u32).unwrap();
+        assert_eq!(mem.state(target.pid), Some(stopped));
+        assert_eq!(proc_state(target.pid), 't');
+    }
+    mem.set_signal(target.pid, None);
+    mem.resume().unwrap();
+    assert_eq!(mem.read::<u32>(target.known + 8).unwrap(), 2);
+    assert_eq!(mem.state(target.pid), Some(TraceState::Running));
+}
+
+#[test]
+fn detach_on_drop() {
+    let target = Target::spawn();
+    {
+        let mem = PtraceMemory::new(target.pid);
+        mem.stop_world().unwrap();
+        assert_eq!(tracer_pid(target.pid), nix::unistd::gettid().as_raw() as u32);
+    }
+    assert_eq!(tracer_pid(target.pid), 0);
+    assert_ne!(proc_state(target.pid), 't');
+
+    // 可以再次附加
+    let mem = PtraceMemory::new(target.pid);
+    assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+    mem.dettach().unwrap();
+    assert!(!mem.is_attached());
+    assert_eq!(tracer_pid(target.pid), 0);
+}
+
+#[test]
+fn signal_between_operations() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+    assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+
+    // 没人 wait 时线程停在信号投递处，SIGWINCH 默认忽略，投递后目标继续运行
+    for _ in 0..2 {
+        nix::sys::signal::kill(nix::unistd::Pid::from_raw(target.pid as i32), nix::sys::signal::Signal::SIGWINCH).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(proc_state(target.pid), 't');
+
+        assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+        assert_eq!(mem.state(target.pid), Some(TraceState::Running));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_ne!(proc_state(target.pid), 't');
+    }
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::Target;
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::registers::{self, GeneralRegisters, X86_64Regs};
+
+#[test]
+fn read_general_registers() {
+    let target = Target::spawn();
+    let mut mem = PtraceMemory::new(target.pid);
+    mem.process.maps().unwrap();
+    // 停住以保证两次读取之间寄存器不变
+    mem.stop_world().unwrap();
+
+    let regs = mem.regs(target.pid).unwrap();
+    let maps = &mem.process.maps;
+    let pc = regs.pc() as usize;
+    let sp = regs.sp() as usize;
+    assert!(maps.iter().any(|m| m.executable() && m.address.0 <= pc && pc < m.address.1), "pc {pc:#x}");
+    // 测试线程 fork 出的子进程栈不一定是 [stack]
+    assert!(maps.iter().any(|m| m.writable() && m.address.0 <= sp && sp < m.address.1), "sp {sp:#x}");
+
+    let roots = mem.register_roots().unwrap();
+    assert!(roots.iter().any(|r| r.tid == target.pid && r.value == sp));
+    assert!(roots.iter().all(|r| maps.iter().any(|m| m.address.0 <= r.value && r.value < m.address.1)));
+
+    let status = mem.thread_status().unwrap();
+    assert_eq!(status[0].regs.sp(), regs.sp());
+    mem.resume().unwrap();
+
+    // 运行中的线程读取后恢复运行
+    mem.regs(target.pid).unwrap();
+    assert!(mem.thread_states().iter().all(|(_, s)| *s == TraceState::Running));
+}
+
+#[test]
+fn write_registers() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+    mem.stop_world().unwrap();
+
+    let orig = mem.regs(target.pid).unwrap();
+    let mut regs = orig;
+    let sp = regs.sp();
+    regs.set_sp(sp - 0x100);
+    mem.set_regs(target.pid, &regs).unwrap();
+    assert_eq!(mem.regs(target.pid).unwrap().sp(), sp - 0x100);
+    mem.set_regs(target.pid, &orig).unwrap();
+    assert_eq!(mem.regs(target.pid).unwrap(), orig);
+
+    let fp = mem.fp_regs(target.pid).unwrap();
+    let mut changed = fp;
+    #[cfg(target_arch = "x86_64")]
+    {
+        assert_eq!(fp.mxcsr & 0xffc0, 0x1f80);
+        changed.set_xmm(15, 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+        mem.set_fp_regs(target.pid, &changed).unwrap();
+        assert_eq!(mem.fp_regs(target.pid).unwrap().xmm(15), 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        changed.vregs[31] = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210;
+        mem.set_fp_regs(target.pid, &changed).unwrap();
+        assert_eq!(mem.fp_regs(target.pid).unwrap().vregs[31], changed.vregs[31]);
+    }
+    mem.set_fp_regs(target.pid, &fp).unwrap();
+    mem.resume().unwrap();
+}
+
+#[test]
+fn roots_filter_unmapped_values() {
+    let mut mem = mempoll::memory::mock_memory::MockMemory::new();
+    mem.add_rw(0x1000, vec![0; 0x100]);
+    let regs = X86_64Regs { rax: 0x1010, rbx: 0x5000, rsp: 0x10ff, ..Default::default() };
+    let roots = registers::roots(7, &regs, &mem.process.maps);
+    let names: Vec<&str> = roots.iter().map(|r| r.register).collect();
+    assert_eq!(names, vec!["rax", "rsp"]);
+    assert!(roots.iter().all(|r| r.tid == 7));
+}
+
+#[test]
+fn syscall_arguments() {
+    let mut regs = X86_64Regs { orig_rax: 35, ..Default::default() };
+    regs.set_syscall(9, &[1, 2, 3, 4, 5, 6]);
+    assert_eq!((regs.rax, regs.orig_rax), (9, u64::MAX));
+    assert_eq!([regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9], [1, 2, 3, 4, 5, 6]);
+    regs.rax = -22i64 as u64;
+    assert_eq!(regs.syscall_result(), -22);
+}
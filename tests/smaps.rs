@@ -0,0 +1,71 @@
+use mempoll::memory::{proc_memory::ProcMemory, MemoryReader};
+use mempoll::process::{smaps::parse_smaps, MapRange, Process};
+use mempoll::searcher::{MemorySearcher, SearchType};
+use nix::libc;
+
+const SAMPLE: &str = "\
+55d1c0a00000-55d1c0a21000 rw-p 00000000 00:00 0                          [heap]
+Size:                132 kB
+KernelPageSize:        4 kB
+Rss:                   8 kB
+Pss:                   6 kB
+Shared_Clean:          0 kB
+Shared_Dirty:          0 kB
+Private_Clean:         0 kB
+Private_Dirty:         8 kB
+Referenced:            8 kB
+Anonymous:             8 kB
+Swap:                  4 kB
+SwapPss:               4 kB
+THPeligible:    0
+VmFlags: rd wr mr mw me ac sd
+7f0000000000-7f0000001000 r--p 00000000 08:01 1234                       /usr/lib/libc.so.6
+Size:                  4 kB
+Rss:                   4 kB
+Private_Dirty:         0 kB
+VmFlags: rd mr mw me sd
+";
+
+#[test]
+fn parse_sample() {
+    let regions = parse_smaps(SAMPLE).unwrap();
+    assert_eq!(regions.len(), 2);
+    let (start, end, heap) = &regions[0];
+    assert_eq!((*start, *end), (0x55d1c0a00000, 0x55d1c0a21000));
+    assert_eq!(heap.size, 132 * 1024);
+    assert_eq!(heap.pss, 6 * 1024);
+    assert_eq!(heap.swap, 4 * 1024);
+    assert!(heap.is_dirty());
+    assert!(heap.has_flag("wr"));
+    assert!(!regions[1].2.is_dirty());
+
+    assert!(parse_smaps("Rss: 4 kB\n").is_err());
+}
+
+#[test]
+fn dirty_filter() {
+    let page = 4096;
+    let addr = unsafe {
+        libc::mmap(std::ptr::null_mut(), 4 * page, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)
+    } as usize;
+    unsafe { *(addr as *mut u64) = 0x5A5A_1234_0000_9999 };
+
+    let mut process = Process::new(std::process::id());
+    process.smaps().unwrap();
+    let region = process.maps.iter().find(|m| m.address.0 <= addr && addr < m.address.1).unwrap();
+    assert!(region.stats.as_ref().unwrap().private_dirty >= page);
+
+    let mut mem = ProcMemory::new(std::process::id());
+    mem.open().unwrap();
+    mem.process = process;
+    assert_eq!(mem.read::<u64>(addr).unwrap(), 0x5A5A_1234_0000_9999);
+    let res = mem.search::<SearchType<u64>, 4096>(
+        SearchType::Eq(0x5A5A_1234_0000_9999),
+        Some(|m: &MapRange| m.address.0 <= addr && addr < m.address.1 && m.stats.as_ref().is_some_and(|s| s.is_dirty()))
+    ).unwrap();
+    assert!(res.contains(&addr));
+
+    let rollup = mem.process.smaps_rollup().unwrap();
+    assert!(rollup.rss > 0);
+    unsafe { libc::munmap(addr as *mut libc::c_void, 4 * page) };
+}
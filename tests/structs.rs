@@ -0,0 +1,149 @@
+use mempoll::memory::{mock_memory::MockMemory, MemoryReader};
+use mempoll::structs::{FieldType, Primitive, StructError, StructRegistry, Value};
+
+const DEFS: &str = "
+# 测试用定义
+struct Vec3 {
+    x: f32
+    y: f32
+    z: f32
+}
+
+struct Player size 0x40 {
+    0x00 hp: i32
+    0x04 alive: bool
+    0x08 pos: Vec3
+    0x14 items: [u16; 4]
+    0x20 target: *Player
+    0x28 data: *void
+}
+";
+
+fn player(hp: i32, target: usize) -> Vec<u8> {
+    let mut b = vec![0u8; 0x40];
+    b[0..4].copy_from_slice(&hp.to_ne_bytes());
+    b[4] = 1;
+    for (i, v) in [1.0f32, 2.0, 3.5].iter().enumerate() {
+        b[8 + i * 4..12 + i * 4].copy_from_slice(&v.to_ne_bytes());
+    }
+    for i in 0..4u16 {
+        b[0x14 + i as usize * 2..0x16 + i as usize * 2].copy_from_slice(&(i * 10).to_ne_bytes());
+    }
+    b[0x20..0x28].copy_from_slice(&target.to_ne_bytes());
+    b
+}
+
+fn mock() -> MockMemory {
+    let mut mem = MockMemory::new();
+    let mut data = player(100, 0x1040);
+    data.extend(player(50, 0x1000));
+    mem.add_rw(0x1000, data);
+    mem
+}
+
+#[test]
+fn parse_definitions() {
+    let reg = StructRegistry::parse(DEFS).unwrap();
+    assert_eq!(reg.struct_size("Vec3").unwrap(), 12);
+    assert_eq!(reg.struct_size("Player").unwrap(), 0x40);
+    let player = reg.get("Player").unwrap();
+    assert_eq!(player.fields.len(), 6);
+    assert_eq!(player.fields[3].ty, FieldType::Array(Box::new(FieldType::Primitive(Primitive::U16)), 4));
+    assert_eq!(reg.get("Vec3").unwrap().fields[2].offset, 8);
+
+    assert_eq!(reg.field_offset("Player", "pos.z").unwrap().0, 0x10);
+    assert_eq!(reg.field_offset("Player", "items[3]").unwrap().0, 0x1a);
+    assert!(reg.field_offset("Player", "items[4]").is_err());
+    assert!(reg.field_offset("Player", "mana").is_err());
+}
+
+#[test]
+fn parse_errors() {
+    let err = StructRegistry::parse("struct A {\n  x: [u32]\n}").unwrap_err();
+    assert!(matches!(err, StructError::ParseError { line: 2, .. }), "{err:?}");
+    let err = StructRegistry::parse("struct A {\n  x: u33\n}").unwrap_err();
+    assert!(matches!(err, StructError::UnknownType(ref t) if t == "u33"), "{err:?}");
+    let err = StructRegistry::parse("struct A {\n  x: u32\n").unwrap_err();
+    assert!(matches!(err, StructError::ParseError { .. }), "{err:?}");
+    let err = StructRegistry::parse("x: u32").unwrap_err();
+    assert!(matches!(err, StructError::ParseError { line: 1, .. }), "{err:?}");
+}
+
+#[test]
+fn recursive_and_forward_structs() {
+    // 直接或间接内嵌自身
+    let err = StructRegistry::parse("struct Node {\n  next: Node\n}").unwrap_err();
+    assert!(matches!(err, StructError::RecursiveType(_)), "{err:?}");
+    let err = StructRegistry::parse("struct A {\n  0 b: B\n}\nstruct B {\n  a: [A; 2]\n}").unwrap_err();
+    assert!(matches!(err, StructError::RecursiveType(_)), "{err:?}");
+    let err = StructRegistry::parse("struct A {\n  x: [[u64; 0x1000000000]; 0x1000000000]\n}").unwrap_err();
+    assert!(matches!(err, StructError::SizeOverflow(_)), "{err:?}");
+
+    // 指针和写明大小的结构体可以引用自身
+    let reg = StructRegistry::parse("struct Node {\n  next: *Node\n  value: u32\n}").unwrap();
+    assert_eq!(reg.struct_size("Node").unwrap(), 12);
+
+    // 后面定义的结构体大小未知，之后的字段必须写明偏移
+    let err = StructRegistry::parse("struct A {\n  v: Vec3\n  hp: u32\n}\nstruct Vec3 {\n  x: f32\n}").unwrap_err();
+    assert!(matches!(err, StructError::ParseError { line: 3, .. }), "{err:?}");
+    let reg = StructRegistry::parse("struct A {\n  v: Vec3\n  0x10 hp: u32\n}\nstruct Vec3 {\n  x: f32\n}").unwrap();
+    assert_eq!(reg.struct_size("A").unwrap(), 0x14);
+
+    // 大小为 0 的数组元素
+    let err = StructRegistry::parse("struct E {\n}\nstruct A {\n  0 e: [E; 0x7fffffffffffffff]\n}").unwrap_err();
+    assert!(matches!(err, StructError::TypeMismatch(_)), "{err:?}");
+}
+
+#[test]
+fn decode_size_limit() {
+    let mem = mock();
+    // 声明的大小超过限制时不分配
+    let reg = StructRegistry::parse("struct Big size 0x7fffffffffff {\n  x: u32\n}\nstruct Huge {\n  x: [u8; 0x7fffffffffff]\n}").unwrap();
+    let err = reg.decode(&mem, "Big", 0x1000, 0).unwrap_err();
+    assert!(matches!(err, StructError::SizeOverflow(_)), "{err:?}");
+    assert!(matches!(reg.decode(&mem, "Huge", 0x1000, 0), Err(StructError::SizeOverflow(_))));
+
+    let reg = StructRegistry::parse(DEFS).unwrap().decode_limit(0x10);
+    assert!(matches!(reg.decode(&mem, "Player", 0x1000, 0), Err(StructError::SizeOverflow(_))));
+    assert!(reg.decode(&mem, "Vec3", 0x1000, 0).is_ok());
+}
+
+#[test]
+fn decode_tree() {
+    let reg = StructRegistry::parse(DEFS).unwrap();
+    let mem = mock();
+
+    let v = reg.decode(&mem, "Player", 0x1000, 1).unwrap();
+    assert_eq!(v.get("hp"), Some(&Value::I32(100)));
+    assert_eq!(v.get("alive"), Some(&Value::Bool(true)));
+    assert_eq!(v.get("pos.z"), Some(&Value::F32(3.5)));
+    assert_eq!(v.get("items[2]"), Some(&Value::U16(20)));
+    assert_eq!(v.get("data"), Some(&Value::Pointer { address: 0, target: None }));
+
+    // 跟随一层指针
+    assert_eq!(v.get("target.hp"), Some(&Value::I32(50)));
+    // 第二层超过深度
+    let Some(Value::Pointer { target: Some(t), .. }) = v.get("target") else { panic!() };
+    assert_eq!(t.get("target"), Some(&Value::Pointer { address: 0x1000, target: None }));
+
+    assert!(v.to_string().contains("hp: 100"));
+    assert!(reg.decode(&mem, "Player", 0x9000, 1).is_err());
+    assert!(reg.decode(&mem, "Enemy", 0x1000, 1).is_err());
+}
+
+#[test]
+fn write_fields() {
+    let reg = StructRegistry::parse(DEFS).unwrap();
+    let mem = mock();
+
+    reg.write_field(&mem, "Player", 0x1000, "hp", &Value::I32(7)).unwrap();
+    reg.write_field(&mem, "Player", 0x1000, "pos.y", &Primitive::F32.parse_value("-4.25").unwrap()).unwrap();
+    reg.write_field(&mem, "Player", 0x1000, "items[1]", &Primitive::U16.parse_value("0xbeef").unwrap()).unwrap();
+    assert_eq!(mem.read::<i32>(0x1000).unwrap(), 7);
+    assert_eq!(mem.read::<f32>(0x100c).unwrap(), -4.25);
+    assert_eq!(mem.read::<u16>(0x1016).unwrap(), 0xbeef);
+
+    assert!(matches!(reg.write_field(&mem, "Player", 0x1000, "hp", &Value::U8(1)), Err(StructError::TypeMismatch(_))));
+    assert!(matches!(reg.write_field(&mem, "Player", 0x1000, "pos", &Value::I32(1)), Err(StructError::TypeMismatch(_))));
+    assert!(Primitive::U8.parse_value("256").is_err());
+}
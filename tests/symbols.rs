@@ -0,0 +1,102 @@
+use mempoll::memory::process_vm_memory::ProcessVmMemory;
+use mempoll::symbols::{Symbol, SymbolKind, SymbolTable, Symbolizer};
+use nix::libc;
+
+fn own_process() -> ProcessVmMemory {
+    let mut mem = ProcessVmMemory::new(std::process::id());
+    mem.process.maps().unwrap();
+    mem
+}
+
+#[test]
+fn resolve_libc_symbols() {
+    let mem = own_process();
+    let malloc = libc::malloc as *const () as usize;
+    let libc_module = mem.process.module_at(malloc).unwrap();
+
+    let from_file = SymbolTable::from_file(&libc_module).unwrap();
+    let from_memory = SymbolTable::from_memory(&libc_module, &mem).unwrap();
+    assert_eq!(from_file.resolve("malloc"), Some(malloc));
+    assert_eq!(from_memory.resolve("malloc"), Some(malloc));
+    assert_eq!(from_memory.resolve("free"), Some(libc::free as *const () as usize));
+
+    let symbolizer = Symbolizer::new(&mem.process, &mem);
+    assert_eq!(symbolizer.resolve(&format!("{}!malloc", libc_module.name)), Some(malloc));
+    assert_eq!(symbolizer.resolve("malloc"), Some(malloc));
+
+    let name = symbolizer.symbolize(malloc + 0x12);
+    let (module, rest) = name.split_once('!').unwrap();
+    assert_eq!(module, libc_module.name);
+    let (symbol, offset) = rest.split_once('+').unwrap();
+    assert_eq!(offset, "0x12");
+    assert_eq!(symbolizer.resolve(symbol), Some(malloc));
+}
+
+#[test]
+fn fall_back_to_module_offset() {
+    let mem = own_process();
+    let symbolizer = Symbolizer::new(&mem.process, &mem);
+    let heap = mem.process.maps.iter().find(|m| m.pathname == "[heap]").map(|m| m.address.0);
+    if let Some(heap) = heap {
+        assert_eq!(symbolizer.symbolize(heap), format!("{:#x}", heap));
+    }
+}
+
+fn file_module(path: &std::path::Path) -> mempoll::process::Module {
+    let line = format!("7f0030000000-7f0030001000 r--p 00000000 08:01 0 {}\n", path.display());
+    let maps = mempoll::process::parse_maps(&line).unwrap();
+    mempoll::process::module::modules_from_maps(&maps).remove(0)
+}
+
+#[test]
+fn reject_bad_files() {
+    let dir = std::env::temp_dir();
+
+    // 稀疏的大文件不是 ELF，只读文件头就放弃
+    let big = dir.join(format!("mempoll-big-{}.pak", std::process::id()));
+    std::fs::File::create(&big).unwrap().set_len(8 << 30).unwrap();
+    assert!(SymbolTable::from_file(&file_module(&big)).is_err());
+    std::fs::remove_file(&big).unwrap();
+
+    // 节头偏移溢出、截断的节返回错误而不是 panic
+    let mem = own_process();
+    let libc_module = mem.process.module_at(libc::malloc as *const () as usize).unwrap();
+    let data = std::fs::read(&libc_module.path).unwrap();
+    let bad = dir.join(format!("mempoll-bad-{}.so", std::process::id()));
+    let mut overflow = data.clone();
+    overflow[0x28..0x30].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+    std::fs::write(&bad, &overflow).unwrap();
+    assert!(SymbolTable::from_file(&file_module(&bad)).is_err());
+    std::fs::write(&bad, &data[..data.len() / 2]).unwrap();
+    assert!(SymbolTable::from_file(&file_module(&bad)).is_err());
+    std::fs::remove_file(&bad).unwrap();
+}
+
+#[test]
+fn symbolize_covering_symbol() {
+    let module = file_module(std::path::Path::new("/tmp/mempoll-fake.so"));
+    let sym = |name: &str, address: usize, size: usize| Symbol {
+        name: name.to_string(),
+        address: module.base + address,
+        size,
+        kind: SymbolKind::Func,
+    };
+    let table = SymbolTable::new(&module, vec![
+        sym("outer", 0x100, 0x100),
+        sym("label", 0x140, 0),
+        sym("short", 0x150, 0x10),
+        sym("tail", 0x300, 0),
+    ]).unwrap();
+    let name = |address: usize| table.symbolize(module.base + address).map(|(s, off)| (s.name.as_str(), off));
+
+    // 局部标号大小为 0，取外层的函数
+    assert_eq!(name(0x140), Some(("outer", 0x40)));
+    assert_eq!(name(0x148), Some(("outer", 0x48)));
+    assert_eq!(name(0x154), Some(("short", 0x4)));
+    // short 已经结束，外层函数仍然覆盖
+    assert_eq!(name(0x170), Some(("outer", 0x70)));
+    // 没有符号覆盖
+    assert_eq!(name(0x80), None);
+    assert_eq!(name(0x200), None);
+    assert_eq!(name(0x300), None);
+}
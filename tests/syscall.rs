@@ -0,0 +1,80 @@
+mod common;
+
+use std::time::Duration;
+use common::{Target, MAGIC};
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::memory::{MemoryReader, MemoryWriter};
+use mempoll::process::Process;
+use nix::libc;
+
+fn find_map(pid: u32, address: usize) -> Option<mempoll::process::MapRange> {
+    Process::new(pid).read_maps().unwrap().into_iter().find(|m| m.address.0 <= address && address < m.address.1)
+}
+
+#[test]
+fn remote_getpid() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+    assert_eq!(mem.syscall(libc::SYS_getpid, &[]).unwrap(), target.pid as i64);
+    assert_eq!(mem.syscall(libc::SYS_close, &[u64::MAX]).unwrap(), -libc::EBADF as i64);
+    assert!(mem.syscall(libc::SYS_getpid, &[0; 7]).is_err());
+
+    // 目标状态不受影响
+    assert_eq!(mem.state(target.pid), Some(TraceState::Running));
+    assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+}
+
+#[test]
+fn remote_mmap_mprotect_munmap() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+
+    let address = mem.remote_mmap(0, 0x2000, libc::PROT_READ | libc::PROT_WRITE).unwrap();
+    let map = find_map(target.pid, address).unwrap();
+    assert_eq!(map.address, (address, address + 0x2000));
+    assert!(map.writable() && !map.executable());
+
+    mem.write(address + 0x1ff8, &0x1122334455667788u64).unwrap();
+    assert_eq!(mem.read::<u64>(address + 0x1ff8).unwrap(), 0x1122334455667788);
+
+    mem.remote_mprotect(address, 0x1000, libc::PROT_READ).unwrap();
+    assert!(!find_map(target.pid, address).unwrap().writable());
+    assert!(find_map(target.pid, address + 0x1000).unwrap().writable());
+
+    mem.remote_munmap(address, 0x2000).unwrap();
+    assert!(find_map(target.pid, address).is_none());
+
+    // 失败时返回 errno
+    let err = mem.remote_munmap(address + 1, 0x1000).unwrap_err();
+    assert!(format!("{:?}", err).contains("EINVAL"), "{:?}", err);
+}
+
+#[test]
+fn inject_into_sleeping_syscall() {
+    // 子进程大部分时间停在 usleep 里，注入后原来的系统调用要能正常重启
+    let target = Target::spawn_busy();
+    let mem = PtraceMemory::new(target.pid);
+    for _ in 0..20 {
+        assert_eq!(mem.syscall(libc::SYS_getpid, &[]).unwrap(), target.pid as i64);
+    }
+    let counter = mem.read::<u32>(target.known + 8).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(mem.read::<u32>(target.known + 8).unwrap() > counter);
+}
+
+#[test]
+fn syscall_with_other_threads() {
+    let target = Target::spawn_threads(2);
+    let mem = PtraceMemory::new(target.pid);
+
+    // 注入期间其它线程也停住，之后全部恢复
+    assert_eq!(mem.syscall(libc::SYS_getpid, &[]).unwrap(), target.pid as i64);
+    assert_eq!(mem.attached_threads().len(), 3);
+    assert!(mem.thread_states().iter().all(|(_, s)| *s == TraceState::Running));
+
+    // 批量操作中注入不会恢复目标
+    mem.stop_world().unwrap();
+    assert_eq!(mem.syscall(libc::SYS_gettid, &[]).unwrap(), target.pid as i64);
+    assert!(mem.thread_states().iter().all(|(_, s)| *s == TraceState::Stopped { signal: None }));
+    mem.resume().unwrap();
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::sync::mpsc;
+use common::Target;
+use mempoll::memory::ptrace_memory::PtraceMemory;
+use mempoll::process::Process;
+use mempoll::registers::GeneralRegisters;
+use mempoll::process::thread::ThreadState;
+
+#[test]
+fn list_own_threads() {
+    let (tx, rx) = mpsc::channel::<()>();
+    let (ready_tx, ready_rx) = mpsc::channel::<()>();
+    let worker = std::thread::Builder::new()
+        .name("mempoll-worker".to_string())
+        .spawn(move || {
+            ready_tx.send(()).unwrap();
+            rx.recv().unwrap();
+        })
+        .unwrap();
+    ready_rx.recv().unwrap();
+
+    let mut process = Process::new(std::process::id());
+    process.maps().unwrap();
+    // 等 worker 阻塞在 recv 里，/proc/.../syscall 才有栈指针
+    let mut threads = process.threads().unwrap();
+    for _ in 0..100 {
+        if threads.iter().any(|t| t.name == "mempoll-worker" && t.state == ThreadState::Sleeping) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        threads = process.threads().unwrap();
+    }
+
+    let main = threads.iter().find(|t| t.is_main(process.pid)).unwrap();
+    assert_eq!(main.stack.as_ref().unwrap().pathname, "[stack]");
+
+    let worker_info = threads.iter().find(|t| t.name == "mempoll-worker").unwrap();
+    assert_ne!(worker_info.state, ThreadState::Zombie);
+    assert!(worker_info.stack.is_some());
+
+    tx.send(()).unwrap();
+    worker.join().unwrap();
+}
+
+#[test]
+fn attach_all_threads() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+    assert!(mem.attach_threads().unwrap().is_empty());
+    assert_eq!(mem.attached_threads(), vec![target.pid]);
+
+    let status = mem.thread_status().unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].tid, target.pid);
+    mem.dettach().unwrap();
+    assert!(mem.attached_threads().is_empty());
+}
+
+#[test]
+fn attach_cloned_threads() {
+    let target = Target::spawn_threads(3);
+    let tids = Process::new(target.pid).tids().unwrap();
+    assert_eq!(tids.len(), 4);
+
+    let mem = PtraceMemory::new(target.pid);
+    let mut attached = mem.attach_threads().unwrap();
+    attached.sort();
+    assert_eq!(attached, tids.iter().copied().filter(|t| *t != target.pid).collect::<Vec<_>>());
+    let mut all = mem.attached_threads();
+    assert_eq!(all[0], target.pid);
+    all.sort();
+    assert_eq!(all, tids);
+    assert!(mem.attach_threads().unwrap().is_empty());
+
+    // 每个线程有自己的栈
+    let status = mem.thread_status().unwrap();
+    assert_eq!(status.len(), 4);
+    let mut sps = status.iter().map(|s| s.regs.sp()).collect::<Vec<_>>();
+    sps.sort();
+    sps.dedup();
+    assert_eq!(sps.len(), 4);
+}
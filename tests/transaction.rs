@@ -0,0 +1,143 @@
+mod common;
+
+use std::cell::Cell;
+use std::time::Duration;
+use common::{Target, MAGIC};
+use mempoll::memory::mock_memory::MockMemory;
+use mempoll::memory::process_vm_memory::ProcessVmMemory;
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::memory::{MemoryError, MemoryReader, MemoryWriter, Pod};
+use mempoll::transaction::{Pause, Transaction, TransactionError};
+
+/// 写入预算用完后所有写入都失败；swallow 处的写入返回成功但不生效
+struct Faulty {
+    inner: MockMemory,
+    budget: Cell<usize>,
+    swallow: Option<usize>,
+}
+
+impl Faulty {
+    fn new() -> Self {
+        let mut inner = MockMemory::new();
+        inner.add_rw(0x1000, vec![0; 0x100]);
+        Faulty { inner, budget: Cell::new(usize::MAX), swallow: None }
+    }
+}
+
+impl MemoryReader for Faulty {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        self.inner.read(address)
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        self.inner.readbuf(address, buf)
+    }
+}
+
+impl MemoryWriter for Faulty {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        self.write_exact(address, bytemuck::bytes_of(value))
+    }
+
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        if self.budget.get() == 0 {
+            return Err(MemoryError::MockWriteError("Out of budget".to_string()));
+        }
+        self.budget.set(self.budget.get() - 1);
+        if self.swallow.is_some_and(|a| address <= a && a < address + buf.len()) {
+            return Ok(buf.len());
+        }
+        self.inner.writebuf(address, buf)
+    }
+}
+
+#[test]
+fn commit_all() {
+    let mem = Faulty::new();
+    let mut tx = Transaction::new(&mem);
+    tx.write(0x1000, &0x11223344u32).write(0x1010, &1.5f64);
+    // 重叠的写入以后面的为准
+    tx.write(0x1001, &0xffu8);
+    assert_eq!(tx.len(), 3);
+    tx.commit().unwrap();
+    assert_eq!(mem.read::<u32>(0x1000).unwrap(), 0x1122ff44);
+    assert_eq!(mem.read::<f64>(0x1010).unwrap(), 1.5);
+}
+
+#[test]
+fn failed_write_rolls_back() {
+    let mut mem = Faulty::new();
+    mem.inner.add_region(0x2000, vec![0; 0x10], mempoll::process::permissions::READABLE, "");
+    mem.inner.write(0x1000, &7u32).unwrap();
+
+    // 读不到原始值时什么都不写
+    let mut tx = Transaction::new(&mem);
+    tx.write(0x1000, &1u32).write(0x10fe, &3u32);
+    assert!(matches!(tx.commit(), Err(TransactionError::SaveError(_))));
+    assert_eq!(mem.read::<u32>(0x1000).unwrap(), 7);
+
+    // 只读区域写入失败，之前的写入被恢复
+    let mut tx = Transaction::new(&mem);
+    tx.write(0x1000, &1u32).write(0x1004, &2u32).write(0x2000, &3u32);
+    let err = tx.commit().unwrap_err();
+    assert!(matches!(err, TransactionError::WriteError(_)), "{err}");
+    assert_eq!(mem.read::<u64>(0x1000).unwrap(), 7);
+
+    // 第二个写入失败，回滚时也写不进去
+    let mut tx = Transaction::new(&mem);
+    tx.write(0x1000, &1u32).write(0x1004, &2u32).write(0x1008, &3u32);
+    mem.budget.set(1);
+    let err = tx.commit().unwrap_err();
+    assert!(matches!(err, TransactionError::RollbackError { ref failed, .. } if *failed == vec![0x1000]), "{err}");
+    assert_eq!(mem.read::<u32>(0x1000).unwrap(), 1);
+}
+
+#[test]
+fn verify_failure_rolls_back() {
+    let mut mem = Faulty::new();
+    mem.swallow = Some(0x1020);
+    let mut tx = Transaction::new(&mem);
+    tx.write(0x1000, &1u64).write(0x1020, &2u32);
+    match tx.commit() {
+        Err(TransactionError::VerifyError { address, expected, found }) => {
+            assert_eq!(address, 0x1020);
+            assert_eq!(expected, 2u32.to_ne_bytes());
+            assert_eq!(found, vec![0; 4]);
+        },
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(mem.read::<u64>(0x1000).unwrap(), 0);
+}
+
+#[test]
+fn pause_live_target() {
+    let target = Target::spawn_busy();
+    let mem = ProcessVmMemory::new(target.pid);
+    let speed = target.known + 12;
+    let bytes = target.known + 16;
+
+    let mut tx = Transaction::new(&mem).pause(Pause::Signal(target.pid));
+    tx.write(speed, &99.5f32).write(bytes, b"transaction-test");
+    tx.commit().unwrap();
+    assert_eq!(mem.read::<f32>(speed).unwrap(), 99.5);
+    assert_eq!(&mem.read::<[u8; 16]>(bytes).unwrap(), b"transaction-test");
+    assert_eq!(mem.read::<u64>(target.known).unwrap(), MAGIC);
+
+    // 提交后目标继续运行
+    let counter = mem.read::<u32>(target.known + 8).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(mem.read::<u32>(target.known + 8).unwrap() > counter);
+
+    let ptrace = PtraceMemory::new(target.pid);
+    let mut tx = Transaction::new(&mem).pause(Pause::Ptrace(&ptrace));
+    tx.write(speed, &1.0f32);
+    tx.commit().unwrap();
+    assert_eq!(mem.read::<f32>(speed).unwrap(), 1.0);
+    assert!(ptrace.thread_states().iter().all(|(_, s)| *s == TraceState::Running));
+
+    // 调用者已经停住目标时，提交后保持停止
+    ptrace.stop_world().unwrap();
+    tx.commit().unwrap();
+    assert!(ptrace.thread_states().iter().all(|(_, s)| *s == TraceState::Stopped { signal: None }));
+    ptrace.resume().unwrap();
+}
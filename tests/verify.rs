@@ -0,0 +1,122 @@
+mod common;
+
+use std::cell::Cell;
+use std::time::Duration;
+use common::Target;
+use mempoll::memory::mock_memory::MockMemory;
+use mempoll::memory::process_vm_memory::ProcessVmMemory;
+use mempoll::memory::verify_memory::{mismatches, Mismatch, VerifyMemory, VerifyMode};
+use mempoll::memory::{MemoryError, MemoryReader, MemoryWriter, Pod};
+
+/// 模拟目标：swallow 处的写入不生效，前 resets 次写入之后立刻被改回 0
+struct Fake {
+    inner: MockMemory,
+    swallow: Option<usize>,
+    resets: Cell<usize>,
+}
+
+impl Fake {
+    fn new() -> Self {
+        let mut inner = MockMemory::new();
+        inner.add_rw(0x1000, vec![0; 0x100]);
+        Fake { inner, swallow: None, resets: Cell::new(0) }
+    }
+}
+
+impl MemoryReader for Fake {
+    fn read<T: Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        self.inner.read(address)
+    }
+
+    fn readbuf(&self, address: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        self.inner.readbuf(address, buf)
+    }
+}
+
+impl MemoryWriter for Fake {
+    fn write<T: Pod>(&self, address: usize, value: &T) -> Result<(), MemoryError> {
+        self.write_exact(address, bytemuck::bytes_of(value))
+    }
+
+    fn writebuf(&self, address: usize, buf: &[u8]) -> Result<usize, MemoryError> {
+        if self.swallow.is_some_and(|a| address <= a && a < address + buf.len()) {
+            return Ok(buf.len());
+        }
+        if self.resets.get() > 0 {
+            self.resets.set(self.resets.get() - 1);
+            return self.inner.writebuf(address, &vec![0; buf.len()]);
+        }
+        self.inner.writebuf(address, buf)
+    }
+}
+
+const STABLE: VerifyMode = VerifyMode::UntilStable { retries: 3, stable: 2, interval: Duration::from_millis(1) };
+
+#[test]
+fn find_mismatches() {
+    let diff = mismatches(0x100, &[1, 2, 3, 4, 5, 6], &[1, 0, 0, 4, 5, 0]);
+    assert_eq!(diff, vec![
+        Mismatch { address: 0x101, expected: vec![2, 3], found: vec![0, 0] },
+        Mismatch { address: 0x105, expected: vec![6], found: vec![0] },
+    ]);
+    assert!(mismatches(0, &[1, 2], &[1, 2]).is_empty());
+}
+
+#[test]
+fn swallowed_write() {
+    let mut target = Fake::new();
+    target.swallow = Some(0x1012);
+
+    // 不校验时相信返回值
+    let mem = VerifyMemory::new(&target, VerifyMode::Off);
+    mem.write(0x1010, &0xAABBCCDDu32).unwrap();
+
+    let mem = VerifyMemory::new(&target, VerifyMode::Once);
+    mem.write(0x1020, &1u32).unwrap();
+    let err = mem.write(0x1010, &0xAABBCCDDu32).unwrap_err();
+    assert!(matches!(err, MemoryError::VerifyError(_)));
+
+    let failures = mem.take_failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!((failures[0].address, failures[0].len, failures[0].attempts), (0x1010, 4, 1));
+    assert_eq!(failures[0].mismatches, vec![Mismatch { address: 0x1010, expected: 0xAABBCCDDu32.to_ne_bytes().to_vec(), found: vec![0; 4] }]);
+    assert!(mem.take_failures().is_empty());
+}
+
+#[test]
+fn retry_until_stable() {
+    let target = Fake::new();
+    let mut mem = VerifyMemory::new(&target, VerifyMode::Once);
+
+    target.resets.set(1);
+    assert!(mem.write(0x1000, &7u64).is_err());
+
+    // 前两次被改回，第三次成功
+    mem.set_mode(STABLE);
+    target.resets.set(2);
+    let report = mem.write_verified(0x1000, &7u64.to_ne_bytes()).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.attempts, 3);
+    assert_eq!(mem.read::<u64>(0x1000).unwrap(), 7);
+
+    target.resets.set(10);
+    let report = mem.write_verified(0x1000, &8u64.to_ne_bytes()).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.attempts, 4);
+}
+
+#[test]
+fn live_target_resets_value() {
+    let target = Target::spawn_busy();
+    let vm = ProcessVmMemory::new(target.pid);
+    let mode = VerifyMode::UntilStable { retries: 2, stable: 3, interval: Duration::from_millis(5) };
+    let mem = VerifyMemory::new(&vm, mode);
+
+    // 目标每毫秒改一次 counter，稳定不下来
+    let report = mem.write_verified(target.known + 8, &0u32.to_ne_bytes()).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.attempts, 3);
+
+    mem.write(target.known + 12, &2.5f32).unwrap();
+    assert_eq!(mem.read::<f32>(target.known + 12).unwrap(), 2.5);
+}
@@ -0,0 +1,91 @@
+#![cfg(target_arch = "x86_64")]
+
+mod common;
+
+use std::time::Duration;
+use common::{bump, Target};
+use mempoll::memory::ptrace_memory::{PtraceMemory, TraceState};
+use mempoll::watchpoint::{WatchKind, Watchpoints};
+
+#[test]
+fn find_what_writes() {
+    let target = Target::spawn_busy();
+    let mut mem = PtraceMemory::new(target.pid);
+    mem.process.maps().unwrap();
+    let counter = target.known + 8;
+
+    let mut watch = Watchpoints::new(&mem);
+    let slot = watch.add(counter, 4, WatchKind::Write).unwrap();
+    assert_eq!(watch.debug_control(target.pid).unwrap() & 0b11, 0b01);
+
+    let hits = watch.wait(Duration::from_secs(5), 3).unwrap();
+    assert_eq!(hits.len(), 3);
+    let bump_addr = bump as *const () as usize;
+    for hit in hits.iter() {
+        assert_eq!(hit.tid, target.pid);
+        assert_eq!(hit.slot, slot);
+        assert_eq!(hit.watchpoint.address, counter);
+        // 写入指令在 bump 里面，pc 指向它的下一条
+        assert!(hit.pc > bump_addr && hit.pc < bump_addr + 0x100, "pc {:#x} bump {bump_addr:#x}", hit.pc);
+        assert_eq!(hit.regs.rip as usize, hit.pc);
+        assert_eq!(hit.fault_pc, None);
+    }
+    assert!(mem.thread_states().iter().all(|(_, s)| *s == TraceState::Running));
+
+    watch.remove(slot).unwrap();
+    assert_eq!(watch.debug_control(target.pid).unwrap(), 0);
+    assert!(watch.wait(Duration::from_millis(50), 1).unwrap().is_empty());
+}
+
+#[test]
+fn execute_watchpoint() {
+    let target = Target::spawn_busy();
+    let mem = PtraceMemory::new(target.pid);
+    let bump_addr = bump as *const () as usize;
+
+    {
+        let mut watch = Watchpoints::new(&mem);
+        watch.add(bump_addr, 1, WatchKind::Execute).unwrap();
+        // 恢复后能继续执行并再次命中
+        let hits = watch.wait(Duration::from_secs(5), 2).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|h| h.pc == bump_addr && h.fault_pc == Some(bump_addr)));
+    }
+
+    // drop 后断点被清除
+    let watch = Watchpoints::new(&mem);
+    assert_eq!(watch.debug_control(target.pid).unwrap(), 0);
+}
+
+#[test]
+fn invalid_watchpoints() {
+    let target = Target::spawn();
+    let mem = PtraceMemory::new(target.pid);
+    let mut watch = Watchpoints::new(&mem);
+    assert!(watch.add(target.known + 1, 4, WatchKind::Write).is_err());
+    assert!(watch.add(target.known, 3, WatchKind::Write).is_err());
+    assert!(watch.add(target.known, 8, WatchKind::Execute).is_err());
+    for i in 0..4 {
+        assert_eq!(watch.add(target.known + i * 8, 8, WatchKind::ReadWrite).unwrap(), i);
+    }
+    assert!(watch.add(target.known, 1, WatchKind::Write).is_err());
+    watch.clear().unwrap();
+    assert!(watch.slots().iter().all(|s| s.is_none()));
+}
+
+
+#[test]
+fn watch_threads_created_later() {
+    let target = Target::spawn_late_busy_thread();
+    let mem = PtraceMemory::new(target.pid);
+    let mut watch = Watchpoints::new(&mem);
+    watch.add(target.known + 8, 4, WatchKind::Write).unwrap();
+    assert_eq!(mem.attached_threads(), vec![target.pid]);
+
+    // 写入来自 add 之后才创建的线程
+    let hits = watch.wait(common::LATE_THREAD_DELAY + Duration::from_secs(5), 2).unwrap();
+    assert_eq!(hits.len(), 2);
+    let tids = mem.attached_threads();
+    assert_eq!(tids.len(), 2);
+    assert!(hits.iter().all(|h| h.tid == tids[1]));
+}